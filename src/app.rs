@@ -1,18 +1,62 @@
 use {
     std::{
-        thread,
-        time::Duration,
+        env,
+        fs,
+        io,
+        path::PathBuf,
+        time::{
+            Duration,
+            SystemTime,
+            UNIX_EPOCH,
+        },
     },
     sdl2::{
-        event::Event,
+        event::{
+            Event,
+            WindowEvent,
+        },
         keyboard::Keycode,
         messagebox::*,
         mouse::MouseButton,
         pixels::Color,
+        render::WindowCanvas,
     },
     crate::{
-        board::Board,
-        sdl::SdlContext,
+        board::{
+            Board,
+            Camera,
+            Replay,
+            Theme,
+            ViewAngle,
+            Viewer,
+            TileTextures,
+            Tray,
+            Versus,
+            Zen,
+            REPLAY_DIR,
+        },
+        game::{
+            Game,
+            GameOver,
+        },
+        leaderboard::{
+            self,
+            HttpBackend,
+            Leaderboard,
+            Reply,
+            Response,
+            Worker,
+        },
+        memory::Memory,
+        race::{
+            Race,
+            Role,
+        },
+        sdl::{
+            self,
+            SdlContext,
+        },
+        shisen::Shisen,
         ui::{
             Action,
             UiContext
@@ -20,23 +64,96 @@ use {
     },
 };
 
-pub fn run(sdl: &mut SdlContext) {
-    let mut board = Board::new(&sdl.texture_creator);
+/// The leaderboard games are sent to, unless `MAHJONG_LEADERBOARD_URL` names another.
+static LEADERBOARD_URL: &str = "http://127.0.0.1:8787/";
+
+/// Where games wait that couldn't be sent to the leaderboard yet.
+static LEADERBOARD_QUEUE_PATH: &str = "leaderboard-queue.json";
+
+/// How much one step of the mouse wheel or one press of `+` zooms in.
+static ZOOM_STEP: f64 = 1.25;
+
+/// How long the window waits for something to happen while nothing moves, before the game is
+/// updated and drawn anyway for its timers.
+static TICK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long a frame waits for something to happen while something moves.
+static FRAME_INTERVAL: Duration = Duration::from_millis(10);
+
+pub fn run(sdl: &mut SdlContext, role: Option<Role>, replay: Option<PathBuf>) {
+    let themes = Theme::presets();
+    let mut theme_index = 0;
+    let max_texture_size = sdl::max_texture_size(&sdl.canvas);
+    let mut textures =
+        TileTextures::new(&sdl.texture_creator, &themes[theme_index], max_texture_size);
     let mut ui = UiContext::new(&sdl.texture_creator);
-    
+
+    let url = env::var("MAHJONG_LEADERBOARD_URL").unwrap_or_else(|_| LEADERBOARD_URL.to_string());
+    let backend = HttpBackend::new(&url).unwrap_or_else(|err| {
+        eprintln!("{}, sending games to {} instead", err, LEADERBOARD_URL);
+        HttpBackend::new(LEADERBOARD_URL).expect("the default leaderboard url is valid")
+    });
+    let queue_path = Some(PathBuf::from(LEADERBOARD_QUEUE_PATH));
+    let leaderboard = Worker::spawn(Leaderboard::new(backend, queue_path));
+
+    let mut games: Vec<Box<dyn Game>> = vec![
+        Box::new(Board::new()),
+        Box::new(Shisen::new()),
+        Box::new(Memory::new()),
+        Box::new(Tray::new()),
+        Box::new(Zen::new()),
+        Box::new(Versus::new()),
+    ];
+    let mut game_index = 0;
+
+    // a replay given on the command line is shown right away
+    let mut viewer = Viewer::new();
+    if let Some(path) = replay {
+        viewer.open(&path);
+        game_index = games.len();
+    }
+    games.push(Box::new(viewer));
+
+    // a race given on the command line starts right away
+    let mut race = Race::new();
+    if let Some(role) = role {
+        race.connect(&role);
+        game_index = games.len();
+    }
+    games.push(Box::new(race));
+    let mut title = String::new();
+
+    let (mut width, mut height) = sdl::canvas_size(&sdl.canvas);
+    let mut pointer_scale = sdl::pointer_scale(&sdl.canvas);
+    ui.resize(width, height);
+    for game in &mut games {
+        game.resize(width, height);
+    }
+
     let mut running = true;
-    let mut game_over = false;
+    let mut game_over = None;
 
     let mut mouse_x = 0;
     let mut mouse_y = 0;
+    let mut pointer = (0, 0);
+    let mut view_angle = ViewAngle::default();
 
     while running {
-        for event in sdl.event_pump.poll_iter() {
+        // the game is only drawn when something happened, something moves or a tick changed it
+        let animating = games[game_index].is_animating();
+        let timeout = if animating { FRAME_INTERVAL } else { TICK_INTERVAL };
+        let mut redraw = animating;
+        let event = sdl.event_pump.wait_event_timeout(timeout.as_millis() as u32);
+        for event in event.into_iter().chain(sdl.event_pump.poll_iter()) {
+            redraw = true;
+            let event = sdl::to_canvas(event, pointer_scale);
+            let game = &mut games[game_index];
+
             let mut done = true;
             match ui.handle_event(&event) {
-                Some(Action::Start) => board.reset(),
-                Some(Action::Undo) => board.undo(),
-                Some(Action::Hint) => board.highlight_possible_matches(),
+                Some(Action::Start) => game.reset(),
+                Some(Action::Undo) => game.undo(),
+                Some(Action::Hint) => game.hint(),
                 _ => done = false,
             }
             if done {
@@ -47,46 +164,206 @@ pub fn run(sdl: &mut SdlContext) {
                 Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
                     running = false;
                 }
+                Event::Window { win_event: WindowEvent::SizeChanged(..), .. } => {
+                    let (new_width, new_height) = sdl::canvas_size(&sdl.canvas);
+                    width = new_width;
+                    height = new_height;
+                    pointer_scale = sdl::pointer_scale(&sdl.canvas);
+                    ui.resize(width, height);
+                    for game in &mut games {
+                        game.resize(width, height);
+                    }
+                }
                 Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
                     mouse_x = x;
                     mouse_y = y;
                 }
                 Event::MouseButtonUp { mouse_btn: MouseButton::Left, .. } => {
-                    if board.try_select_tile(mouse_x, mouse_y).is_err() {
-                        game_over = true;
+                    if let Err(result) = game.click(mouse_x, mouse_y) {
+                        game_over = Some(result);
+                    }
+                }
+                Event::MouseMotion { mousestate, x, y, xrel, yrel, .. } => {
+                    pointer = (x, y);
+                    if let (true, Some(camera)) = (mousestate.right(), game.camera()) {
+                        camera.pan(xrel, yrel);
+                    }
+                }
+                Event::MouseWheel { y, .. } => {
+                    if let Some(camera) = game.camera() {
+                        camera.zoom_at(ZOOM_STEP.powi(y), pointer.0, pointer.1);
                     }
                 }
                 Event::KeyUp { keycode: Some(Keycode::H), .. } => {
-                    board.highlight_possible_matches();
+                    game.hint();
                 }
                 Event::KeyUp { keycode: Some(Keycode::N), .. } => {
-                    board.reset();
+                    game.reset();
                 }
                 Event::KeyUp { keycode: Some(Keycode::U), .. } => {
-                    board.undo();
+                    game.undo();
+                }
+                Event::KeyUp { keycode: Some(Keycode::Tab), .. } => {
+                    view_angle = view_angle.next();
+                    for game in &mut games {
+                        game.set_view_angle(view_angle);
+                    }
+                }
+                Event::KeyUp { keycode: Some(Keycode::I), .. } => {
+                    theme_index = (theme_index + 1) % themes.len();
+                    let theme = &themes[theme_index];
+                    textures = TileTextures::new(&sdl.texture_creator, theme, max_texture_size);
+                    for game in &mut games {
+                        game.set_tile_size(theme.tile_size());
+                    }
+                }
+                Event::KeyUp { keycode: Some(Keycode::M), .. } => {
+                    game_index = (game_index + 1) % games.len();
+                }
+                Event::KeyUp { keycode: Some(keycode), .. } => {
+                    let center = (width as i32 / 2, height as i32 / 2);
+                    let used = game
+                        .camera()
+                        .is_some_and(|camera| move_camera(camera, keycode, center))
+                        || game.key(keycode);
+                    if !used && keycode == Keycode::L {
+                        leaderboard.fetch(leaderboard::LAYOUT);
+                    }
                 }
                 _ => {}
             }
         }
 
-        board.update();
-
-        sdl.canvas.set_draw_color(Color::RGB(0, 0, 0));
-        sdl.canvas.clear();
-        board.render(&mut sdl.canvas);
-        ui.render(&mut sdl.canvas);
-        sdl.canvas.present();
-
-        if game_over {
-            show_simple_message_box(
-                MessageBoxFlag::all(),
-                                    "Game Over",
-                                    "You have no possible moves left",
-                None,
-            ).ok();
-            game_over = false;
+        for reply in leaderboard.replies() {
+            match reply {
+                Reply::Submitted(result) => show_submitted(result),
+                Reply::Fetched(entries) => show_leaderboard(entries),
+            }
+        }
+
+        let game = &mut games[game_index];
+        match game.update() {
+            Ok(changed) => redraw |= changed || game.is_animating(),
+            Err(result) => {
+                game_over = Some(result);
+                redraw = true;
+            }
+        }
+
+        let game_title = match theme_index {
+            0 => game.title(),
+            _ => format!("{} - {} theme", game.title(), themes[theme_index].name()),
+        };
+        if game_title != title {
+            set_title(&mut sdl.canvas, &game_title);
+            title = game_title;
+        }
+
+        if redraw {
+            sdl.canvas.set_draw_color(Color::RGB(0, 0, 0));
+            sdl.canvas.clear();
+            game.render(&mut sdl.canvas, &textures);
+            ui.render(&mut sdl.canvas);
+            sdl.canvas.present();
+        }
+
+        if let Some(result) = game_over.take() {
+            let mut notes = game.summary().into_iter().collect::<Vec<_>>();
+            if let Some(replay) = game.replay() {
+                notes.push(match save_replay(&replay) {
+                    Ok(path) => format!("The replay was saved to {}.", path.display()),
+                    Err(_) => "The replay couldn't be saved.".to_string(),
+                });
+            }
+            if let Some(submission) = game.submission() {
+                leaderboard.submit(submission);
+                notes.push("Your game is being sent to the leaderboard.".to_string());
+            }
+            let summary = if notes.is_empty() { None } else { Some(notes.join("\n\n")) };
+            show_game_over(result, summary);
         }
+    }
+}
+
+/// Zooms around the center of the canvas with `+` and `-`, and shows the whole board with `0`.
+fn move_camera(camera: &mut Camera, keycode: Keycode, (x, y): (i32, i32)) -> bool {
+    match keycode {
+        Keycode::Plus | Keycode::Equals | Keycode::KpPlus => camera.zoom_at(ZOOM_STEP, x, y),
+        Keycode::Minus | Keycode::KpMinus => camera.zoom_at(1.0 / ZOOM_STEP, x, y),
+        Keycode::Num0 | Keycode::Kp0 => camera.reset(),
+        _ => return false,
+    }
+    true
+}
+
+/// Saves the replay into the replay directory, named after the time and the seed of the game.
+fn save_replay(replay: &Replay) -> io::Result<PathBuf> {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+    let path = PathBuf::from(REPLAY_DIR).join(format!("{}-{}.replay", secs, replay.seed));
+
+    fs::create_dir_all(REPLAY_DIR)?;
+    replay.save(&path)?;
+    Ok(path)
+}
+
+fn set_title(canvas: &mut WindowCanvas, game_title: &str) {
+    let title = format!("Mahjong - {}", game_title);
+    canvas.window_mut().set_title(&title).ok();
+}
 
-        thread::sleep(Duration::from_millis(10));
+fn show_game_over(result: GameOver, summary: Option<String>) {
+    let mut message = result.message();
+    if let Some(summary) = summary {
+        message = format!("{}\n\n{}", message, summary);
     }
+
+    show_simple_message_box(
+        MessageBoxFlag::all(),
+        "Game Over",
+        &message,
+        None,
+    ).ok();
+}
+
+/// Lets the player know when a game didn't make it onto the leaderboard.
+fn show_submitted(result: Result<Response, &'static str>) {
+    let message = match result {
+        Ok(Response::Accepted) => return,
+        Ok(Response::Rejected) => "The leaderboard rejected your game.".to_string(),
+        Err(err) => format!("Your game will be sent to the leaderboard later: {}.", err),
+    };
+
+    show_simple_message_box(
+        MessageBoxFlag::all(),
+        "Leaderboard",
+        &message,
+        None,
+    ).ok();
+}
+
+fn show_leaderboard(entries: Result<Vec<leaderboard::Entry>, &'static str>) {
+    let message = match entries {
+        Ok(ref entries) if entries.is_empty() => "No games on the leaderboard yet".to_string(),
+        Ok(entries) => entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                format!(
+                    "{}. {} points in {}s - {} rules",
+                    index + 1,
+                    entry.score,
+                    entry.time_millis / 1000,
+                    entry.rules,
+                )
+            }).collect::<Vec<_>>()
+            .join("\n"),
+        Err(err) => format!("The leaderboard can't be shown: {}", err),
+    };
+
+    show_simple_message_box(
+        MessageBoxFlag::all(),
+        "Leaderboard",
+        &message,
+        None,
+    ).ok();
 }
@@ -0,0 +1,90 @@
+use {
+    sdl2::{
+        image::ImageRWops,
+        render::{
+            Texture,
+            TextureCreator,
+        },
+        rwops::RWops,
+        surface::Surface,
+        video::WindowContext,
+    },
+    crate::board::TileType,
+};
+
+// the images of the default theme and the buttons are built into the executable, so it runs from
+// any directory
+pub static START_BUTTON: &[u8] = include_bytes!("../img/start.png");
+pub static UNDO_BUTTON: &[u8] = include_bytes!("../img/undo.png");
+pub static HINT_BUTTON: &[u8] = include_bytes!("../img/hint.png");
+pub static TILE_SIDE: &[u8] = include_bytes!("../img/TileSide.png");
+pub static TILE_BOTTOM: &[u8] = include_bytes!("../img/TileBottom.png");
+
+/// Returns the image of the face of the tile type in the default theme.
+pub fn tile_face(tile_type: TileType) -> &'static [u8] {
+    use crate::board::TileType::*;
+
+    match tile_type {
+        BallOne => include_bytes!("../img/BallOne.png"),
+        BallTwo => include_bytes!("../img/BallTwo.png"),
+        BallThree => include_bytes!("../img/BallThree.png"),
+        BallFour => include_bytes!("../img/BallFour.png"),
+        BallFive => include_bytes!("../img/BallFive.png"),
+        BallSix => include_bytes!("../img/BallSix.png"),
+        BallSeven => include_bytes!("../img/BallSeven.png"),
+        BallEight => include_bytes!("../img/BallEight.png"),
+        BallNine => include_bytes!("../img/BallNine.png"),
+        BambooOne => include_bytes!("../img/BambooOne.png"),
+        BambooTwo => include_bytes!("../img/BambooTwo.png"),
+        BambooThree => include_bytes!("../img/BambooThree.png"),
+        BambooFour => include_bytes!("../img/BambooFour.png"),
+        BambooFive => include_bytes!("../img/BambooFive.png"),
+        BambooSix => include_bytes!("../img/BambooSix.png"),
+        BambooSeven => include_bytes!("../img/BambooSeven.png"),
+        BambooEight => include_bytes!("../img/BambooEight.png"),
+        BambooNine => include_bytes!("../img/BambooNine.png"),
+        CharacterOne => include_bytes!("../img/CharacterOne.png"),
+        CharacterTwo => include_bytes!("../img/CharacterTwo.png"),
+        CharacterThree => include_bytes!("../img/CharacterThree.png"),
+        CharacterFour => include_bytes!("../img/CharacterFour.png"),
+        CharacterFive => include_bytes!("../img/CharacterFive.png"),
+        CharacterSix => include_bytes!("../img/CharacterSix.png"),
+        CharacterSeven => include_bytes!("../img/CharacterSeven.png"),
+        CharacterEight => include_bytes!("../img/CharacterEight.png"),
+        CharacterNine => include_bytes!("../img/CharacterNine.png"),
+        WindNorth => include_bytes!("../img/WindNorth.png"),
+        WindEast => include_bytes!("../img/WindEast.png"),
+        WindSouth => include_bytes!("../img/WindSouth.png"),
+        WindWest => include_bytes!("../img/WindWest.png"),
+        DragonRed => include_bytes!("../img/DragonRed.png"),
+        DragonGreen => include_bytes!("../img/DragonGreen.png"),
+        DragonWhite => include_bytes!("../img/DragonWhite.png"),
+        FlowerPlum => include_bytes!("../img/FlowerPlum.png"),
+        FlowerOrchid => include_bytes!("../img/FlowerOrchid.png"),
+        FlowerBamboo => include_bytes!("../img/FlowerBamboo.png"),
+        FlowerChrysanthemum => include_bytes!("../img/FlowerChrysanthemum.png"),
+        SeasonSpring => include_bytes!("../img/SeasonSpring.png"),
+        SeasonSummer => include_bytes!("../img/SeasonSummer.png"),
+        SeasonAutumn => include_bytes!("../img/SeasonAutumn.png"),
+        SeasonWinter => include_bytes!("../img/SeasonWinter.png"),
+    }
+}
+
+/// Loads an image in memory.
+pub fn load_surface(image: &[u8]) -> Result<Surface<'static>, String> {
+    let rwops = RWops::from_bytes(image)?;
+    let surface = rwops.load()?;
+    // the loaded surface borrows the image, so it's copied into one that owns its pixels
+    surface.convert(&surface.pixel_format())
+}
+
+/// Loads a texture from an image in memory.
+pub fn load_texture<'tc>(
+    texture_creator: &'tc TextureCreator<WindowContext>,
+    image: &[u8],
+) -> Result<Texture<'tc>, String> {
+    let surface = load_surface(image)?;
+    texture_creator
+        .create_texture_from_surface(&surface)
+        .map_err(|err| err.to_string())
+}
@@ -1,48 +1,248 @@
+mod analysis;
+mod computer;
+mod history;
+mod replay;
 mod tiles;
+mod tray;
+mod versus;
+mod viewer;
+mod zen;
 
 use {
-    std::time::Instant,
+    std::{
+        sync::mpsc::{
+            Receiver,
+            TryRecvError,
+        },
+        time::{
+            Duration,
+            Instant,
+        },
+    },
     sdl2::{
-        render::{
-            TextureCreator,
-            WindowCanvas,
+        keyboard::Keycode,
+        pixels::Color,
+        rect::Rect,
+        render::WindowCanvas,
+    },
+    crate::{
+        game::{
+            Game,
+            GameOver,
+        },
+        leaderboard::{
+            self,
+            Submission,
         },
-        video::WindowContext,
     },
-    self::tiles::{
-        TileId,
-        Tiles
+    self::{
+        analysis::{
+            spawn_analysis,
+            Analysis,
+            Finding,
+        },
+        history::{
+            History,
+            TreeView,
+            ROOT,
+        },
+        replay::{
+            Action,
+            Step,
+        },
+        tiles::{
+            TileId,
+            Tiles,
+        },
     },
 };
 
-pub struct Board<'tc> {
-    tiles: Tiles<'tc>,
-    played: Vec<(TileId, TileId)>,
+pub use self::{
+    tiles::{
+        Camera,
+        Position,
+        RuleSet,
+        Theme,
+        TileSet,
+        TileSize,
+        TileTextures,
+        TileType,
+        ViewAngle,
+    },
+    replay::{
+        Replay,
+        REPLAY_DIR,
+    },
+    tray::Tray,
+    versus::Versus,
+    viewer::Viewer,
+    zen::Zen,
+};
+
+static LOSING_PAIR_COLOR: Color = Color { r: 230, g: 50, b: 50, a: 255 };
+
+pub struct Board {
+    tiles: Tiles,
+    seed: u64,
+    history: History,
+    show_history: bool,
+    tree_view: TreeView,
+    review: Option<Review>,
+    player: usize,
     selected_tile: Option<TileId>,
     hints: Option<Hints>,
+    start_time: Instant,
+    finish_time: Option<Duration>,
+    recording: Vec<Step>,
 }
 
-impl<'tc> Board<'tc> {
-    pub fn new(texture_creator: &'tc TextureCreator<WindowContext>) -> Self {
-        let mut positions = get_raw_positios();
-
-        let tiles = Tiles::new(&mut positions, texture_creator);
+impl Board {
+    pub fn new() -> Self {
+        Board::with_deal(RuleSet::default(), TileSet::default(), false, rand::random())
+            .expect("no deal found for the layout")
+    }
 
-        Board {
+    /// Starts a game with the deal of the given seed, dealt once the rules and tiles are set.
+    pub fn with_deal(
+        rules: RuleSet,
+        tile_set: TileSet,
+        blind: bool,
+        seed: u64,
+    ) -> Result<Self, &'static str> {
+        let mut tiles = Tiles::new(&mut get_raw_positios(), rules);
+        tiles.set_tile_set(tile_set)?;
+        tiles.set_blind(blind);
+        tiles.reset_with_seed(seed)?;
+
+        Ok(Board {
             tiles,
-            played: Vec::new(),
+            seed,
+            history: History::new(),
+            show_history: false,
+            tree_view: TreeView::default(),
+            review: None,
+            player: 0,
             selected_tile: None,
             hints: None,
-        }
+            start_time: Instant::now(),
+            finish_time: None,
+            recording: Vec::new(),
+        })
     }
 
+    /**
+     * Deals the game of the seed with the rules and the tile set, as a replay was dealt, on the
+     * same layout and camera. The current game is kept when there is no such deal.
+     */
+    pub fn redeal(
+        &mut self,
+        rules: RuleSet,
+        tile_set: TileSet,
+        blind: bool,
+        seed: u64,
+    ) -> Result<(), &'static str> {
+        let current = (self.rules(), self.tile_set().clone(), self.is_blind());
+        self.tiles.set_tile_set(tile_set)?;
+        self.tiles.set_rules(rules);
+        self.tiles.set_blind(blind);
+        self.reset_with_seed(seed).inspect_err(|_| {
+            let (rules, tile_set, blind) = current;
+            self.tiles.set_rules(rules);
+            let _ = self.tiles.set_tile_set(tile_set);
+            self.tiles.set_blind(blind);
+        })
+    }
+
+    /// Starts a new game, or keeps the current one when the rules leave no deal.
     pub fn reset(&mut self) {
+        let _ = self.reset_with_seed(rand::random());
+    }
+
+    /// Starts a new game with the deal of the given seed, so players can race on the same deal
+    /// and finished games can be checked by playing them again.
+    pub fn reset_with_seed(&mut self, seed: u64) -> Result<(), &'static str> {
         self.deselect_tile();
         self.stop_hints();
-        self.tiles.reset();
-        self.played = Vec::new();
-        self.selected_tile = None;
-        self.hints = None;
+        self.tiles.reset_with_seed(seed)?;
+        self.seed = seed;
+        self.history = History::new();
+        self.player = 0;
+        self.start_time = Instant::now();
+        self.finish_time = None;
+        self.recording = Vec::new();
+        Ok(())
+    }
+
+    /// Returns the time from the deal until the board was cleared, or until now.
+    pub fn time(&self) -> Duration {
+        self.finish_time.unwrap_or_else(|| self.start_time.elapsed())
+    }
+
+    /// Returns the pairs that were played, in order, by their tile numbers.
+    pub fn moves(&self) -> Vec<(usize, usize)> {
+        self.history
+            .line()
+            .iter()
+            .map(|played| (played.tiles.0.index(), played.tiles.1.index()))
+            .collect()
+    }
+
+    /// Returns everything that was done since the deal, to be played again later.
+    pub fn replay(&self) -> Replay {
+        Replay {
+            layout: leaderboard::LAYOUT.to_string(),
+            rules: self.rules().name().to_string(),
+            tile_set: self.tile_set().name().to_string(),
+            blind: self.is_blind(),
+            seed: self.seed,
+            steps: self.recording.clone(),
+        }
+    }
+
+    fn record(&mut self, action: Action) {
+        self.recording.push(Step {
+            millis: self.start_time.elapsed().as_millis() as u64,
+            action,
+        });
+    }
+
+    pub fn rules(&self) -> RuleSet {
+        self.tiles.rules()
+    }
+
+    /// Switches the free tile rules and starts a new game with them. The current rules and game
+    /// are kept when the new rules leave no deal.
+    pub fn set_rules(&mut self, rules: RuleSet) -> Result<(), &'static str> {
+        let current = self.rules();
+        self.tiles.set_rules(rules);
+        self.reset_with_seed(rand::random()).inspect_err(|_| self.tiles.set_rules(current))
+    }
+
+    pub fn is_blind(&self) -> bool {
+        self.tiles.is_blind()
+    }
+
+    /// Switches between showing all faces and showing only the faces of free tiles, and starts a
+    /// new game. Every deal has a solution, so hiding the blocked tiles never makes a game
+    /// unwinnable.
+    pub fn set_blind(&mut self, blind: bool) -> Result<(), &'static str> {
+        let current = self.is_blind();
+        self.tiles.set_blind(blind);
+        self.reset_with_seed(rand::random()).inspect_err(|_| self.tiles.set_blind(current))
+    }
+
+    pub fn tile_set(&self) -> &TileSet {
+        self.tiles.tile_set()
+    }
+
+    /// Switches the tile set and starts a new game with it. The current tile set and game are
+    /// kept when the new tile set is invalid or leaves no deal.
+    pub fn set_tile_set(&mut self, tile_set: TileSet) -> Result<(), &'static str> {
+        let current = self.tile_set().clone();
+        self.tiles.set_tile_set(tile_set)?;
+        self.reset_with_seed(rand::random()).inspect_err(|_| {
+            let _ = self.tiles.set_tile_set(current);
+        })
     }
 
     pub fn try_select_tile(&mut self, mouse_x: i32, mouse_y: i32) -> Result<(), GameOver> {
@@ -53,7 +253,7 @@ impl<'tc> Board<'tc> {
                 Some(tile2) => {
                     // deselect tile
                     if tile1 == tile2 {
-                        self.deselect_tile();
+                        self.toggle_selection(tile1);
                         return Ok(());
                     }
 
@@ -63,33 +263,166 @@ impl<'tc> Board<'tc> {
                     }
 
                     // valid match
-                    self.tiles.play_tile(tile1);
-                    self.tiles.play_tile(tile2);
-                    self.played.push((tile1, tile2));
+                    return self.play_pair(tile1, tile2);
+                }
+                None => self.toggle_selection(tile1),
+            }
+        }
+        Ok(())
+    }
 
-                    self.deselect_tile();
+    fn play_pair(&mut self, tile1: TileId, tile2: TileId) -> Result<(), GameOver> {
+        self.record(Action::Match(tile1.index(), tile2.index()));
+        self.tiles.remove_tile(tile1);
+        self.tiles.remove_tile(tile2);
+        self.history.play(Move {
+            tiles: (tile1, tile2),
+            player: self.player,
+        });
 
-                    if self.get_available_matches().is_err() {
-                        return Err(GameOver);
-                    }
-                }
-                None => self.select_tile(tile1),
+        self.deselect_tile();
+
+        if self.tiles.is_cleared() {
+            self.finish_time = Some(self.start_time.elapsed());
+            return Err(GameOver::Won);
+        }
+        if self.get_available_matches().is_err() {
+            return Err(GameOver::Stuck);
+        }
+        Ok(())
+    }
+
+    /// Plays the pairs of a finished game again by their tile numbers, failing when a pair wasn't
+    /// free and matching at that point of the game.
+    pub fn play_moves(&mut self, moves: &[(usize, usize)]) -> Result<(), &'static str> {
+        for &(tile1, tile2) in moves {
+            let (tile1, tile2) = (self.free_tile(tile1)?, self.free_tile(tile2)?);
+            if tile1 == tile2 || !self.tiles.are_matching(tile1, tile2) {
+                return Err("a played pair doesn't match");
             }
+            let _ = self.play_pair(tile1, tile2);
         }
         Ok(())
     }
 
+    /// Finds the tile with the given number, if it is on the board and free.
+    fn free_tile(&self, tile: usize) -> Result<TileId, &'static str> {
+        self.tiles
+            .playable_tiles()
+            .find(|playable| playable.index() == tile)
+            .ok_or("a played tile isn't free")
+    }
+
+    /// Sets the player that the next played pairs are recorded for, players are numbered from 0.
+    pub fn set_player(&mut self, player: usize) {
+        self.player = player;
+    }
+
+    pub fn pairs_played_by(&self, player: usize) -> usize {
+        self.history.line().iter().filter(|played| played.player == player).count()
+    }
+
+    /// Returns the player that played the last pair which can be undone.
+    pub fn last_player(&self) -> Option<usize> {
+        self.history.played(self.history.current()).map(|played| played.player)
+    }
+
+    pub fn num_tiles(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn tiles_left(&self) -> usize {
+        self.tiles.tiles_left()
+    }
+
+    pub fn pairs_played(&self) -> usize {
+        self.history.path(self.history.current()).len()
+    }
+
+    /// Deals new tiles into the empty positions. Played pairs can't be taken back after this.
+    pub fn refill(&mut self) -> Result<(), &'static str> {
+        self.refill_with_seed(rand::random())
+    }
+
+    fn refill_with_seed(&mut self, seed: u64) -> Result<(), &'static str> {
+        self.deselect_tile();
+        self.stop_hints();
+        self.tiles.refill(seed)?;
+        self.record(Action::Shuffle(seed));
+        self.history = History::new();
+        Ok(())
+    }
+
     pub fn undo(&mut self) {
+        self.record(Action::Undo);
+        self.deselect_tile();
+        self.stop_hints();
+
+        if let Some(Move { tiles: (tile1, tile2), .. }) = self.history.undo() {
+            self.tiles.put_back_tile(tile1);
+            self.tiles.put_back_tile(tile2);
+        }
+    }
+
+    /// Takes back and plays pairs until the board is at the given position of the history.
+    pub fn jump_to(&mut self, node: usize) -> Result<(), GameOver> {
+        let target = self.history.path(node);
+        let current = self.history.path(self.history.current());
+        let common = target
+            .iter()
+            .zip(&current)
+            .take_while(|(node1, node2)| node1 == node2)
+            .count();
+
+        for _ in common..current.len() {
+            self.undo();
+        }
+        let mut result = Ok(());
+        for &node in &target[common..] {
+            if let Some(Move { tiles: (tile1, tile2), .. }) = self.history.played(node) {
+                result = self.play_pair(tile1, tile2);
+            }
+        }
+        // the tiles are shown where the jump lands rather than every pair flying on and off
+        self.finish_animations();
+        result
+    }
+
+    /**
+     * Starts looking for the pair that made the game unwinnable, on a thread of its own. Once
+     * it is found the board goes back to the position before it, see `show_finding`.
+     */
+    pub fn review(&mut self) {
         self.deselect_tile();
         self.stop_hints();
 
-        if let Some((tile1, tile2)) = self.played.pop() {
-            self.tiles.reset_tile(tile1);
-            self.tiles.reset_tile(tile2);
+        let finding = spawn_analysis(self.tiles.clone(), self.history.line());
+        self.review = Some(Review::Pending(finding));
+    }
+
+    /**
+     * Goes back to the position before the pair that lost the game, with the pair outlined and
+     * a pair that keeps the game winnable highlighted. The line that was played stays in the
+     * history.
+     */
+    fn show_finding(&mut self, finding: Finding) {
+        if let Finding::Lost(analysis) = finding {
+            let node = match analysis.pairs_before {
+                0 => ROOT,
+                pairs => self.history.path(self.history.current())[pairs - 1],
+            };
+            let _ = self.jump_to(node);
+
+            if let Some((tile1, tile2)) = analysis.alternative {
+                self.tiles.highlight_tile(tile1);
+                self.tiles.highlight_tile(tile2);
+            }
         }
+        self.review = Some(Review::Done(finding));
     }
 
     pub fn highlight_possible_matches(&mut self) {
+        self.record(Action::Hint);
         self.deselect_tile();
         self.stop_hints();
 
@@ -104,12 +437,27 @@ impl<'tc> Board<'tc> {
         }
     }
 
-    pub fn update(&mut self) {
+    /// Shows the review once it is found and the next hint once it is due, and returns whether
+    /// the board looks different.
+    pub fn update(&mut self) -> bool {
+        let mut changed = self.tiles.update();
+        if let Some(Review::Pending(finding)) = &self.review {
+            match finding.try_recv() {
+                Ok(finding) => self.show_finding(finding),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => {
+                    self.review = Some(Review::Done(Finding::Incomplete));
+                }
+            }
+            changed |= !matches!(self.review, Some(Review::Pending(_)));
+        }
+
         let mut done = false;
         if let Some(hints) = self.hints.as_mut() {
             let index = (hints.start_time.elapsed().as_secs() / 2) as usize;
 
             if index > hints.current_index {
+                changed = true;
                 hints.sets[hints.current_index].dehighlight(&mut self.tiles);
                 if index >= hints.sets.len() {
                     done = true;
@@ -122,10 +470,52 @@ impl<'tc> Board<'tc> {
         if done {
             self.hints = None;
         }
+        changed
     }
 
-    pub fn render(&mut self, canvas: &mut WindowCanvas) {
-        self.tiles.render(canvas);
+    pub fn is_animating(&self) -> bool {
+        self.tiles.is_animating()
+    }
+
+    /// Shows the board as it is once everything that moves has come to rest.
+    pub fn finish_animations(&mut self) {
+        self.tiles.finish_motions();
+    }
+
+    /// Lays the board out again to fill a canvas of the given size.
+    pub fn fit_canvas(&mut self, width: u32, height: u32) {
+        self.tiles.fit_canvas(width, height);
+        self.tree_view = TreeView::of_canvas(width, height);
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        self.tiles.camera_mut()
+    }
+
+    pub fn set_view_angle(&mut self, angle: ViewAngle) {
+        self.tiles.set_view_angle(angle);
+    }
+
+    pub fn set_tile_size(&mut self, tile_size: TileSize) {
+        self.tiles.set_tile_size(tile_size);
+    }
+
+    pub fn render(&self, canvas: &mut WindowCanvas, textures: &TileTextures) {
+        self.tiles.render(canvas, textures);
+
+        if let Some(Review::Done(Finding::Lost(analysis))) = self.review {
+            let (tile1, tile2) = analysis.losing_pair;
+            canvas.set_draw_color(LOSING_PAIR_COLOR);
+            for &tile in &[tile1, tile2] {
+                let face = self.tiles.face_rect(tile);
+                let _ = canvas.draw_rect(face);
+                let _ = canvas.draw_rect(Rect::from_center(
+                    face.center(),
+                    face.width() - 2,
+                    face.height() - 2,
+                ));
+            }
+        }
     }
 
     fn get_available_matches(&self) -> Result<Vec<HintSet>, NoMatch> {
@@ -147,7 +537,7 @@ impl<'tc> Board<'tc> {
                 set.add(tile2);
             }
 
-            if set.0[1] != None {
+            if set.0.len() > 1 {
                 used_tiles.push(tile);
                 sets.push(set);
             }
@@ -160,31 +550,207 @@ impl<'tc> Board<'tc> {
         }
     }
 
+    /// Selects the tile, or deselects it when it is the selected one, and records the click.
+    fn toggle_selection(&mut self, tile: TileId) {
+        self.record(Action::Select(tile.index()));
+        if self.selected_tile == Some(tile) {
+            self.deselect_tile();
+        } else {
+            self.select_tile(tile);
+        }
+    }
+
     fn select_tile(&mut self, tile: TileId) {
-        self.tiles.highlight_tile(tile);
+        self.tiles.select_tile(tile);
         self.selected_tile = Some(tile);
     }
 
     fn deselect_tile(&mut self) {
         if let Some(tile) = self.selected_tile {
-            self.tiles.dehighlight_tile(tile)
+            self.tiles.deselect_tile(tile)
         }
         self.selected_tile = None;
     }
 
+    /// Stops the hints, and the review, which shows its pairs like hints.
     fn stop_hints(&mut self) {
         if let Some(hints) = self.hints.as_mut() {
             hints.sets[hints.current_index].dehighlight(&mut self.tiles);
         }
+        if let Some(Review::Done(Finding::Lost(analysis))) = self.review {
+            if let Some((tile1, tile2)) = analysis.alternative {
+                self.tiles.dehighlight_tile(tile1);
+                self.tiles.dehighlight_tile(tile2);
+            }
+        }
 
         self.hints = None;
+        self.review = None;
     }
 }
 
-pub struct GameOver;
-
 struct NoMatch;
 
+impl Game for Board {
+    fn title(&self) -> String {
+        let view = if self.show_history {
+            " - history, click a position to go back to it".to_string()
+        } else {
+            self.review.as_ref().map_or_else(String::new, Review::describe)
+        };
+
+        format!(
+            "Solitaire - {} rules{} - {} tiles{}",
+            self.rules().name(),
+            if self.is_blind() { " - blind" } else { "" },
+            self.tile_set().name(),
+            view,
+        )
+    }
+
+    fn reset(&mut self) {
+        Board::reset(self);
+    }
+
+    fn undo(&mut self) {
+        Board::undo(self);
+    }
+
+    fn hint(&mut self) {
+        self.highlight_possible_matches();
+    }
+
+    fn click(&mut self, x: i32, y: i32) -> Result<(), GameOver> {
+        if self.show_history {
+            return match self.history.node_at(self.tree_view, x, y) {
+                Some(node) => self.jump_to(node),
+                None => Ok(()),
+            };
+        }
+        self.try_select_tile(x, y)
+    }
+
+    fn key(&mut self, keycode: Keycode) -> bool {
+        match keycode {
+            Keycode::B => {
+                let _ = self.set_blind(!self.is_blind());
+            }
+            Keycode::V => self.show_history = !self.show_history,
+            Keycode::A => self.review(),
+            Keycode::R => {
+                let _ = self.set_rules(self.rules().next());
+            }
+            Keycode::T => {
+                let _ = self.set_tile_set(next_tile_set(self.tile_set()));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.fit_canvas(width, height);
+    }
+
+    fn camera(&mut self) -> Option<&mut Camera> {
+        Some(self.camera_mut())
+    }
+
+    fn set_view_angle(&mut self, angle: ViewAngle) {
+        Board::set_view_angle(self, angle);
+    }
+
+    fn set_tile_size(&mut self, tile_size: TileSize) {
+        Board::set_tile_size(self, tile_size);
+    }
+
+    fn update(&mut self) -> Result<bool, GameOver> {
+        Ok(Board::update(self))
+    }
+
+    fn is_animating(&self) -> bool {
+        Board::is_animating(self)
+    }
+
+    fn summary(&self) -> Option<String> {
+        if self.tiles.is_cleared() || self.get_available_matches().is_ok() {
+            return None;
+        }
+        Some("Press A to find the pair that lost the game.".to_string())
+    }
+
+    fn submission(&self) -> Option<Submission> {
+        if !self.tiles.is_cleared() {
+            return None;
+        }
+
+        let time = self.time();
+        Some(Submission {
+            layout: leaderboard::LAYOUT.to_string(),
+            rules: self.rules().name().to_string(),
+            tile_set: self.tile_set().name().to_string(),
+            seed: self.seed,
+            time_millis: time.as_millis() as u64,
+            score: leaderboard::score(self.pairs_played(), time, true),
+            moves: self.moves(),
+        })
+    }
+
+    fn replay(&self) -> Option<Replay> {
+        Some(Board::replay(self))
+    }
+
+    fn render(&self, canvas: &mut WindowCanvas, textures: &TileTextures) {
+        Board::render(self, canvas, textures);
+        if self.show_history {
+            self.history.render(canvas, self.tree_view);
+        }
+    }
+}
+
+/// Returns the tile set preset that follows the given tile set.
+pub fn next_tile_set(tile_set: &TileSet) -> TileSet {
+    let presets = TileSet::presets();
+    let index = presets
+        .iter()
+        .position(|preset| preset.name() == tile_set.name())
+        .map_or(0, |index| (index + 1) % presets.len());
+
+    presets[index].clone()
+}
+
+/// The review of a lost game, which is analysed on a thread of its own.
+enum Review {
+    Pending(Receiver<Finding>),
+    Done(Finding),
+}
+
+impl Review {
+    fn describe(&self) -> String {
+        match self {
+            Review::Pending(_) => " - review: analysing the game".to_string(),
+            Review::Done(Finding::Lost(Analysis { pairs_before, alternative, .. })) => format!(
+                " - review: pair {} (outlined) lost the game{}",
+                pairs_before + 1,
+                if alternative.is_some() { ", the highlighted pair keeps it winnable" } else { "" },
+            ),
+            Review::Done(Finding::Winnable) => {
+                " - review: no pair was found that lost the game".to_string()
+            }
+            Review::Done(Finding::Incomplete) => {
+                " - review: analysis incomplete, the solver gave up on the game".to_string()
+            }
+        }
+    }
+}
+
+/// A played pair together with the player that played it.
+#[derive(Clone, Copy, Debug)]
+struct Move {
+    tiles: (TileId, TileId),
+    player: usize,
+}
+
 struct Hints {
     sets: Vec<HintSet>,
     start_time: Instant,
@@ -192,40 +758,31 @@ struct Hints {
 }
 
 #[derive(Debug)]
-struct HintSet([Option<TileId>; 4]);
+struct HintSet(Vec<TileId>);
 
 impl HintSet {
     fn new(tile: TileId) -> HintSet {
-        HintSet([Some(tile), None, None, None])
+        HintSet(vec![tile])
     }
 
     fn add(&mut self, tile: TileId) {
-        for opt_tile in &mut self.0 {
-            if *opt_tile == None {
-                *opt_tile = Some(tile);
-                break;
-            }
-        }
+        self.0.push(tile);
     }
 
     fn highlight(&self, tiles: &mut Tiles) {
-        for opt_tile in &self.0 {
-            if let Some(tile) = *opt_tile {
-                tiles.highlight_tile(tile);
-            }
+        for &tile in &self.0 {
+            tiles.highlight_tile(tile);
         }
     }
 
     fn dehighlight(&self, tiles: &mut Tiles) {
-        for opt_tile in &self.0 {
-            if let Some(tile) = *opt_tile {
-                tiles.dehighlight_tile(tile)
-            }
+        for &tile in &self.0 {
+            tiles.dehighlight_tile(tile)
         }
     }
 }
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 fn get_raw_positios() -> [(u8, u8, u8); 144] {
     [(4, 0, 0), (6, 0, 0), (8, 0, 0), (10, 0, 0), (12, 0, 0), (14, 0, 0), (16, 0, 0), (18, 0, 0),
     (20, 0, 0), (22, 0, 0), (24, 0, 0), (26, 0, 0), (8, 2, 0), (10, 2, 0), (12, 2, 0), (14, 2, 0),
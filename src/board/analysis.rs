@@ -0,0 +1,232 @@
+use {
+    std::{
+        sync::mpsc::{
+            self,
+            Receiver,
+        },
+        thread,
+    },
+    super::{
+        tiles::{
+            is_solvable,
+            matching_pairs,
+            TileId,
+            Tiles,
+        },
+        Move,
+    },
+};
+
+/// The number of board positions the solver looks at for each position of the game.
+static ANALYSIS_POSITIONS: usize = 20_000;
+
+type Pair = (TileId, TileId);
+
+/// The pair after which a game could no longer be won.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Analysis {
+    /// The number of pairs played before the losing pair.
+    pub pairs_before: usize,
+    pub losing_pair: Pair,
+    /// A pair that keeps the board winnable instead, if the solver found one in time.
+    pub alternative: Option<Pair>,
+}
+
+/// What the analysis of a game found.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Finding {
+    Lost(Analysis),
+    /// The game can still be won.
+    Winnable,
+    /// The solver gave up on a position before the search was done.
+    Incomplete,
+}
+
+/// Analyses the game on a thread of its own, so the window keeps responding while the solver
+/// works. The finding is sent once it is done.
+pub fn spawn_analysis(mut tiles: Tiles, line: Vec<Move>) -> Receiver<Finding> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(find_losing_pair(&mut tiles, &line, ANALYSIS_POSITIONS));
+    });
+    receiver
+}
+
+/**
+ * Searches the played pairs for the last position that can still be won, halving the pairs to
+ * look at with every position the solver decides, visiting at most `max_positions` board
+ * positions for each. Every deal can be won, so the search starts between the deal and the end
+ * of the game. The pair played from the last winnable position is the one that lost the game.
+ * The tiles are left as they were.
+ */
+pub fn find_losing_pair(tiles: &mut Tiles, line: &[Move], max_positions: usize) -> Finding {
+    let mut position = line.len();
+    let finding = search(tiles, line, &mut position, max_positions);
+    move_to(tiles, line, &mut position, line.len());
+    finding
+}
+
+fn search(
+    tiles: &mut Tiles,
+    line: &[Move],
+    position: &mut usize,
+    max_positions: usize,
+) -> Finding {
+    // the pairs played before `won` still leave a winnable board, those before `lost` don't
+    let mut won = 0;
+    let mut lost = line.len();
+    match is_solvable(tiles, max_positions) {
+        Some(false) => {}
+        Some(true) => return Finding::Winnable,
+        None => return Finding::Incomplete,
+    }
+
+    while lost - won > 1 {
+        let middle = (won + lost) / 2;
+        move_to(tiles, line, position, middle);
+        match is_solvable(tiles, max_positions) {
+            Some(true) => won = middle,
+            Some(false) => lost = middle,
+            None => return Finding::Incomplete,
+        }
+    }
+
+    move_to(tiles, line, position, won);
+    let losing_pair = line[won].tiles;
+    Finding::Lost(Analysis {
+        pairs_before: won,
+        losing_pair,
+        alternative: find_winning_pair(tiles, losing_pair, max_positions),
+    })
+}
+
+/// Plays or takes back pairs of the line until the given number of pairs is played.
+fn move_to(tiles: &mut Tiles, line: &[Move], position: &mut usize, target: usize) {
+    while *position < target {
+        tiles.play_tile(line[*position].tiles.0);
+        tiles.play_tile(line[*position].tiles.1);
+        *position += 1;
+    }
+    while *position > target {
+        *position -= 1;
+        tiles.reset_tile(line[*position].tiles.1);
+        tiles.reset_tile(line[*position].tiles.0);
+    }
+}
+
+/// Finds a pair other than the given one after which the board can still be won.
+fn find_winning_pair(tiles: &mut Tiles, except: Pair, max_positions: usize) -> Option<Pair> {
+    matching_pairs(tiles)
+        .into_iter()
+        .filter(|&pair| pair != except && pair != (except.1, except.0))
+        .find(|&(tile1, tile2)| {
+            tiles.play_tile(tile1);
+            tiles.play_tile(tile2);
+            let solvable = is_solvable(tiles, max_positions);
+            tiles.reset_tile(tile2);
+            tiles.reset_tile(tile1);
+            solvable == Some(true)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::{
+            thread,
+            time::Duration,
+        },
+        super::{
+            super::{
+                tiles::{
+                    is_solvable,
+                    matching_pairs,
+                    TileType,
+                },
+                Board,
+                Review,
+            },
+            find_losing_pair,
+            Finding,
+            ANALYSIS_POSITIONS,
+        },
+    };
+
+    /// Plays the first pair found until no pair is left.
+    fn play_out(board: &mut Board) {
+        while let Some(&(tile1, tile2)) = matching_pairs(&board.tiles).first() {
+            let _ = board.play_pair(tile1, tile2);
+        }
+    }
+
+    /// Returns the boards of the first deals that were lost by playing them out.
+    fn lost_boards() -> impl Iterator<Item = Board> {
+        (0..10).filter_map(|seed| {
+            let mut board = Board::new();
+            board.reset_with_seed(seed).unwrap();
+            play_out(&mut board);
+            Some(board).filter(|board| !board.tiles.is_cleared())
+        })
+    }
+
+    #[test]
+    fn losing_pairs_have_a_winning_alternative() {
+        for mut board in lost_boards() {
+            let line = board.history.line();
+            let playable = board.tiles.playable_tiles().collect::<Vec<_>>();
+            let analysis = match find_losing_pair(&mut board.tiles, &line, ANALYSIS_POSITIONS) {
+                Finding::Lost(analysis) => analysis,
+                _ => continue,
+            };
+            assert_eq!(board.tiles.playable_tiles().collect::<Vec<_>>(), playable);
+
+            // the position right after the losing pair is lost
+            let node = board.history.path(board.history.current())[analysis.pairs_before];
+            let _ = board.jump_to(node);
+            assert_eq!(board.pairs_played(), analysis.pairs_before + 1);
+            assert_ne!(is_solvable(&mut board.tiles, ANALYSIS_POSITIONS), Some(true));
+
+            board.undo();
+            let (tile1, tile2) = analysis.alternative.unwrap();
+            let _ = board.play_pair(tile1, tile2);
+            assert_ne!(is_solvable(&mut board.tiles, ANALYSIS_POSITIONS), Some(false));
+            return;
+        }
+        panic!("no losing pair was found");
+    }
+
+    #[test]
+    fn winnable_games_have_no_losing_pair() {
+        let mut board = Board::new();
+        board.tiles.reset_with_triples(vec![TileType::BallOne; board.num_tiles()]).unwrap();
+        play_out(&mut board);
+
+        let line = board.history.line();
+        let finding = find_losing_pair(&mut board.tiles, &line, ANALYSIS_POSITIONS);
+        assert_eq!(finding, Finding::Winnable);
+    }
+
+    #[test]
+    fn searches_the_solver_gives_up_on_are_incomplete() {
+        let mut board = lost_boards().next().unwrap();
+        let line = board.history.line();
+
+        assert_eq!(find_losing_pair(&mut board.tiles, &line, 1), Finding::Incomplete);
+        assert!(Review::Done(Finding::Incomplete).describe().contains("analysis incomplete"));
+    }
+
+    #[test]
+    fn reviews_are_found_off_the_window_thread() {
+        let mut board = lost_boards().next().unwrap();
+
+        board.review();
+        for _ in 0..2000 {
+            if let Some(Review::Done(_)) = board.review {
+                return;
+            }
+            thread::sleep(Duration::from_millis(5));
+            board.update();
+        }
+        panic!("the review never finished");
+    }
+}
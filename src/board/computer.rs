@@ -0,0 +1,151 @@
+use {
+    std::cmp::Reverse,
+    rand::{
+        seq::SliceRandom,
+        Rng,
+    },
+    super::tiles::{
+        is_solvable,
+        matching_pairs,
+        TileId,
+        Tiles,
+    },
+};
+
+/// The number of board positions the strongest opponent looks at for each pair it considers.
+static SOLVER_POSITIONS: usize = 1000;
+
+type Pair = (TileId, TileId);
+
+/// How well a computer opponent plays.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strength {
+    /// Plays any pair that can be played.
+    Random,
+    /// Plays the pair that frees the most tiles.
+    Greedy,
+    /// Plays the pair that frees the most tiles among the pairs that keep the board solvable.
+    Solver,
+}
+
+impl Strength {
+    pub fn name(self) -> &'static str {
+        match self {
+            Strength::Random => "random",
+            Strength::Greedy => "greedy",
+            Strength::Solver => "solver",
+        }
+    }
+}
+
+/// Picks the pair to play, or `None` when no pair can be played.
+pub fn choose_pair<R: Rng>(tiles: &mut Tiles, strength: Strength, rng: &mut R) -> Option<Pair> {
+    // pairs that are equally good are picked at random
+    let mut pairs = matching_pairs(tiles);
+    pairs.shuffle(rng);
+
+    match strength {
+        Strength::Random => pairs.first().cloned(),
+        Strength::Greedy => by_freed_tiles(tiles, pairs).first().cloned(),
+        Strength::Solver => {
+            let pairs = by_freed_tiles(tiles, pairs);
+
+            // a pair the solver gave up on is still better than one it knows loses
+            let mut undecided = None;
+            for &(tile1, tile2) in &pairs {
+                tiles.play_tile(tile1);
+                tiles.play_tile(tile2);
+                let solvable = is_solvable(tiles, SOLVER_POSITIONS);
+                tiles.reset_tile(tile2);
+                tiles.reset_tile(tile1);
+
+                match solvable {
+                    Some(true) => return Some((tile1, tile2)),
+                    None if undecided.is_none() => undecided = Some((tile1, tile2)),
+                    _ => {}
+                }
+            }
+            undecided.or_else(|| pairs.first().cloned())
+        }
+    }
+}
+
+/// Orders the pairs by the number of tiles they free when played, the most first.
+fn by_freed_tiles(tiles: &mut Tiles, pairs: Vec<Pair>) -> Vec<Pair> {
+    let playable = tiles.playable_tiles().count();
+
+    let mut scored = pairs
+        .into_iter()
+        .map(|(tile1, tile2)| {
+            tiles.play_tile(tile1);
+            tiles.play_tile(tile2);
+            let freed = tiles.playable_tiles().count() + 2 - playable;
+            tiles.reset_tile(tile2);
+            tiles.reset_tile(tile1);
+
+            (freed, (tile1, tile2))
+        }).collect::<Vec<_>>();
+
+    scored.sort_by_key(|&(freed, _)| Reverse(freed));
+    scored.into_iter().map(|(_, pair)| pair).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        rand::{
+            rngs::SmallRng,
+            SeedableRng,
+        },
+        crate::board::{
+            get_raw_positios,
+            tiles::{
+                matching_pairs,
+                RuleSet,
+                Tiles,
+            },
+        },
+        super::{
+            choose_pair,
+            Strength,
+        },
+    };
+
+    #[test]
+    fn every_strength_plays_a_matching_pair() {
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        for &strength in &[Strength::Random, Strength::Greedy, Strength::Solver] {
+            let mut tiles = Tiles::new(&mut get_raw_positios(), RuleSet::Classic);
+            tiles.reset_with_seed(0).unwrap();
+            let playable = tiles.playable_tiles().collect::<Vec<_>>();
+
+            let (tile1, tile2) = choose_pair(&mut tiles, strength, &mut rng).unwrap();
+            assert!(matching_pairs(&tiles).contains(&(tile1, tile2)));
+            assert_eq!(tiles.playable_tiles().collect::<Vec<_>>(), playable);
+        }
+    }
+
+    #[test]
+    fn greedy_frees_the_most_tiles() {
+        let mut tiles = Tiles::new(&mut get_raw_positios(), RuleSet::Classic);
+        tiles.reset_with_seed(0).unwrap();
+        let playable = tiles.playable_tiles().count();
+
+        let freed = |tiles: &mut Tiles, (tile1, tile2)| {
+            tiles.play_tile(tile1);
+            tiles.play_tile(tile2);
+            let freed = tiles.playable_tiles().count() + 2 - playable;
+            tiles.reset_tile(tile2);
+            tiles.reset_tile(tile1);
+            freed
+        };
+
+        let pairs = matching_pairs(&tiles);
+        let most = pairs.iter().map(|&pair| freed(&mut tiles, pair)).max().unwrap();
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        let pair = choose_pair(&mut tiles, Strength::Greedy, &mut rng).unwrap();
+        assert_eq!(freed(&mut tiles, pair), most);
+    }
+}
@@ -0,0 +1,340 @@
+use {
+    sdl2::{
+        pixels::Color,
+        rect::{
+            Point,
+            Rect,
+        },
+        render::WindowCanvas,
+    },
+    crate::game::{
+        Scale,
+        MIN_CANVAS,
+    },
+    super::Move,
+};
+
+static BACKGROUND_COLOR: Color = Color { r: 20, g: 20, b: 20, a: 255 };
+static NODE_COLOR: Color = Color { r: 110, g: 110, b: 110, a: 255 };
+static LINE_COLOR: Color = Color { r: 80, g: 140, b: 255, a: 255 };
+static CURRENT_COLOR: Color = Color { r: 255, g: 220, b: 80, a: 255 };
+/// The space between the tree view and the edges of the canvas, the buttons are left of it.
+static VIEW_LEFT: i32 = 140;
+static VIEW_TOP: i32 = 10;
+static VIEW_RIGHT: i32 = 10;
+static VIEW_BOTTOM: i32 = 15;
+/// The space between the edges of the tree view and the positions in it.
+static VIEW_PADDING: i32 = 8;
+static NODE_SIZE: u32 = 5;
+static NODE_SPACING: i32 = 7;
+static ROW_SPACING: i32 = 11;
+
+/// The position before any pair was played.
+pub static ROOT: usize = 0;
+
+struct Node {
+    parent: usize,
+    played: Option<Move>,
+    children: Vec<usize>,
+}
+
+/// Where the tree of positions is drawn over the board, and how large its positions are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TreeView {
+    rect: Rect,
+    scale: Scale,
+}
+
+impl TreeView {
+    /// Fills a canvas of the given size right of the buttons.
+    pub fn of_canvas(width: u32, height: u32) -> Self {
+        let scale = Scale::of_canvas(width, height);
+        let (x, y) = (scale.distance(VIEW_LEFT), scale.distance(VIEW_TOP));
+        let right = width as i32 - scale.distance(VIEW_RIGHT);
+        let bottom = height as i32 - scale.distance(VIEW_BOTTOM);
+        TreeView {
+            rect: Rect::new(x, y, (right - x).max(1) as u32, (bottom - y).max(1) as u32),
+            scale,
+        }
+    }
+
+    fn node_spacing(self) -> i32 {
+        self.scale.distance(NODE_SPACING)
+    }
+
+    fn row_spacing(self) -> i32 {
+        self.scale.distance(ROW_SPACING)
+    }
+}
+
+impl Default for TreeView {
+    fn default() -> Self {
+        TreeView::of_canvas(MIN_CANVAS.0, MIN_CANVAS.1)
+    }
+}
+
+/**
+ * Every line of pairs played since the deal, as a tree of positions. Taking a pair back moves to
+ * the position before it without forgetting it, so playing another pair starts a new branch and
+ * the earlier line can still be returned to.
+ */
+pub struct History {
+    nodes: Vec<Node>,
+    current: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History {
+            nodes: vec![Node { parent: ROOT, played: None, children: Vec::new() }],
+            current: ROOT,
+        }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Moves to the position after the pair, which is added unless it was played here before.
+    pub fn play(&mut self, played: Move) {
+        let known = self.nodes[self.current]
+            .children
+            .iter()
+            .find(|&&child| self.nodes[child].played.map(|known| known.tiles) == Some(played.tiles))
+            .cloned();
+
+        self.current = known.unwrap_or_else(|| {
+            self.nodes.push(Node {
+                parent: self.current,
+                played: Some(played),
+                children: Vec::new(),
+            });
+            let node = self.nodes.len() - 1;
+            self.nodes[self.current].children.push(node);
+            node
+        });
+    }
+
+    /// Moves to the position before the last pair, and returns the pair.
+    pub fn undo(&mut self) -> Option<Move> {
+        let played = self.nodes[self.current].played?;
+        self.current = self.nodes[self.current].parent;
+        Some(played)
+    }
+
+    /// Returns the positions from the deal up to the given position, without the deal.
+    pub fn path(&self, mut node: usize) -> Vec<usize> {
+        let mut path = Vec::new();
+        while node != ROOT {
+            path.push(node);
+            node = self.nodes[node].parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Returns the pair that led to the position.
+    pub fn played(&self, node: usize) -> Option<Move> {
+        self.nodes[node].played
+    }
+
+    /// Returns the pairs that led to the current position, in order.
+    pub fn line(&self) -> Vec<Move> {
+        self.path(self.current).into_iter().filter_map(|node| self.played(node)).collect()
+    }
+
+    /**
+     * Returns the column and row of every position in the tree view. Positions are in the
+     * column of the number of pairs played; the first branch of a position continues in its row
+     * and every other branch gets a row of its own, below the branches that came before it.
+     */
+    fn layout(&self) -> Vec<(i32, i32)> {
+        let mut cells = vec![(0, 0); self.nodes.len()];
+        let mut next_row = 1;
+        let mut stack = vec![(ROOT, Some(0))];
+        while let Some((node, row)) = stack.pop() {
+            let row = row.unwrap_or_else(|| {
+                next_row += 1;
+                next_row - 1
+            });
+            let column = if node == ROOT { 0 } else { cells[self.nodes[node].parent].0 + 1 };
+            cells[node] = (column, row);
+
+            for (index, &child) in self.nodes[node].children.iter().enumerate().rev() {
+                stack.push((child, if index == 0 { Some(row) } else { None }));
+            }
+        }
+        cells
+    }
+
+    /**
+     * Returns the first column and row in the view. Trees larger than the view are scrolled to
+     * keep the current position in the middle of it, or as close to the middle as the tree
+     * goes.
+     */
+    fn scroll(&self, view: TreeView, cells: &[(i32, i32)]) -> (i32, i32) {
+        let padding = 2 * view.scale.distance(VIEW_PADDING);
+        let columns = (view.rect.width() as i32 - padding) / view.node_spacing();
+        let rows = (view.rect.height() as i32 - padding) / view.row_spacing();
+        let (column, row) = cells[self.current];
+        ((column - columns / 2).max(0), (row - rows / 2).max(0))
+    }
+
+    fn node_rect(
+        view: TreeView,
+        (column, row): (i32, i32),
+        (first_column, first_row): (i32, i32),
+    ) -> Rect {
+        let padding = view.scale.distance(VIEW_PADDING);
+        let size = view.scale.size(NODE_SIZE);
+        Rect::new(
+            view.rect.x() + padding + (column - first_column) * view.node_spacing(),
+            view.rect.y() + padding + (row - first_row) * view.row_spacing(),
+            size,
+            size,
+        )
+    }
+
+    /// Returns the position drawn at the coordinates of the tree view.
+    pub fn node_at(&self, view: TreeView, x: i32, y: i32) -> Option<usize> {
+        if !view.rect.contains_point((x, y)) {
+            return None;
+        }
+
+        let cells = self.layout();
+        let scroll = self.scroll(view, &cells);
+        let (width, height) = (view.node_spacing() as u32, view.row_spacing() as u32);
+        cells.iter().position(|&cell| {
+            let rect = History::node_rect(view, cell, scroll);
+            let (left, top) = (view.scale.distance(1), view.scale.distance(3));
+            Rect::new(rect.x() - left, rect.y() - top, width, height).contains_point((x, y))
+        })
+    }
+
+    /// Draws the tree over the board, with the line to the current position highlighted.
+    pub fn render(&self, canvas: &mut WindowCanvas, view: TreeView) {
+        canvas.set_draw_color(BACKGROUND_COLOR);
+        let _ = canvas.fill_rect(view.rect);
+
+        let cells = self.layout();
+        let scroll = self.scroll(view, &cells);
+        let line = self.path(self.current);
+        let center = |node: usize| History::node_rect(view, cells[node], scroll).center();
+
+        // lines to positions out of view are cut off at its edges
+        canvas.set_clip_rect(view.rect);
+        for (node, &cell) in cells.iter().enumerate() {
+            let rect = History::node_rect(view, cell, scroll);
+
+            let color = if node == self.current {
+                CURRENT_COLOR
+            } else if node == ROOT || line.contains(&node) {
+                LINE_COLOR
+            } else {
+                NODE_COLOR
+            };
+            canvas.set_draw_color(color);
+            if node != ROOT {
+                let parent = center(self.nodes[node].parent);
+                let corner = Point::new(parent.x(), rect.center().y());
+                let _ = canvas.draw_line(parent, corner);
+                let _ = canvas.draw_line(corner, rect.center());
+            }
+            let _ = canvas.fill_rect(rect);
+        }
+        canvas.set_clip_rect(None);
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            tiles::matching_pairs,
+            Board,
+        },
+        History,
+        TreeView,
+        ROOT,
+    };
+
+    #[test]
+    fn undone_lines_stay_reachable() {
+        let mut board = Board::new();
+        board.reset_with_seed(9).unwrap();
+        let pairs = matching_pairs(&board.tiles);
+
+        let _ = board.play_pair(pairs[0].0, pairs[0].1);
+        let first = board.history.current();
+        board.undo();
+        let _ = board.play_pair(pairs[1].0, pairs[1].1);
+        assert_eq!(board.pairs_played(), 1);
+
+        assert!(board.jump_to(first).is_ok());
+        assert_eq!(board.moves(), vec![(pairs[0].0.index(), pairs[0].1.index())]);
+        assert_eq!(board.tiles_left(), 142);
+
+        assert!(board.jump_to(ROOT).is_ok());
+        assert_eq!(board.tiles_left(), 144);
+        // playing a known pair again follows its branch
+        let _ = board.play_pair(pairs[0].0, pairs[0].1);
+        assert_eq!(board.history.current(), first);
+    }
+
+    #[test]
+    fn branches_get_rows_of_their_own() {
+        let mut board = Board::new();
+        board.reset_with_seed(9).unwrap();
+        let pairs = matching_pairs(&board.tiles);
+
+        for &(tile1, tile2) in &pairs[..3] {
+            let _ = board.play_pair(tile1, tile2);
+            board.undo();
+        }
+        let _ = board.play_pair(pairs[0].0, pairs[0].1);
+        let (tile1, tile2) = matching_pairs(&board.tiles)[0];
+        let _ = board.play_pair(tile1, tile2);
+
+        assert_eq!(board.history.layout(), vec![(0, 0), (1, 0), (1, 1), (1, 2), (2, 0)]);
+        let view = TreeView::default();
+        let rect = History::node_rect(view, (1, 2), (0, 0));
+        assert_eq!(board.history.node_at(view, rect.x(), rect.y()), Some(3));
+    }
+
+    #[test]
+    fn the_view_follows_the_current_position() {
+        let mut board = Board::new();
+        board.reset_with_seed(9).unwrap();
+
+        // every pair that could be played is tried and taken back, each adding a row
+        let view = TreeView::of_canvas(2780, 1872);
+        let rows = view.rect.height() as i32 / view.row_spacing();
+        while board.history.layout().iter().all(|&(_, row)| row < 2 * rows) {
+            let pairs = matching_pairs(&board.tiles);
+            for &(tile1, tile2) in &pairs[1..] {
+                let _ = board.play_pair(tile1, tile2);
+                board.undo();
+            }
+            assert!(board.play_pair(pairs[0].0, pairs[0].1).is_ok());
+        }
+
+        let cells = board.history.layout();
+        let last_row = cells.iter().map(|&(_, row)| row).max().unwrap();
+        let node = cells.iter().position(|&(_, row)| row == last_row).unwrap();
+        assert!(board.jump_to(node).is_ok());
+
+        let scroll = board.history.scroll(view, &cells);
+        assert!(scroll.1 > 0);
+        let rect = History::node_rect(view, cells[node], scroll);
+        assert!(view.rect.contains_rect(rect));
+        assert_eq!(board.history.node_at(view, rect.x(), rect.y()), Some(node));
+        let (x, y) = (view.rect.x(), view.rect.y());
+        assert_eq!(board.history.node_at(view, x - 1, y + 40), None);
+    }
+}
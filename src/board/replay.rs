@@ -0,0 +1,339 @@
+use {
+    std::{
+        fs,
+        io,
+        path::Path,
+        time::Duration,
+    },
+    crate::leaderboard,
+    super::{
+        Board,
+        RuleSet,
+        TileSet,
+    },
+};
+
+/// The first line of every replay file, which names the version of the format.
+static HEADER: &str = "mahjong-replay 1";
+
+/// Where the replays of finished games are saved.
+pub static REPLAY_DIR: &str = "replays";
+
+/// Something a player did, with tiles given by their numbers on the layout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Selects a free tile, or deselects it when it is selected already.
+    Select(usize),
+    Match(usize, usize),
+    Undo,
+    Hint,
+    /// Deals new tiles into the empty positions with the given seed.
+    Shuffle(u64),
+}
+
+/// An action together with the milliseconds from the deal until it was done.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Step {
+    pub millis: u64,
+    pub action: Action,
+}
+
+/**
+ * A recorded solitaire game: the deal, given by the layout, rules, tile set and seed, and every
+ * action in order. Replays are stored as text with a header of one setting per line and a line
+ * per step, such as `1520 m 12 40` for a pair matched 1.52 seconds into the game.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub layout: String,
+    pub rules: String,
+    pub tile_set: String,
+    pub blind: bool,
+    pub seed: u64,
+    pub steps: Vec<Step>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, &'static str> {
+        let text = fs::read_to_string(path).map_err(|_| "replay can't be read")?;
+        Replay::parse(&text)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{}\nlayout {}\nrules {}\ntiles {}\nblind {}\nseed {}\n",
+            HEADER,
+            self.layout,
+            self.rules,
+            self.tile_set,
+            self.blind,
+            self.seed,
+        );
+
+        for step in &self.steps {
+            let action = match step.action {
+                Action::Select(tile) => format!("s {}", tile),
+                Action::Match(tile1, tile2) => format!("m {} {}", tile1, tile2),
+                Action::Undo => "u".to_string(),
+                Action::Hint => "h".to_string(),
+                Action::Shuffle(seed) => format!("x {}", seed),
+            };
+            text.push_str(&format!("{} {}\n", step.millis, action));
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, &'static str> {
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err("not a replay file");
+        }
+
+        let mut setting = |name: &str| {
+            lines
+                .next()
+                .and_then(|line| line.strip_prefix(name))
+                .and_then(|line| line.strip_prefix(' '))
+                .map(str::to_string)
+                .ok_or("replay has missing settings")
+        };
+        let layout = setting("layout")?;
+        let rules = setting("rules")?;
+        let tile_set = setting("tiles")?;
+        let blind = setting("blind")?.parse().map_err(|_| "replay has invalid settings")?;
+        let seed = setting("seed")?.parse().map_err(|_| "replay has invalid settings")?;
+
+        let steps = lines
+            .filter(|line| !line.trim().is_empty())
+            .map(parse_step)
+            .collect::<Option<Vec<_>>>()
+            .ok_or("replay has invalid steps")?;
+
+        Ok(Replay {
+            layout,
+            rules,
+            tile_set,
+            blind,
+            seed,
+            steps,
+        })
+    }
+
+    /// Returns the board as it was dealt when the game started.
+    pub fn deal(&self) -> Result<Board, &'static str> {
+        let (rules, tile_set) = self.settings()?;
+        Board::with_deal(rules, tile_set, self.blind, self.seed)
+    }
+
+    /// Deals the game again on a board that is already shown, keeping how it is shown.
+    pub fn deal_on(&self, board: &mut Board) -> Result<(), &'static str> {
+        let (rules, tile_set) = self.settings()?;
+        board.redeal(rules, tile_set, self.blind, self.seed)
+    }
+
+    fn settings(&self) -> Result<(RuleSet, TileSet), &'static str> {
+        if self.layout != leaderboard::LAYOUT {
+            return Err("replay is on an unknown layout");
+        }
+        let rules = RuleSet::from_name(&self.rules).ok_or("replay has unknown rules")?;
+        let tile_set = TileSet::presets()
+            .into_iter()
+            .find(|tile_set| tile_set.name() == self.tile_set)
+            .ok_or("replay has an unknown tile set")?;
+        Ok((rules, tile_set))
+    }
+
+    /**
+     * Plays the game again from the deal without a window, and returns the time it took to
+     * clear the board. Fails when an action couldn't have been done at that point of the game,
+     * or when the board isn't cleared in the end.
+     */
+    pub fn verify(&self) -> Result<Duration, &'static str> {
+        let mut board = self.deal()?;
+        let mut last_millis = 0;
+        let mut cleared_at = None;
+        for step in &self.steps {
+            if step.millis < last_millis {
+                return Err("replay steps are out of order");
+            }
+            last_millis = step.millis;
+
+            apply(&mut board, step.action)?;
+            if !board.tiles.is_cleared() {
+                cleared_at = None;
+            } else if cleared_at.is_none() {
+                cleared_at = Some(step.millis);
+            }
+        }
+
+        cleared_at
+            .map(Duration::from_millis)
+            .ok_or("replay doesn't clear the board")
+    }
+}
+
+/// Does the action on the board, failing when it couldn't have been done at this point.
+pub(super) fn apply(board: &mut Board, action: Action) -> Result<(), &'static str> {
+    match action {
+        Action::Select(tile) => {
+            let tile = board.free_tile(tile)?;
+            match board.selected_tile {
+                Some(selected) if selected != tile => {
+                    return Err("replay selects a second tile without a match");
+                }
+                _ => board.toggle_selection(tile),
+            }
+        }
+        Action::Match(tile1, tile2) => {
+            let (tile1, tile2) = (board.free_tile(tile1)?, board.free_tile(tile2)?);
+            if tile1 == tile2 || !board.tiles.are_matching(tile1, tile2) {
+                return Err("replay matches tiles that don't match");
+            }
+            let _ = board.play_pair(tile1, tile2);
+        }
+        Action::Undo => board.undo(),
+        Action::Hint => board.highlight_possible_matches(),
+        Action::Shuffle(seed) => board.refill_with_seed(seed)?,
+    }
+    Ok(())
+}
+
+fn parse_step(line: &str) -> Option<Step> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let (millis, code, numbers) = match words.as_slice() {
+        [millis, code, numbers @ ..] => (millis.parse().ok()?, *code, numbers),
+        _ => return None,
+    };
+    let tile = |index: usize| numbers[index].parse().ok();
+
+    let action = match (code, numbers.len()) {
+        ("s", 1) => Action::Select(tile(0)?),
+        ("m", 2) => Action::Match(tile(0)?, tile(1)?),
+        ("u", 0) => Action::Undo,
+        ("h", 0) => Action::Hint,
+        ("x", 1) => Action::Shuffle(numbers[0].parse().ok()?),
+        _ => return None,
+    };
+    Some(Step { millis, action })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        rand::{
+            rngs::SmallRng,
+            SeedableRng,
+        },
+        crate::game::{
+            Game,
+            GameOver,
+        },
+        super::{
+            super::{
+                computer::{
+                    choose_pair,
+                    Strength,
+                },
+                tiles::TileId,
+                Board,
+            },
+            Action,
+            Replay,
+            Step,
+        },
+    };
+
+    /// Plays the board until no pair is left, returning whether it was cleared.
+    fn play_out(board: &mut Board, rng: &mut SmallRng) -> bool {
+        while let Some((tile1, tile2)) = choose_pair(&mut board.tiles, Strength::Solver, rng) {
+            if board.play_pair(tile1, tile2).is_err() {
+                break;
+            }
+        }
+        board.tiles.is_cleared()
+    }
+
+    /// Clicks the middle of the face of the tile, as a player would once the deal has dropped in.
+    fn click(board: &mut Board, tile: TileId) -> Result<(), GameOver> {
+        board.finish_animations();
+        let center = board.tiles.face_rect(tile).center();
+        board.click(center.x(), center.y())
+    }
+
+    /// Returns the replay of a cleared game that uses every kind of action.
+    fn won_replay() -> Replay {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for seed in 0..20 {
+            let mut board = Board::new();
+            board.reset_with_seed(seed).unwrap();
+
+            board.highlight_possible_matches();
+            let (tile1, tile2) = choose_pair(&mut board.tiles, Strength::Solver, &mut rng).unwrap();
+            // select, deselect and select again
+            for _ in 0..3 {
+                click(&mut board, tile1).unwrap();
+            }
+            let _ = click(&mut board, tile2);
+            board.undo();
+            board.refill_with_seed(seed).unwrap();
+
+            if play_out(&mut board, &mut rng) {
+                return board.replay();
+            }
+        }
+        panic!("no deal was cleared");
+    }
+
+    #[test]
+    fn replays_are_written_and_read_back() {
+        let replay = Replay {
+            layout: "turtle".to_string(),
+            rules: "3D".to_string(),
+            tile_set: "Any honour".to_string(),
+            blind: true,
+            seed: 42,
+            steps: vec![
+                Step { millis: 0, action: Action::Hint },
+                Step { millis: 900, action: Action::Select(3) },
+                Step { millis: 1500, action: Action::Match(3, 17) },
+                Step { millis: 2000, action: Action::Undo },
+                Step { millis: 2500, action: Action::Shuffle(u64::MAX) },
+            ],
+        };
+
+        assert_eq!(Replay::parse(&replay.to_text()), Ok(replay));
+        assert!(Replay::parse("mahjong-replay 1\nlayout turtle\n").is_err());
+        assert!(Replay::parse("something else").is_err());
+    }
+
+    #[test]
+    fn recorded_wins_are_verified() {
+        let replay = won_replay();
+        let last_millis = replay.steps.last().unwrap().millis;
+        let is_selection = |step: &&Step| matches!(step.action, Action::Select(_));
+        assert_eq!(replay.steps.iter().filter(is_selection).count(), 3);
+
+        assert_eq!(replay.verify().map(|time| time.as_millis() as u64), Ok(last_millis));
+    }
+
+    #[test]
+    fn illegal_and_unfinished_games_are_rejected() {
+        let mut replay = won_replay();
+
+        // the tiles under the top of the turtle can't be played first
+        let mut illegal = replay.clone();
+        illegal.steps.insert(0, Step { millis: 0, action: Action::Match(139, 140) });
+        assert!(illegal.verify().is_err());
+
+        let mut wrong_deal = replay.clone();
+        wrong_deal.seed += 1;
+        assert!(wrong_deal.verify().is_err());
+
+        replay.steps.pop();
+        assert_eq!(replay.verify(), Err("replay doesn't clear the board"));
+    }
+}
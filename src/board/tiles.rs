@@ -1,129 +1,347 @@
+mod camera;
 mod models;
+mod motion;
 mod position;
+mod refill;
+mod rules;
 mod shuffle;
+mod solver;
+mod textures;
+mod theme;
+mod tileset;
+mod tween;
 mod types;
 
 use {
     std::{
-        cmp::Ordering,
-        collections::HashMap,
+        cmp::Reverse,
         iter::{
             Enumerate,
             FilterMap,
         },
-        path::{
-            Path,
-            PathBuf,
-        },
         slice::Iter,
+        time::{
+            Duration,
+            Instant,
+        },
     },
     sdl2::{
-        image::LoadTexture,
-        render::{
-            Texture,
-            TextureCreator,
-            WindowCanvas,
-        },
-        video::WindowContext,
+        rect::Rect,
+        render::WindowCanvas,
     },
     self::{
         models::Models,
+        motion::{
+            Look,
+            Motion,
+        },
         position::{
             Direction,
             Neighbour,
-            Position,
         },
-        shuffle::get_shuffled_types,
-        types::TileType,
+        refill::get_refilled_types,
+        rules::FreeRule,
+        shuffle::{
+            get_seeded_shuffled_types,
+            get_shuffled_groups,
+        },
         PlayState::*,
     }
 };
 
-static ERROR_MESSAGE: &str = "error loading texture";
+pub use self::{
+    camera::Camera,
+    models::ViewAngle,
+    position::Position,
+    rules::RuleSet,
+    solver::{
+        is_solvable,
+        matching_pairs,
+    },
+    textures::{
+        Sprite,
+        TileTextures,
+    },
+    theme::{
+        Theme,
+        TileSize,
+    },
+    tileset::TileSet,
+    types::TileType,
+};
+
+static REVEAL_DURATION: Duration = Duration::from_millis(300);
 
-pub struct Tiles<'tc> {
+/// How long after one another the tiles of a deal drop, in the order they are drawn.
+static DEAL_STAGGER: Duration = Duration::from_millis(4);
+
+#[derive(Clone)]
+pub struct Tiles {
     positions: Vec<Position>,
     neighbours: Vec<Vec<Neighbour>>,
     types: Vec<TileType>,
     states: Vec<PlayState>,
+    reveal_times: Vec<Option<Instant>>,
+    motions: Vec<Option<Motion>>,
+    /// The steps pulsing highlights were at when last updated.
+    pulse_steps: Vec<Option<u32>>,
     models: Models,
-    textures: HashMap<TextureId, Texture<'tc>>,
+    camera: Camera,
+    rules: RuleSet,
+    tile_set: TileSet,
+    blind: bool,
 }
 
-impl<'tc> Tiles<'tc> {
-    pub fn new(
-        raw_positions: &mut [(u8, u8, u8); 144],
-        texture_creator: &'tc TextureCreator<WindowContext>,
-    ) -> Self {
-        // NOTE: sorting currently needed for rendering
-        // NOTE: also needed now for searching for a tile based on coords
-        //       maybe this should just be left in?
-        raw_positions.sort_by(|&(x1, y1, z1), &(x2, y2, z2)| {
-            if (z1, x2, y1) < (z2, x1, y2) {
-                Ordering::Less
-            } else {
-                Ordering::Greater
-            }
-        });
+impl Tiles {
+    /// Returns the tiles of the layout with the types of the tile set in order, to be dealt
+    /// once the rules and the tile set are settled.
+    pub fn new(raw_positions: &mut [(u8, u8, u8); 144], rules: RuleSet) -> Self {
+        // the tiles are numbered layer by layer, which replays rely on, while the order they
+        // are drawn and picked in is worked out by the models
+        raw_positions.sort_by_key(|&(x, y, z)| (z, Reverse(x), y));
 
         let positions = raw_positions.iter().map(Position::from).collect::<Vec<_>>();
 
         let neighbours = create_neighbour_list(&positions);
-        let types = get_shuffled_types(&positions, &neighbours);
+        let tile_set = TileSet::default();
+        let types = tile_set.types();
         let states = vec![Default::default(); 144];
         let models = Models::new(raw_positions);
-        let textures = create_textures(texture_creator);
+        let mut camera = Camera::new();
+        camera.fit(models.area());
 
         let mut tiles = Tiles {
             positions,
             neighbours,
             types,
             states,
+            reveal_times: vec![None; 144],
+            motions: vec![None; 144],
+            pulse_steps: vec![None; 144],
             models,
-            textures,
+            camera,
+            rules,
+            tile_set,
+            blind: false,
         };
 
-        for tile in 0..144 {
-            tiles.update_neighbouring_tile_states(tile);
-        }
+        tiles.reset_states();
 
         tiles
     }
 
-    pub fn reset(&mut self) {
-        self.types = get_shuffled_types(&self.positions, &self.neighbours);
-        
+    /// Deals the same types for the same seed, with the same rules and tile set. The tiles are
+    /// left as they are when no deal is found.
+    pub fn reset_with_seed(&mut self, seed: u64) -> Result<(), &'static str> {
+        self.types = get_seeded_shuffled_types(
+            &self.positions,
+            &self.neighbours,
+            self.rules.free_rule(),
+            &self.tile_set,
+            seed,
+        )?;
+        self.reset_states();
+        self.deal(|_| true);
+        Ok(())
+    }
+
+    /// Deals the types so that each three consecutive types can be played one after another.
+    pub fn reset_with_triples(&mut self, types: Vec<TileType>) -> Result<(), &'static str> {
+        self.types = get_shuffled_groups(
+            &self.positions,
+            &self.neighbours,
+            self.rules.free_rule(),
+            types,
+            3,
+        )?;
+        self.reset_states();
+        self.deal(|_| true);
+        Ok(())
+    }
+
+    /// Deals new tiles into the positions of played tiles, keeping the board solvable.
+    pub fn refill(&mut self, seed: u64) -> Result<(), &'static str> {
+        let current = self
+            .types
+            .iter()
+            .zip(self.states.iter())
+            .map(|(&tile_type, &state)| if state == Played { None } else { Some(tile_type) })
+            .collect::<Vec<_>>();
+
+        self.types = get_refilled_types(
+            &self.positions,
+            &self.neighbours,
+            self.rules.free_rule(),
+            &self.tile_set,
+            &current,
+            seed,
+        )?;
+        self.reset_states();
+        self.deal(|tile| current[tile].is_none());
+        Ok(())
+    }
+
+    /// Drops the tiles onto the board one after another, in the order they are drawn.
+    fn deal<F: Fn(usize) -> bool>(&mut self, dealt: F) {
+        let now = Instant::now();
+        let order = self.models.order().iter().filter(|&&index| dealt(index));
+        for (rank, &index) in order.enumerate() {
+            self.motions[index] = Some(Motion::deal(now, DEAL_STAGGER * rank as u32));
+        }
+    }
+
+    fn reset_states(&mut self) {
         for tile in 0..144 {
             self.states[tile] = Blocked;
         }
         for tile in 0..144 {
             self.update_neighbouring_tile_states(tile);
         }
+        for reveal_time in &mut self.reveal_times {
+            *reveal_time = None;
+        }
+        for motion in &mut self.motions {
+            *motion = None;
+        }
     }
 
-    pub fn render(&mut self, canvas: &mut WindowCanvas) {
-        use self::TextureId::*;
+    pub fn rules(&self) -> RuleSet {
+        self.rules
+    }
 
-        let side_tex = &self.textures[&Side];
-        let bottom_tex = &self.textures[&Bottom];
+    pub fn set_rules(&mut self, rules: RuleSet) {
+        self.rules = rules;
+    }
 
-        let iter = self
-            .types
+    pub fn is_blind(&self) -> bool {
+        self.blind
+    }
+
+    /// Sets whether blocked tiles lie face-down, so a tile only shows its face once it is free.
+    pub fn set_blind(&mut self, blind: bool) {
+        self.blind = blind;
+    }
+
+    pub fn tile_set(&self) -> &TileSet {
+        &self.tile_set
+    }
+
+    pub fn set_tile_set(&mut self, tile_set: TileSet) -> Result<(), &'static str> {
+        tile_set.validate(self.positions.len())?;
+        self.tile_set = tile_set;
+        Ok(())
+    }
+
+    /// Draws the tiles, which are kept in a layer while they don't change.
+    pub fn render(&self, canvas: &mut WindowCanvas, textures: &TileTextures) {
+        textures.draw_layer(canvas, self.sprites(Instant::now()));
+    }
+
+    /// Returns whether a pulsing highlight changed since the last update, as those are drawn
+    /// on the slow ticks of the window rather than every frame.
+    pub fn update(&mut self) -> bool {
+        let now = Instant::now();
+        let steps = self
+            .motions
+            .iter()
+            .map(|motion| motion.and_then(|motion| motion.pulse_step(now)));
+        let changed = !steps.clone().eq(self.pulse_steps.iter().copied());
+        if changed {
+            self.pulse_steps = steps.collect();
+        }
+        changed
+    }
+
+    /// Returns whether tiles are moving or turning over.
+    pub fn is_animating(&self) -> bool {
+        let now = Instant::now();
+        let revealing = self
+            .reveal_times
             .iter()
-            .zip(self.models.iter())
-            .zip(self.states.iter());
+            .flatten()
+            .any(|&time| now.saturating_duration_since(time) < REVEAL_DURATION);
+        self.motions.iter().flatten().any(|motion| motion.is_moving(now))
+            || (self.blind && revealing)
+    }
 
-        for ((tile_type, model), state) in iter {
-            if *state == Played {
-                continue;
+    /// Returns the images the tiles are drawn with at the time, in the order they are drawn.
+    /// Tiles leaving the board are drawn over the others.
+    fn sprites(&self, now: Instant) -> Vec<Sprite> {
+        let mut sprites = Vec::new();
+        let mut leaving = Vec::new();
+
+        for &index in self.models.order() {
+            match (self.states[index], self.motions[index]) {
+                (Played, Some(Motion::Leave(_))) => leaving.push(index),
+                (Played, _) => {}
+                _ => self.push_sprites(&mut sprites, index, now),
             }
+        }
+        for index in leaving {
+            self.push_sprites(&mut sprites, index, now);
+        }
 
-            let face_tex = &self.textures[&Face(*tile_type, model.is_highlighted())];
+        sprites
+    }
 
-            let _ = canvas.copy(side_tex, None, Some(model.side()));
-            let _ = canvas.copy(bottom_tex, None, Some(model.bottom()));
-            let _ = canvas.copy(face_tex, None, Some(model.face()));
+    fn push_sprites(&self, sprites: &mut Vec<Sprite>, index: usize, now: Instant) {
+        let motion = self.motions[index];
+        let look = match motion.map_or(Some(Look::rest()), |motion| motion.look(now)) {
+            Some(look) => look,
+            None => return,
+        };
+        let (model, state) = (&self.models[index], self.states[index]);
+        let place = |rect| self.camera.canvas_rect(look.place(rect, model.face()));
+
+        let flips = self.models.angle().flips();
+        if let Some(side) = model.side() {
+            sprites.push(Sprite::side(place(side), flips));
+        }
+        if let Some(bottom) = model.bottom() {
+            sprites.push(Sprite::bottom(place(bottom), flips));
         }
+
+        let tile_type = self.types[index];
+        let face = place(model.face());
+        let highlight = if model.is_highlighted() {
+            (motion.map_or(1.0, |motion| motion.highlight(now)) * 255.0) as u8
+        } else {
+            0
+        };
+
+        sprites.push(if !self.blind {
+            Sprite::face(tile_type, highlight, face)
+        } else if state == Blocked {
+            Sprite::back(face)
+        } else {
+            let progress = self.reveal_times[index].map_or(1.0, |reveal_time| {
+                let elapsed = now.saturating_duration_since(reveal_time);
+                elapsed.as_secs_f32() / REVEAL_DURATION.as_secs_f32()
+            });
+            // the tile turns over by narrowing the back and then widening the face
+            if progress < 0.5 {
+                Sprite::back(narrow_rect(face, 1.0 - progress * 2.0))
+            } else {
+                let face = narrow_rect(face, (progress * 2.0 - 1.0).min(1.0));
+                Sprite::face(tile_type, highlight, face)
+            }
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn tile_type(&self, tile: TileId) -> TileType {
+        self.types[tile.0]
+    }
+
+    pub fn tiles_left(&self) -> usize {
+        self.states.iter().filter(|&&state| state != Played).count()
+    }
+
+    pub fn is_cleared(&self) -> bool {
+        self.states.iter().all(|&state| state == Played)
     }
 
     pub fn play_tile(&mut self, tile: TileId) {
@@ -136,19 +354,79 @@ impl<'tc> Tiles<'tc> {
         self.update_neighbouring_tile_states(tile.0);
     }
 
+    /// Lays the tiles out again to fill a canvas of the given size, keeping the zoom.
+    pub fn fit_canvas(&mut self, width: u32, height: u32) {
+        self.models.fit_canvas(width, height);
+        self.camera.fit(self.models.area());
+    }
+
+    /// Lays the tiles out again as seen from the direction.
+    pub fn set_view_angle(&mut self, angle: ViewAngle) {
+        self.models.set_angle(angle);
+    }
+
+    /// Lays the tiles out again for the size the theme draws them at.
+    pub fn set_tile_size(&mut self, tile_size: TileSize) {
+        self.models.set_tile_size(tile_size);
+    }
+
+    /// Returns where the face of the tile is drawn.
+    pub fn face_rect(&self, tile: TileId) -> Rect {
+        self.camera.canvas_rect(self.models[tile.0].face())
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    /// Highlights the tile as a hint, which pulses.
     pub fn highlight_tile(&mut self, tile: TileId) {
         self.models[tile.0].highlight();
+        self.motions[tile.0] = Some(Motion::pulse(Instant::now()));
     }
 
     pub fn dehighlight_tile(&mut self, tile: TileId) {
-        self.models[tile.0].dehighlight()
+        self.models[tile.0].dehighlight();
+        if let Some(Motion::Pulse(_)) = self.motions[tile.0] {
+            self.motions[tile.0] = None;
+        }
+    }
+
+    /// Highlights the tile and lifts it.
+    pub fn select_tile(&mut self, tile: TileId) {
+        self.models[tile.0].highlight();
+        self.motions[tile.0] = Some(Motion::lift(Instant::now(), self.motions[tile.0]));
+    }
+
+    pub fn deselect_tile(&mut self, tile: TileId) {
+        self.models[tile.0].dehighlight();
+        self.motions[tile.0] = Motion::lower(Instant::now(), self.motions[tile.0]);
+    }
+
+    /// Plays the tile, which is shown leaving the board.
+    pub fn remove_tile(&mut self, tile: TileId) {
+        self.play_tile(tile);
+        self.motions[tile.0] = Some(Motion::leave(Instant::now(), self.motions[tile.0]));
+    }
+
+    /// Takes the tile back, which is shown coming back to its place.
+    pub fn put_back_tile(&mut self, tile: TileId) {
+        self.reset_tile(tile);
+        self.motions[tile.0] = Some(Motion::put_back(Instant::now(), self.motions[tile.0]));
+    }
+
+    /// Shows every tile where its motion ends, such as after jumping through a game.
+    pub fn finish_motions(&mut self) {
+        for motion in &mut self.motions {
+            *motion = motion.and_then(Motion::finished);
+        }
     }
 
     pub fn are_matching(&self, tile1: TileId, tile2: TileId) -> bool {
-        self.types[tile1.0].matches(self.types[tile2.0])
+        self.tile_set.matches(self.types[tile1.0], self.types[tile2.0])
     }
 
-    pub fn playable_tiles(&self) -> PlayableTiles {
+    pub fn playable_tiles(&self) -> PlayableTiles<'_> {
         PlayableTiles {
             iter: self
                 .states
@@ -165,49 +443,64 @@ impl<'tc> Tiles<'tc> {
     }
 
     pub fn find_playable_tile_by_coord(&self, x: i32, y: i32) -> Option<TileId> {
-        for (index, model) in self.models.iter().enumerate().rev() {
-            if self.states[index] == Playable && model.hit_test(x, y) {
-                return Some(TileId(index));
-            }
-        }
-        None
+        // the tile drawn on top at the point is picked, even when it is blocked and a free tile
+        // is drawn under it; tiles are hit where they are drawn, so tiles that are yet to be
+        // dealt can't be
+        let now = Instant::now();
+        let point = self.camera.layout_point(x, y);
+        let is_hit = |index: usize| {
+            let look = self.motions[index].map_or(Some(Look::rest()), |motion| motion.look(now));
+            let model = &self.models[index];
+            look.and_then(|look| look.rest_point(point, model.face()))
+                .is_some_and(|(x, y)| model.hit_test(x, y))
+        };
+        self.models
+            .order()
+            .iter()
+            .rev()
+            .find(|&&index| self.states[index] != Played && is_hit(index))
+            .filter(|&&index| self.states[index] == Playable)
+            .map(|&index| TileId(index))
     }
 
     fn update_neighbouring_tile_states(&mut self, tile: usize) {
-        use self::Direction::*;
+        let rule = self.rules.free_rule();
 
         for neighbour in &self.neighbours[tile] {
             match self.states[neighbour.id] {
                 Blocked | Playable => {
-                    let any_up = self.any_unplayed_neighbour_in_direction(neighbour.id, Up);
-                    let any_left = self.any_unplayed_neighbour_in_direction(neighbour.id, Left);
-                    let any_right = self.any_unplayed_neighbour_in_direction(neighbour.id, Right);
+                    let states = &self.states;
+                    let is_unplayed = |id: usize| states[id] != Played;
 
-                    if any_up || (any_left && any_right) {
-                        self.states[neighbour.id] = Blocked;
-                    } else {
+                    let (position, neighbours) =
+                        (self.positions[neighbour.id], &self.neighbours[neighbour.id]);
+
+                    if rule.is_free(position, neighbours, &is_unplayed) {
+                        if self.states[neighbour.id] == Blocked {
+                            self.reveal_times[neighbour.id] = Some(Instant::now());
+                        }
                         self.states[neighbour.id] = Playable;
+                    } else {
+                        self.states[neighbour.id] = Blocked;
                     }
                 }
                 Played => (),
             }
         }
     }
+}
 
-    fn any_unplayed_neighbour_in_direction(&self, tile: usize, direction: Direction) -> bool {
-        self.neighbours[tile]
-            .iter()
-            .filter(|neighbour| neighbour.direction == direction)
-            .any(|neighbour| self.states[neighbour.id] != Played)
-    }
+/// Narrows a rect around its center to the given fraction of its width.
+fn narrow_rect(rect: Rect, fraction: f32) -> Rect {
+    let width = (rect.width() as f32 * fraction) as u32;
+    Rect::new(rect.x() + (rect.width() - width) as i32 / 2, rect.y(), width.max(1), rect.height())
 }
 
+type PlayableFilter = for<'r> fn((usize, &'r PlayState)) -> Option<TileId>;
+
 #[derive(Clone, Debug)]
 pub struct PlayableTiles<'a> {
-    iter: FilterMap<
-        Enumerate<Iter<'a, PlayState>>,
-        for<'r> fn((usize, &'r PlayState)) -> Option<TileId>,
-    >,
+    iter: FilterMap<Enumerate<Iter<'a, PlayState>>, PlayableFilter>,
 }
 
 impl Iterator for PlayableTiles<'_> {
@@ -221,13 +514,24 @@ impl Iterator for PlayableTiles<'_> {
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct TileId(usize);
 
+impl TileId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
 fn create_neighbour_list(positions: &[Position]) -> Vec<Vec<Neighbour>> {
     let mut neighbour_list = vec![Vec::new(); 144];
 
     for tile1 in 0..144 {
         for tile2 in 0..144 {
-            if let Some(direction) = positions[tile1].neighbours(positions[tile2]) {
-                neighbour_list[tile1].push(Neighbour::new(tile2, direction));
+            let (position1, position2) = (positions[tile1], positions[tile2]);
+            if let Some(direction) = position1.neighbours(position2) {
+                neighbour_list[tile1].push(if position1.is_offset(position2) {
+                    Neighbour::offset(tile2, direction)
+                } else {
+                    Neighbour::new(tile2, direction)
+                });
             }
         }
     }
@@ -235,61 +539,273 @@ fn create_neighbour_list(positions: &[Position]) -> Vec<Vec<Neighbour>> {
     neighbour_list
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 enum PlayState {
+    #[default]
     Blocked,
     Playable,
     Played,
 }
 
-impl Default for PlayState {
-    fn default() -> PlayState {
-        Blocked
+#[cfg(test)]
+mod tests {
+    use {
+        std::time::{
+            Duration,
+            Instant,
+        },
+        crate::board::get_raw_positios,
+        super::{
+            motion::Motion,
+            PlayState::*,
+            RuleSet,
+            Sprite,
+            TileId,
+            Tiles,
+        },
+    };
+
+    fn tiles() -> Tiles {
+        Tiles::new(&mut get_raw_positios(), Default::default())
     }
-}
 
-fn create_textures<'tc>(
-    texture_creator: &'tc TextureCreator<WindowContext>,
-) -> HashMap<TextureId, Texture<'tc>> {
-    use self::TextureId::*;
+    fn tile_at(tiles: &Tiles, x: u8, y: u8, z: u8) -> usize {
+        tiles
+            .positions
+            .iter()
+            .position(|p| (p.x, p.y, p.z) == (x, y, z))
+            .unwrap()
+    }
 
-    let mut textures = HashMap::new();
+    #[test]
+    fn initial_playable_tiles() {
+        let tiles = tiles();
+
+        let mut playable = tiles
+            .playable_tiles()
+            .map(|tile| {
+                let p = tiles.positions[tile.0];
+                (p.x, p.y, p.z)
+            }).collect::<Vec<_>>();
+        playable.sort();
+
+        #[rustfmt::skip]
+        let expected = vec![
+            (0, 7, 0), (4, 0, 0), (4, 14, 0), (6, 4, 0), (6, 10, 0), (8, 2, 0), (8, 12, 0),
+            (10, 2, 1), (10, 4, 1), (10, 6, 1), (10, 8, 1), (10, 10, 1), (10, 12, 1),
+            (12, 4, 2), (12, 6, 2), (12, 8, 2), (12, 10, 2), (15, 7, 4),
+            (18, 4, 2), (18, 6, 2), (18, 8, 2), (18, 10, 2),
+            (20, 2, 1), (20, 4, 1), (20, 6, 1), (20, 8, 1), (20, 10, 1), (20, 12, 1),
+            (22, 2, 0), (22, 12, 0), (24, 4, 0), (24, 10, 0), (26, 0, 0), (26, 14, 0),
+            (28, 7, 0),
+        ];
+        assert_eq!(playable, expected);
+    }
 
-    for tile_type in TileType::iter() {
-        let mut texture_path_buf = PathBuf::from("img/");
-        texture_path_buf.push(tile_type.filename_texture());
-        let texture_path = texture_path_buf.as_path();
+    #[test]
+    fn playing_top_tile_frees_tiles_below() {
+        let mut tiles = tiles();
+        let top = tile_at(&tiles, 15, 7, 4);
+        let below = [
+            tile_at(&tiles, 14, 6, 3),
+            tile_at(&tiles, 16, 6, 3),
+            tile_at(&tiles, 14, 8, 3),
+            tile_at(&tiles, 16, 8, 3),
+        ];
+
+        for &tile in &below {
+            assert_eq!(tiles.states[tile], Blocked);
+        }
 
-        let mut texture = texture_creator
-            .load_texture(texture_path)
-            .expect(ERROR_MESSAGE);
-        texture.set_color_mod(255, 127, 127);
-        textures.insert(Face(*tile_type, true), texture);
+        tiles.play_tile(TileId(top));
 
-        let texture = texture_creator
-            .load_texture(texture_path)
-            .expect(ERROR_MESSAGE);
-        textures.insert(Face(*tile_type, false), texture);
+        assert_eq!(tiles.states[top], Played);
+        for &tile in &below {
+            assert_eq!(tiles.states[tile], Playable);
+        }
     }
 
-    let side_texture = texture_creator
-        .load_texture(Path::new("img/TileSide.png"))
-        .expect(ERROR_MESSAGE);
-    let bottom_texture = texture_creator
-        .load_texture(Path::new("img/TileBottom.png"))
-        .expect(ERROR_MESSAGE);
+    #[test]
+    fn playing_side_tile_frees_horizontal_neighbour() {
+        let mut tiles = tiles();
+        let edge = tile_at(&tiles, 0, 7, 0);
+        let next = tile_at(&tiles, 2, 7, 0);
 
-    textures.insert(Side, side_texture);
-    textures.insert(Bottom, bottom_texture);
+        assert_eq!(tiles.states[next], Blocked);
 
-    textures.shrink_to_fit();
+        tiles.play_tile(TileId(edge));
 
-    textures
-}
+        assert_eq!(tiles.states[next], Playable);
+    }
+
+    #[test]
+    fn tile_stays_blocked_while_covered() {
+        let mut tiles = tiles();
+        let covered = tile_at(&tiles, 10, 2, 0);
+        let left = tile_at(&tiles, 8, 2, 0);
+
+        tiles.play_tile(TileId(left));
+
+        assert_eq!(tiles.states[covered], Blocked);
+    }
+
+    #[test]
+    fn resetting_tile_blocks_neighbours_again() {
+        let mut tiles = tiles();
+        let edge = tile_at(&tiles, 0, 7, 0);
+        let next = tile_at(&tiles, 2, 7, 0);
+
+        tiles.play_tile(TileId(edge));
+        tiles.reset_tile(TileId(edge));
+
+        assert_eq!(tiles.states[edge], Playable);
+        assert_eq!(tiles.states[next], Blocked);
+    }
+
+    #[test]
+    fn resetting_top_tile_blocks_tiles_below() {
+        let mut tiles = tiles();
+        let top = tile_at(&tiles, 15, 7, 4);
+        let below = tile_at(&tiles, 14, 6, 3);
+
+        tiles.play_tile(TileId(top));
+        tiles.reset_tile(TileId(top));
+
+        assert_eq!(tiles.states[below], Blocked);
+    }
+
+    #[test]
+    fn reset_restores_initial_states() {
+        let mut tiles = tiles();
+        let initial = tiles.states.clone();
+
+        tiles.play_tile(TileId(tile_at(&tiles, 15, 7, 4)));
+        tiles.play_tile(TileId(tile_at(&tiles, 0, 7, 0)));
+        tiles.reset_with_seed(0).unwrap();
+
+        assert_eq!(tiles.states, initial);
+    }
+
+    #[test]
+    fn strict_holds_both_tails_only_by_the_rows_beside_them() {
+        let mut tiles = Tiles::new(&mut get_raw_positios(), RuleSet::Strict);
+        let left_tail = tile_at(&tiles, 0, 7, 0);
+        let right_tail = tile_at(&tiles, 28, 7, 0);
 
-#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
-enum TextureId {
-    Face(TileType, bool),
-    Bottom,
-    Side,
+        assert_eq!(tiles.states[left_tail], Playable);
+        assert_eq!(tiles.states[right_tail], Blocked);
+
+        tiles.play_tile(TileId(tile_at(&tiles, 26, 6, 0)));
+        assert_eq!(tiles.states[right_tail], Blocked);
+
+        tiles.play_tile(TileId(tile_at(&tiles, 26, 8, 0)));
+        assert_eq!(tiles.states[right_tail], Playable);
+    }
+
+    #[test]
+    fn classic_frees_both_tails() {
+        let tiles = tiles();
+
+        assert_eq!(tiles.states[tile_at(&tiles, 0, 7, 0)], Playable);
+        assert_eq!(tiles.states[tile_at(&tiles, 28, 7, 0)], Playable);
+    }
+
+    #[test]
+    fn freed_tiles_are_revealed() {
+        let mut tiles = tiles();
+        let top = tile_at(&tiles, 15, 7, 4);
+        let below = tile_at(&tiles, 14, 6, 3);
+        let side = tile_at(&tiles, 2, 7, 0);
+
+        assert!(tiles.reveal_times.iter().all(Option::is_none));
+
+        tiles.play_tile(TileId(top));
+        assert!(tiles.reveal_times[below].is_some());
+        assert!(tiles.reveal_times[side].is_none());
+
+        tiles.reset_with_seed(0).unwrap();
+        assert!(tiles.reveal_times.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn raised_tiles_are_picked_by_their_edges() {
+        let mut tiles = tiles();
+        let top = tile_at(&tiles, 15, 7, 4);
+        let side = tiles.models[top].side().unwrap();
+        let (x, y) = (side.x() + 1, side.center().y());
+
+        // the side of the top tile is drawn over the blocked tiles of the layer below
+        assert_eq!(tiles.find_playable_tile_by_coord(x, y), Some(TileId(top)));
+        let face = tiles.models[tile_at(&tiles, 14, 6, 3)].face();
+        assert_eq!(tiles.find_playable_tile_by_coord(face.x() + 1, face.y() + 1), None);
+
+        tiles.play_tile(TileId(top));
+        let picked = tiles.find_playable_tile_by_coord(x, y).unwrap();
+        assert_eq!(tiles.positions[picked.0].z, 3);
+    }
+
+    #[test]
+    fn the_layer_changes_only_while_tiles_move() {
+        let mut tiles = tiles();
+        let now = Instant::now();
+        let later = now + Duration::from_secs(1);
+        let sprites = tiles.sprites(now);
+        assert_eq!(tiles.sprites(later), sprites);
+
+        // the selected tile is lifted and highlighted, and comes back down when deselected
+        let top = TileId(tile_at(&tiles, 15, 7, 4));
+        let face = tiles.face_rect(top);
+        tiles.select_tile(top);
+        let selected = tiles.sprites(later);
+        assert_ne!(selected, sprites);
+        assert!(!selected.contains(&Sprite::face(tiles.types[top.0], 255, face)));
+        let lifted = Motion::lift(now, None).look(later).unwrap().place(face, face);
+        assert!(lifted.y() < face.y());
+        assert!(selected.contains(&Sprite::face(tiles.types[top.0], 255, lifted)));
+
+        tiles.deselect_tile(top);
+        assert_eq!(tiles.sprites(later + Duration::from_secs(1)), sprites);
+
+        // played tiles are drawn over the others until they are gone
+        tiles.remove_tile(top);
+        assert!(tiles.is_animating());
+        assert_eq!(tiles.sprites(Instant::now()).len(), sprites.len());
+        assert_eq!(tiles.sprites(later + Duration::from_secs(1)).len(), sprites.len() - 3);
+        tiles.finish_motions();
+        assert!(!tiles.is_animating());
+    }
+
+    #[test]
+    fn deals_drop_in_the_order_tiles_are_drawn() {
+        let mut tiles = tiles();
+        tiles.reset_with_seed(1).unwrap();
+        let now = Instant::now();
+
+        assert!(tiles.is_animating());
+        let dealt = tiles.sprites(now + Duration::from_secs(2));
+        assert!(tiles.sprites(now).len() < 10);
+        assert!(dealt.len() > 144);
+        tiles.finish_motions();
+        assert_eq!(tiles.sprites(now), dealt);
+    }
+
+    #[test]
+    fn clicks_go_to_tiles_where_they_are_drawn() {
+        let mut tiles = tiles();
+        tiles.reset_with_seed(1).unwrap();
+
+        // the top tile is dealt last, so it can't be clicked while it is yet to drop in
+        let top = TileId(tile_at(&tiles, 15, 7, 4));
+        let face = tiles.face_rect(top);
+        let center = face.center();
+        assert_ne!(tiles.find_playable_tile_by_coord(center.x(), center.y()), Some(top));
+        tiles.finish_motions();
+        assert_eq!(tiles.find_playable_tile_by_coord(center.x(), center.y()), Some(top));
+
+        // a lifted tile is hit above its place
+        assert_ne!(tiles.find_playable_tile_by_coord(center.x(), face.y() - 2), Some(top));
+        tiles.select_tile(top);
+        tiles.finish_motions();
+        assert_eq!(tiles.find_playable_tile_by_coord(center.x(), face.y() - 2), Some(top));
+    }
 }
@@ -0,0 +1,188 @@
+use sdl2::rect::Rect;
+
+static MIN_ZOOM: f64 = 1.0;
+static MAX_ZOOM: f64 = 4.0;
+
+/**
+ * Zooms and pans the board view. Tiles are laid out on the canvas as if the camera was reset,
+ * and the camera moves them to where they are drawn and clicked: a point is scaled by the zoom
+ * and then shifted by the offset. The view never leaves the board: the middle of the area the
+ * board is laid out in always shows a part of it.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    zoom: f64,
+    offset_x: f64,
+    offset_y: f64,
+    /// The area the board is laid out in.
+    area: Rect,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Camera {
+            zoom: MIN_ZOOM,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            area: Rect::new(0, 0, 1, 1),
+        }
+    }
+
+    /// Shows the whole board again.
+    pub fn reset(&mut self) {
+        *self = Camera {
+            area: self.area,
+            ..Camera::new()
+        };
+    }
+
+    /// Follows the board to the area it is laid out in now, keeping the zoom and the part of
+    /// the board in the middle of the view.
+    pub fn fit(&mut self, area: Rect) {
+        if self.zoom == MIN_ZOOM {
+            self.area = area;
+            self.reset();
+            return;
+        }
+
+        let (old_x, old_y) = self.view_center();
+        let fraction_x = (old_x - f64::from(self.area.x())) / f64::from(self.area.width());
+        let fraction_y = (old_y - f64::from(self.area.y())) / f64::from(self.area.height());
+
+        self.area = area;
+        let center = area.center();
+        let x = f64::from(area.x()) + fraction_x * f64::from(area.width());
+        let y = f64::from(area.y()) + fraction_y * f64::from(area.height());
+        self.offset_x = f64::from(center.x()) - x * self.zoom;
+        self.offset_y = f64::from(center.y()) - y * self.zoom;
+        self.keep_in_view();
+    }
+
+    /// Zooms in by the factor, or out by a factor below 1, keeping the point on the canvas in
+    /// place.
+    pub fn zoom_at(&mut self, factor: f64, x: i32, y: i32) {
+        let zoom = (self.zoom * factor).max(MIN_ZOOM).min(MAX_ZOOM);
+        let (x, y) = (f64::from(x), f64::from(y));
+        self.offset_x = x - (x - self.offset_x) * zoom / self.zoom;
+        self.offset_y = y - (y - self.offset_y) * zoom / self.zoom;
+        self.zoom = zoom;
+
+        if self.zoom == MIN_ZOOM {
+            self.reset();
+        }
+        self.keep_in_view();
+    }
+
+    /// Moves the view by the given distance on the canvas, which only has an effect when zoomed
+    /// in.
+    pub fn pan(&mut self, dx: i32, dy: i32) {
+        if self.zoom > MIN_ZOOM {
+            self.offset_x += f64::from(dx);
+            self.offset_y += f64::from(dy);
+            self.keep_in_view();
+        }
+    }
+
+    /// Returns the point of the board layout in the middle of the area.
+    fn view_center(&self) -> (f64, f64) {
+        let center = self.area.center();
+        (
+            (f64::from(center.x()) - self.offset_x) / self.zoom,
+            (f64::from(center.y()) - self.offset_y) / self.zoom,
+        )
+    }
+
+    /// Moves the view back onto the board when the middle of the area went off it.
+    fn keep_in_view(&mut self) {
+        let (area, zoom) = (self.area, self.zoom);
+        let center = area.center();
+        let clamp = |offset: f64, middle: i32, start: i32, end: i32| {
+            let middle = f64::from(middle);
+            offset
+                .max(middle - f64::from(end) * zoom)
+                .min(middle - f64::from(start) * zoom)
+        };
+        self.offset_x = clamp(self.offset_x, center.x(), area.left(), area.right());
+        self.offset_y = clamp(self.offset_y, center.y(), area.top(), area.bottom());
+    }
+
+    /// Returns where a rect of the board layout is drawn on the canvas.
+    pub fn canvas_rect(&self, rect: Rect) -> Rect {
+        let x = (f64::from(rect.x()) * self.zoom + self.offset_x).round() as i32;
+        let y = (f64::from(rect.y()) * self.zoom + self.offset_y).round() as i32;
+        let right = (f64::from(rect.right()) * self.zoom + self.offset_x).round() as i32;
+        let bottom = (f64::from(rect.bottom()) * self.zoom + self.offset_y).round() as i32;
+        Rect::new(x, y, (right - x).max(1) as u32, (bottom - y).max(1) as u32)
+    }
+
+    /// Returns the point of the board layout that is drawn at the point on the canvas.
+    pub fn layout_point(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            ((f64::from(x) - self.offset_x) / self.zoom).floor() as i32,
+            ((f64::from(y) - self.offset_y) / self.zoom).floor() as i32,
+        )
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        sdl2::rect::Rect,
+        super::Camera,
+    };
+
+    fn camera() -> Camera {
+        let mut camera = Camera::new();
+        camera.fit(Rect::new(0, 0, 400, 300));
+        camera
+    }
+
+    #[test]
+    fn zooming_keeps_the_point_in_place() {
+        let mut camera = camera();
+        camera.zoom_at(2.0, 100, 50);
+
+        assert_eq!(camera.canvas_rect(Rect::new(100, 50, 10, 10)), Rect::new(100, 50, 20, 20));
+        assert_eq!(camera.layout_point(120, 70), (110, 60));
+    }
+
+    #[test]
+    fn panning_needs_a_zoomed_in_view() {
+        let mut camera = camera();
+        camera.pan(30, 30);
+        assert_eq!(camera, self::camera());
+
+        camera.zoom_at(1.5, 0, 0);
+        camera.pan(30, -30);
+        assert_eq!(camera.canvas_rect(Rect::new(0, 0, 10, 10)), Rect::new(30, -30, 15, 15));
+
+        camera.zoom_at(0.1, 0, 0);
+        assert_eq!(camera, self::camera());
+    }
+
+    #[test]
+    fn panning_keeps_part_of_the_board_in_view() {
+        let mut camera = camera();
+        camera.zoom_at(2.0, 200, 150);
+        camera.pan(5000, -5000);
+
+        assert_eq!(camera.layout_point(200, 150), (0, 300));
+        assert_eq!(camera.canvas_rect(Rect::new(0, 290, 10, 10)), Rect::new(200, 130, 20, 20));
+    }
+
+    #[test]
+    fn resizing_keeps_the_zoom_and_the_middle_of_the_view() {
+        let mut camera = camera();
+        camera.zoom_at(2.0, 100, 75);
+        camera.fit(Rect::new(0, 0, 800, 600));
+
+        assert_eq!(camera.layout_point(400, 300), (300, 225));
+        assert_eq!(camera.canvas_rect(Rect::new(300, 225, 10, 10)), Rect::new(400, 300, 20, 20));
+    }
+}
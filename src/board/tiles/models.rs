@@ -1,67 +1,324 @@
 use {
-    std::ops::{
-        Deref,
-        DerefMut,
+    std::{
+        cmp::Reverse,
+        collections::BinaryHeap,
+        ops::{
+            Deref,
+            DerefMut,
+        },
     },
     sdl2::rect::Rect,
+    super::TileSize,
 };
 
-static TILE_WIDTH: u32 = 46;
-static TILE_HEIGHT: u32 = 57; // TODO: change texture height to even number
-static TILE_SIDE_WIDTH: u32 = 5;
-static TILE_BOTTOM_HEIGHT: u32 = 5;
-static TILE_SIDE_HEIGHT: u32 = TILE_HEIGHT + TILE_BOTTOM_HEIGHT;
+/// The space around the board on a canvas it is fitted to.
+static MARGIN_LEFT: i32 = 15;
+static MARGIN_TOP: i32 = 15;
+static MARGIN_RIGHT: u32 = 20;
+static MARGIN_BOTTOM: u32 = 17;
 
-pub struct Models(Vec<TileModel>);
+/// The canvas size the board is laid out for until it is fitted to another.
+static DEFAULT_CANVAS: (u32, u32) = (730, 500);
+
+/// The direction the board is seen from, which decides the edges of the tiles that show.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ViewAngle {
+    #[default]
+    BottomLeft,
+    BottomRight,
+    TopRight,
+    TopLeft,
+    /// Straight from above, with only the faces showing.
+    Above,
+}
+
+impl ViewAngle {
+    pub fn next(self) -> ViewAngle {
+        match self {
+            ViewAngle::BottomLeft => ViewAngle::BottomRight,
+            ViewAngle::BottomRight => ViewAngle::TopRight,
+            ViewAngle::TopRight => ViewAngle::TopLeft,
+            ViewAngle::TopLeft => ViewAngle::Above,
+            ViewAngle::Above => ViewAngle::BottomLeft,
+        }
+    }
+
+    /// Returns the directions higher layers are shifted in, away from the viewer.
+    fn shift(self) -> (i32, i32) {
+        match self {
+            ViewAngle::BottomLeft => (1, -1),
+            ViewAngle::BottomRight => (-1, -1),
+            ViewAngle::TopRight => (-1, 1),
+            ViewAngle::TopLeft => (1, 1),
+            ViewAngle::Above => (0, 0),
+        }
+    }
+
+    /// Returns whether the edge textures are mirrored horizontally and vertically, since they
+    /// are drawn for the side on the left and the bottom below the face.
+    pub fn flips(self) -> (bool, bool) {
+        let (dx, dy) = self.shift();
+        (dx < 0, dy > 0)
+    }
+}
+
+/**
+ * Where each tile is drawn, and in which order. The layout is scaled to fill as much of an area
+ * as it can without changing its shape, and is centered in it, so layouts of any footprint fit
+ * any window.
+ */
+#[derive(Clone)]
+pub struct Models {
+    positions: Vec<(u8, u8, u8)>,
+    models: Vec<TileModel>,
+    order: Vec<usize>,
+    angle: ViewAngle,
+    tile_size: TileSize,
+    area: Rect,
+}
 
 impl Models {
-    pub fn new(positions: &[(u8, u8, u8); 144]) -> Models {
-        let models = positions
+    pub fn new(positions: &[(u8, u8, u8)]) -> Models {
+        let mut models = Models {
+            positions: positions.to_vec(),
+            models: Vec::new(),
+            order: Vec::new(),
+            angle: ViewAngle::default(),
+            tile_size: TileSize::default(),
+            area: Rect::new(0, 0, 1, 1),
+        };
+        models.fit_canvas(DEFAULT_CANVAS.0, DEFAULT_CANVAS.1);
+        models
+    }
+
+    /// Returns the area the tiles are laid out in.
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+
+    pub fn angle(&self) -> ViewAngle {
+        self.angle
+    }
+
+    /// Lays the tiles out again as seen from the direction.
+    pub fn set_angle(&mut self, angle: ViewAngle) {
+        self.angle = angle;
+        self.fit(self.area);
+    }
+
+    /// Lays the tiles out again for tiles of the size, as a theme draws them.
+    pub fn set_tile_size(&mut self, tile_size: TileSize) {
+        self.tile_size = tile_size;
+        self.fit(self.area);
+    }
+
+    /// Returns the tiles in the order they are drawn, so every tile covers the tiles behind it.
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// Lays the tiles out on a canvas of the given size, leaving a margin around them.
+    pub fn fit_canvas(&mut self, width: u32, height: u32) {
+        let area = Rect::new(
+            MARGIN_LEFT,
+            MARGIN_TOP,
+            width.saturating_sub(MARGIN_LEFT as u32 + MARGIN_RIGHT).max(1),
+            height.saturating_sub(MARGIN_TOP as u32 + MARGIN_BOTTOM).max(1),
+        );
+        self.fit(area);
+    }
+
+    /// Lays the tiles out in the area, as large as they fit.
+    pub fn fit(&mut self, area: Rect) {
+        let (dx, dy) = self.angle.shift();
+        let (dx, dy) = (f64::from(dx), f64::from(dy));
+        let size = self.tile_size;
+        let (tile_width, tile_height) = (f64::from(size.width), f64::from(size.height));
+        let side_width = f64::from(size.side_width);
+        let bottom_height = f64::from(size.bottom_height);
+        // columns and rows are half a tile, rounded up
+        let column_width = f64::from(size.width.div_ceil(2));
+        let row_height = f64::from(size.height.div_ceil(2));
+
+        // the corner of the face at full scale
+        let face = |&(x, y, z): &(u8, u8, u8)| {
+            (
+                f64::from(x) * column_width + f64::from(z) * side_width * dx,
+                f64::from(y) * row_height + f64::from(z) * bottom_height * dy,
+            )
+        };
+        // the corners of the tile at full scale, with the side and bottom strips
+        let corners = |position: &(u8, u8, u8)| {
+            let (face_x, face_y) = face(position);
+            (
+                face_x - side_width * dx.max(0.0),
+                face_y - bottom_height * dy.max(0.0),
+                face_x + tile_width - side_width * dx.min(0.0),
+                face_y + tile_height - bottom_height * dy.min(0.0),
+            )
+        };
+        let (left, top, right, bottom) = self.positions.iter().map(corners).fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(left, top, right, bottom), (x1, y1, x2, y2)| {
+                (left.min(x1), top.min(y1), right.max(x2), bottom.max(y2))
+            },
+        );
+
+        let (width, height) = (f64::from(area.width()), f64::from(area.height()));
+        let scale = (width / (right - left)).min(height / (bottom - top));
+        let offset_x = f64::from(area.x()) + (width - (right - left) * scale) / 2.0;
+        let offset_y = f64::from(area.y()) + (height - (bottom - top) * scale) / 2.0;
+
+        let highlighted = self.models.iter().map(TileModel::is_highlighted).collect::<Vec<_>>();
+        self.models = self
+            .positions
             .iter()
-            .map(|&(x, y, z)| {
-                let model_x = i32::from(x) * 23 + i32::from(z) * TILE_SIDE_WIDTH as i32 + 20;
-                let model_y = i32::from(y) * 29 - i32::from(z) * TILE_BOTTOM_HEIGHT as i32 + 15;
-                TileModel::new(model_x, model_y)
-            }).collect::<Vec<_>>();
+            .map(|position| {
+                let (x, y) = face(position);
+                let model_x = offset_x + (x - left) * scale;
+                let model_y = offset_y + (y - top) * scale;
+                TileModel::new(model_x, model_y, scale, size, self.angle)
+            }).collect();
 
-        Models(models)
+        for (model, highlighted) in self.models.iter_mut().zip(highlighted) {
+            model.highlighted = highlighted;
+        }
+
+        self.order = painters_order(&self.positions, &self.models, self.angle);
+        self.area = area;
     }
 }
 
+/**
+ * Orders the tiles so that every tile is drawn after the tiles it overlaps on the canvas and
+ * stands in front of. Tiles are kept apart by their boxes, so of two overlapping tiles the one on
+ * the lower layer is behind, and on the same layer the one further from the viewer along the
+ * rows or the columns they don't share. Tiles that don't overlap are drawn by layer and number.
+ */
+fn painters_order(
+    positions: &[(u8, u8, u8)],
+    models: &[TileModel],
+    angle: ViewAngle,
+) -> Vec<usize> {
+    let (dx, dy) = angle.shift();
+    let is_behind = |tile: usize, other: usize| {
+        let (x1, y1, z1) = positions[tile];
+        let (x2, y2, z2) = positions[other];
+        let (x1, y1, x2, y2) = (i32::from(x1), i32::from(y1), i32::from(x2), i32::from(y2));
+        if z1 != z2 {
+            z1 < z2
+        } else if (y1 - y2).abs() >= 2 && dy != 0 {
+            -dy * y1 < -dy * y2
+        } else {
+            (x1 - x2).abs() >= 2 && -dx * x1 < -dx * x2
+        }
+    };
+
+    let num_tiles = positions.len();
+    let bounds = models.iter().map(TileModel::bounds).collect::<Vec<_>>();
+    let mut in_front = vec![Vec::new(); num_tiles];
+    let mut num_behind = vec![0; num_tiles];
+    for tile in 0..num_tiles {
+        for other in 0..num_tiles {
+            let (rect, other_rect) = (bounds[tile], bounds[other]);
+            let overlap = rect.left() < other_rect.right()
+                && other_rect.left() < rect.right()
+                && rect.top() < other_rect.bottom()
+                && other_rect.top() < rect.bottom();
+            if tile != other && overlap && is_behind(tile, other) {
+                in_front[tile].push(other);
+                num_behind[other] += 1;
+            }
+        }
+    }
+
+    let key = |tile: usize| Reverse((positions[tile].2, tile));
+    let mut ready = (0..num_tiles)
+        .filter(|&tile| num_behind[tile] == 0)
+        .map(key)
+        .collect::<BinaryHeap<_>>();
+    let mut order = Vec::with_capacity(num_tiles);
+    let mut drawn = vec![false; num_tiles];
+    while order.len() < num_tiles {
+        // tiles that overlap in a cycle are drawn from the lowest one
+        let next = match ready.pop() {
+            Some(Reverse((_, tile))) => tile,
+            None => (0..num_tiles)
+                .filter(|&tile| !drawn[tile])
+                .min_by_key(|&tile| key(tile).0)
+                .unwrap(),
+        };
+        if drawn[next] {
+            continue;
+        }
+        drawn[next] = true;
+        order.push(next);
+        for &tile in &in_front[next] {
+            num_behind[tile] -= 1;
+            if num_behind[tile] == 0 && !drawn[tile] {
+                ready.push(key(tile));
+            }
+        }
+    }
+    order
+}
+
 impl Deref for Models {
     type Target = [TileModel];
 
     fn deref(&self) -> &[TileModel] {
-        self.0.as_slice()
+        self.models.as_slice()
     }
 }
 
 impl DerefMut for Models {
     fn deref_mut(&mut self) -> &mut [TileModel] {
-        self.0.as_mut_slice()
+        self.models.as_mut_slice()
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TileModel {
     pub face_rect: Rect,
-    pub side_rect: Rect,
-    pub bottom_rect: Rect,
+    /// The side and bottom strips, which don't show when the board is seen from above.
+    pub side_rect: Option<Rect>,
+    pub bottom_rect: Option<Rect>,
     pub highlighted: bool,
+    /// Whether the edge textures are mirrored, as they are drawn.
+    flips: (bool, bool),
 }
 
 impl TileModel {
-    fn new(x: i32, y: i32) -> Self {
-        TileModel {
-            face_rect: Rect::new(x, y, TILE_WIDTH, TILE_HEIGHT),
-            side_rect: Rect::new(
-                x - TILE_SIDE_WIDTH as i32,
-                y,
-                TILE_SIDE_WIDTH,
-                TILE_SIDE_HEIGHT,
+    fn new(x: f64, y: f64, scale: f64, tile_size: TileSize, angle: ViewAngle) -> Self {
+        let size = |length: u32| ((f64::from(length) * scale).round() as u32).max(1);
+        let (x, y) = (x.round() as i32, y.round() as i32);
+        let (width, height) = (size(tile_size.width), size(tile_size.height));
+        let side_width = size(tile_size.side_width);
+        let bottom_height = size(tile_size.bottom_height);
+
+        let (side_x, bottom_y) = match angle.shift() {
+            (0, 0) => {
+                return TileModel {
+                    face_rect: Rect::new(x, y, width, height),
+                    side_rect: None,
+                    bottom_rect: None,
+                    highlighted: false,
+                    flips: angle.flips(),
+                }
+            }
+            (dx, dy) => (
+                if dx > 0 { x - side_width as i32 } else { x + width as i32 },
+                if dy < 0 { y + height as i32 } else { y - bottom_height as i32 },
             ),
-            bottom_rect: Rect::new(x, y + TILE_HEIGHT as i32, TILE_WIDTH, TILE_BOTTOM_HEIGHT),
+        };
+        let side_y = y.min(bottom_y);
+
+        TileModel {
+            face_rect: Rect::new(x, y, width, height),
+            side_rect: Some(Rect::new(side_x, side_y, side_width, height + bottom_height)),
+            bottom_rect: Some(Rect::new(x, bottom_y, width, bottom_height)),
             highlighted: false,
+            flips: angle.flips(),
         }
     }
 
@@ -69,11 +326,11 @@ impl TileModel {
         self.face_rect
     }
 
-    pub fn side(&self) -> Rect {
+    pub fn side(&self) -> Option<Rect> {
         self.side_rect
     }
 
-    pub fn bottom(&self) -> Rect {
+    pub fn bottom(&self) -> Option<Rect> {
         self.bottom_rect
     }
 
@@ -89,19 +346,130 @@ impl TileModel {
         self.highlighted = false;
     }
 
-    // TODO: better name needed?
+    /// Returns the rect that holds everything drawn of the tile.
+    pub fn bounds(&self) -> Rect {
+        let edges = [self.side_rect, self.bottom_rect];
+        let rects = edges.iter().flatten().chain(Some(&self.face_rect));
+        let left = rects.clone().map(Rect::left).min().unwrap();
+        let top = rects.clone().map(Rect::top).min().unwrap();
+        let right = rects.clone().map(Rect::right).max().unwrap();
+        let bottom = rects.map(Rect::bottom).max().unwrap();
+        Rect::new(left, top, (right - left) as u32, (bottom - top) as u32)
+    }
+
+    /**
+     * Returns whether the point is drawn by the tile: on the face, or on the side or bottom
+     * strip but not in the corner the edge textures leave transparent. The textures are cut
+     * along the diagonal from the end of the strip away from the other strip.
+     */
     pub fn hit_test(&self, x: i32, y: i32) -> bool {
-        x >= self.x()
-            && x <= self.x() + TILE_WIDTH as i32
-            && y >= self.y()
-            && y <= self.y() + TILE_HEIGHT as i32
+        if self.face_rect.contains_point((x, y)) {
+            return true;
+        }
+        let (side, bottom) = match (self.side_rect, self.bottom_rect) {
+            (Some(side), Some(bottom)) => (side, bottom),
+            _ => return false,
+        };
+        let (flip_x, flip_y) = self.flips;
+        // the pixel of the strip the point is on, as drawn without flipping
+        let pixel = |rect: Rect| {
+            let (column, row) = (x - rect.x(), y - rect.y());
+            (
+                if flip_x { rect.width() as i32 - 1 - column } else { column },
+                if flip_y { rect.height() as i32 - 1 - row } else { row },
+            )
+        };
+        // the corners are cut by lines through the centers of the pixels, which are compared at
+        // twice the scale to stay whole
+        let (side_width, bottom_height) = (side.width() as i32, bottom.height() as i32);
+
+        let (column, row) = pixel(side);
+        let on_side = side.contains_point((x, y))
+            && (2 * column + 1) * bottom_height + (2 * row + 1) * side_width
+                > 2 * side_width * bottom_height;
+
+        let (column, row) = pixel(bottom);
+        let from_right = bottom.width() as i32 - column;
+        let on_bottom = bottom.contains_point((x, y))
+            && (2 * from_right - 1) * bottom_height > (2 * row + 1) * side_width;
+
+        on_side || on_bottom
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        sdl2::rect::Rect,
+        crate::board::get_raw_positios,
+        super::{
+            Models,
+            ViewAngle,
+        },
+    };
+
+    #[test]
+    fn default_canvas_keeps_the_original_layout() {
+        let models = Models::new(&get_raw_positios());
+
+        // the first position is on the bottom layer, the last one on top
+        assert_eq!(models[0].face(), Rect::new(112, 15, 46, 57));
+        assert_eq!(models[143].face(), Rect::new(385, 198, 46, 57));
+    }
+
+    #[test]
+    fn layouts_grow_and_stay_centered() {
+        let mut models = Models::new(&get_raw_positios());
+        models.fit(Rect::new(0, 0, 2780, 1872));
+
+        let top = models[143].face();
+        assert_eq!((top.width(), top.height()), (184, 228));
+
+        let left = models.iter().filter_map(|model| model.side()).map(|side| side.left()).min();
+        let right = models.iter().map(|model| model.face().right()).max().unwrap();
+        assert!((left.unwrap() - (2780 - right)).abs() <= 1);
+    }
+
+    #[test]
+    fn edges_and_order_follow_the_view_angle() {
+        let mut models = Models::new(&get_raw_positios());
+        let drawn_at = |models: &Models, position| {
+            let tile = models.positions.iter().position(|&other| other == position);
+            models.order().iter().position(|&other| Some(other) == tile).unwrap()
+        };
+        // seen from the left, the right end of the board is drawn before the tiles it touches
+        assert!(drawn_at(&models, (28, 7, 0)) < drawn_at(&models, (26, 6, 0)));
+
+        models.set_angle(ViewAngle::TopRight);
+        let (face, side, bottom) = (models[0].face(), models[0].side(), models[0].bottom());
+        assert_eq!(side.map(|side| side.left()), Some(face.right()));
+        assert_eq!(bottom.map(|bottom| bottom.bottom()), Some(face.top()));
+        assert!(drawn_at(&models, (28, 7, 0)) > drawn_at(&models, (26, 8, 0)));
+        assert_eq!(models.positions[*models.order().last().unwrap()].2, 4);
+
+        models.set_angle(ViewAngle::Above);
+        assert_eq!((models[0].side(), models[0].bottom()), (None, None));
     }
 
-    fn x(&self) -> i32 {
-        self.face_rect.x()
+    #[test]
+    fn tiles_in_front_are_drawn_later() {
+        // the first tile is in the row in front of the second, and half a column to the right
+        let mut models = Models::new(&[(1, 2, 0), (0, 0, 0), (3, 0, 1)]);
+        assert_eq!(models.order(), &[1, 0, 2]);
+
+        models.set_angle(ViewAngle::TopLeft);
+        assert_eq!(models.order(), &[0, 1, 2]);
     }
 
-    fn y(&self) -> i32 {
-        self.face_rect.y()
+    #[test]
+    fn transparent_corners_of_the_edges_are_not_hit() {
+        let models = Models::new(&[(0, 0, 0)]);
+        let (side, bottom) = (models[0].side().unwrap(), models[0].bottom().unwrap());
+
+        assert!(models[0].hit_test(side.x(), side.bottom() - 1));
+        assert!(!models[0].hit_test(side.right() - 1, side.y()));
+        assert!(models[0].hit_test(bottom.x(), bottom.y()));
+        assert!(!models[0].hit_test(bottom.right() - 1, bottom.y()));
+        assert!(!models[0].hit_test(bottom.right(), bottom.y()));
     }
 }
@@ -0,0 +1,292 @@
+use {
+    std::time::{
+        Duration,
+        Instant,
+    },
+    sdl2::rect::Rect,
+    super::tween::{
+        Easing,
+        Tween,
+    },
+};
+
+static DEAL_DURATION: Duration = Duration::from_millis(250);
+static LEAVE_DURATION: Duration = Duration::from_millis(300);
+static LIFT_DURATION: Duration = Duration::from_millis(100);
+/// How long a hint takes to fade out and back in.
+static PULSE_PERIOD: Duration = Duration::from_millis(1000);
+/// How long the highlight of a hint keeps a strength, so it changes on the slow ticks of a
+/// window where nothing moves rather than every frame.
+static PULSE_STEP: Duration = Duration::from_millis(250);
+
+/// How far dealt tiles drop, in heights of their face.
+static DEAL_DROP: f32 = 0.5;
+/// How far played tiles rise as they shrink away, in heights of their face.
+static LEAVE_RISE: f32 = 0.6;
+/// How far the selected tile is lifted, in heights of its face.
+static LIFT_RISE: f32 = 0.1;
+/// How strong the highlight of a hint is at its faintest.
+static PULSE_LOW: f32 = 0.3;
+
+/// Where and how large a tile is drawn while it moves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Look {
+    /// How far the tile is raised, in heights of its face.
+    pub rise: f32,
+    /// The size of the tile, from 0 for gone to 1 for its full size.
+    pub scale: f32,
+}
+
+impl Look {
+    /// How a tile looks in its place.
+    pub fn rest() -> Self {
+        Look {
+            rise: 0.0,
+            scale: 1.0,
+        }
+    }
+
+    /// Returns where a rect of the tile is drawn, scaled around the center of the face.
+    pub fn place(self, rect: Rect, face: Rect) -> Rect {
+        let center = face.center();
+        let scale = |from: i32, to: i32| (to as f32 + (from - to) as f32 * self.scale).round();
+        let rise = (face.height() as f32 * self.rise).round();
+
+        let x = scale(rect.x(), center.x());
+        let y = scale(rect.y(), center.y()) - rise;
+        let right = scale(rect.right(), center.x());
+        let bottom = scale(rect.bottom(), center.y()) - rise;
+        Rect::new(x as i32, y as i32, (right - x).max(1.0) as u32, (bottom - y).max(1.0) as u32)
+    }
+
+    /// Returns the point of the tile in its place that is drawn at the point, or `None` when the
+    /// tile is too small to be hit.
+    pub fn rest_point(self, (x, y): (i32, i32), face: Rect) -> Option<(i32, i32)> {
+        if self.scale <= 0.0 {
+            return None;
+        }
+        let center = face.center();
+        let rise = (face.height() as f32 * self.rise).round();
+        let unscale = |from: f32, to: i32| (to as f32 + (from - to as f32) / self.scale).floor();
+
+        Some((unscale(x as f32, center.x()) as i32, unscale(y as f32 + rise, center.y()) as i32))
+    }
+}
+
+/**
+ * How a tile moves on its own. The game acts on the tiles at once and their motions only show
+ * the change, so clicks go to tiles where they end up. A motion that takes over from another
+ * one in flight starts from where the tile is drawn.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Motion {
+    /// The tile is dealt, dropping into its place.
+    Deal(Tween),
+    /// The tile is played, shrinking away as it rises off the board.
+    Leave(Tween),
+    /// The tile is taken back, coming back the way it left.
+    Return(Tween),
+    /// The tile is selected and lifted, staying up once the tween is done.
+    Lift(Tween),
+    /// The tile is no longer selected and goes back down.
+    Lower(Tween),
+    /// The tile is highlighted as a hint, its highlight fading out and in.
+    Pulse(Tween),
+}
+
+impl Motion {
+    pub fn deal(now: Instant, delay: Duration) -> Self {
+        Motion::Deal(Tween::new(now, DEAL_DURATION, Easing::EaseOut).delayed(delay))
+    }
+
+    pub fn leave(now: Instant, current: Option<Motion>) -> Self {
+        match current {
+            Some(Motion::Return(tween)) => Motion::Leave(tween.reversed(now, Easing::EaseIn)),
+            _ => Motion::Leave(Tween::new(now, LEAVE_DURATION, Easing::EaseIn)),
+        }
+    }
+
+    pub fn put_back(now: Instant, current: Option<Motion>) -> Self {
+        match current {
+            Some(Motion::Leave(tween)) => Motion::Return(tween.reversed(now, Easing::EaseOut)),
+            _ => Motion::Return(Tween::new(now, LEAVE_DURATION, Easing::EaseOut)),
+        }
+    }
+
+    pub fn lift(now: Instant, current: Option<Motion>) -> Self {
+        match current {
+            Some(Motion::Lower(tween)) => Motion::Lift(tween.reversed(now, Easing::EaseOut)),
+            _ => Motion::Lift(Tween::new(now, LIFT_DURATION, Easing::EaseOut)),
+        }
+    }
+
+    /// Lowers a lifted tile, and leaves any other motion as it is.
+    pub fn lower(now: Instant, current: Option<Motion>) -> Option<Self> {
+        match current {
+            Some(Motion::Lift(tween)) => {
+                Some(Motion::Lower(tween.reversed(now, Easing::EaseOut)))
+            }
+            _ => current,
+        }
+    }
+
+    pub fn pulse(now: Instant) -> Self {
+        Motion::Pulse(Tween::new(now, PULSE_PERIOD, Easing::Linear))
+    }
+
+    /// Returns the motion once it has played out, or `None` when the tile is left at rest.
+    pub fn finished(self) -> Option<Self> {
+        match self {
+            Motion::Lift(_) => {
+                let tween = Tween::new(Instant::now(), Duration::from_secs(0), Easing::Linear);
+                Some(Motion::Lift(tween))
+            }
+            Motion::Pulse(_) => Some(self),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the tile still changes how it looks every frame, which a pulse doesn't,
+    /// see `pulse_step`.
+    pub fn is_moving(self, now: Instant) -> bool {
+        match self {
+            Motion::Pulse(_) => false,
+            Motion::Deal(tween) |
+            Motion::Leave(tween) |
+            Motion::Return(tween) |
+            Motion::Lift(tween) |
+            Motion::Lower(tween) => !tween.is_done(now),
+        }
+    }
+
+    /// Returns how the tile looks, or `None` when it isn't drawn.
+    pub fn look(self, now: Instant) -> Option<Look> {
+        let look = |rise, scale| Some(Look { rise, scale });
+        match self {
+            Motion::Deal(tween) if !tween.has_started(now) => None,
+            Motion::Deal(tween) => look(DEAL_DROP * (1.0 - tween.progress(now)), 1.0),
+            Motion::Leave(tween) if tween.is_done(now) => None,
+            Motion::Leave(tween) => {
+                let progress = tween.progress(now);
+                look(LEAVE_RISE * progress, 1.0 - progress)
+            }
+            Motion::Return(tween) => {
+                let progress = tween.progress(now);
+                look(LEAVE_RISE * (1.0 - progress), progress)
+            }
+            Motion::Lift(tween) => look(LIFT_RISE * tween.progress(now), 1.0),
+            Motion::Lower(tween) => look(LIFT_RISE * (1.0 - tween.progress(now)), 1.0),
+            Motion::Pulse(_) => Some(Look::rest()),
+        }
+    }
+
+    /// Returns how strong the highlight of a highlighted tile is, from 0 to 1.
+    pub fn highlight(self, now: Instant) -> f32 {
+        match self {
+            Motion::Pulse(tween) => {
+                let wave = tween.wave(tween.step_start(now, PULSE_STEP));
+                PULSE_LOW + (1.0 - PULSE_LOW) * wave
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Returns which step a pulse is at, its highlight changing only when the step does.
+    pub fn pulse_step(self, now: Instant) -> Option<u32> {
+        match self {
+            Motion::Pulse(tween) => Some(tween.steps(now, PULSE_STEP)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::time::{
+            Duration,
+            Instant,
+        },
+        sdl2::rect::Rect,
+        super::{
+            Look,
+            Motion,
+            LEAVE_DURATION,
+            PULSE_STEP,
+        },
+    };
+
+    #[test]
+    fn looks_scale_around_the_face_and_rise() {
+        let face = Rect::new(100, 100, 40, 50);
+        let side = Rect::new(96, 104, 4, 50);
+
+        assert_eq!(Look::rest().place(side, face), side);
+        let look = Look { rise: 0.2, scale: 0.5 };
+        assert_eq!(look.place(face, face), Rect::new(110, 103, 20, 25));
+        assert_eq!(look.place(side, face), Rect::new(108, 105, 2, 25));
+
+        assert_eq!(Look::rest().rest_point((98, 110), face), Some((98, 110)));
+        assert_eq!(look.rest_point((112, 104), face), Some((104, 103)));
+        assert_eq!(Look { rise: 0.0, scale: 0.0 }.rest_point((110, 103), face), None);
+    }
+
+    #[test]
+    fn tiles_taken_back_while_leaving_turn_around() {
+        let now = Instant::now();
+        let leave = Motion::leave(now, None);
+        assert_eq!(leave.look(now), Some(Look::rest()));
+        assert_eq!(leave.look(now + LEAVE_DURATION), None);
+
+        let halfway = now + LEAVE_DURATION / 2;
+        let put_back = Motion::put_back(halfway, Some(leave));
+        let scale = |motion: Motion| motion.look(halfway).unwrap().scale;
+        assert!((scale(put_back) - scale(leave)).abs() < 0.01);
+        assert!(put_back.is_moving(halfway));
+        assert!(!put_back.is_moving(halfway + LEAVE_DURATION));
+        assert_eq!(put_back.look(halfway + LEAVE_DURATION), Some(Look::rest()));
+    }
+
+    #[test]
+    fn dealt_tiles_appear_after_their_delay() {
+        let now = Instant::now();
+        let deal = Motion::deal(now, Duration::from_millis(100));
+
+        assert_eq!(deal.look(now), None);
+        assert!(deal.look(now + Duration::from_millis(150)).unwrap().rise > 0.0);
+        assert_eq!(deal.look(now + Duration::from_secs(1)), Some(Look::rest()));
+        assert_eq!(deal.finished(), None);
+    }
+
+    #[test]
+    fn pulses_change_step_by_step() {
+        let now = Instant::now();
+        let pulse = Motion::pulse(now);
+
+        assert!(!pulse.is_moving(now));
+        assert_eq!(pulse.highlight(now), 1.0);
+        assert_eq!(pulse.highlight(now + PULSE_STEP / 2), 1.0);
+        assert_eq!(pulse.pulse_step(now + PULSE_STEP / 2), Some(0));
+        assert!(pulse.highlight(now + PULSE_STEP) < 1.0);
+        assert_eq!(pulse.pulse_step(now + PULSE_STEP), Some(1));
+    }
+
+    #[test]
+    fn selected_tiles_stay_lifted_and_only_they_are_lowered() {
+        let now = Instant::now();
+        let lift = Motion::lift(now, None);
+        let later = now + Duration::from_secs(1);
+
+        assert!(lift.look(later).unwrap().rise > 0.0);
+        assert!(!lift.is_moving(later));
+        assert_eq!(lift.finished().unwrap().look(later), lift.look(later));
+
+        let lower = Motion::lower(later, Some(lift)).unwrap();
+        assert_eq!(lower.look(later), lift.look(later));
+        assert_eq!(lower.look(later + Duration::from_secs(1)), Some(Look::rest()));
+
+        let leave = Motion::leave(now, None);
+        assert_eq!(Motion::lower(later, Some(leave)), Some(leave));
+        assert_eq!(Motion::lower(later, None), None);
+    }
+}
@@ -13,9 +13,23 @@ impl Position {
         else if self.is_down_neighbour(other) { Some(Down) }
         else if self.is_left_neighbour(other) { Some(Left) }
         else if self.is_right_neighbour(other) { Some(Right) }
+        else if self.is_back_neighbour(other) { Some(Back) }
+        else if self.is_front_neighbour(other) { Some(Front) }
         else { None }
     }
 
+    /// Returns whether the tile lies half a row across from the rows of the layout, which are on
+    /// even coordinates, like the tails of the turtle.
+    pub fn is_between_rows(self) -> bool {
+        self.y % 2 == 1
+    }
+
+    /// Returns whether the other tile lies half a tile across from this one, so it only covers
+    /// part of the side it touches.
+    pub fn is_offset(self, other: Position) -> bool {
+        self.x % 2 != other.x % 2 || self.y % 2 != other.y % 2
+    }
+
     fn is_up_neighbour(self, other: Position) -> bool {
         self.z + 1 == other.z && self.is_potential_vertical_neighbour(other)
     }
@@ -40,9 +54,21 @@ impl Position {
     fn is_potential_horizontal_neighbour(self, other: Position) -> bool {
         self.z == other.z && self.y <= other.y + 1 && self.y + 1 >= other.y
     }
+
+    fn is_back_neighbour(self, other: Position) -> bool {
+        self.y == other.y + 2 && self.is_potential_depth_neighbour(other)
+    }
+
+    fn is_front_neighbour(self, other: Position) -> bool {
+        self.y + 2 == other.y && self.is_potential_depth_neighbour(other)
+    }
+
+    fn is_potential_depth_neighbour(self, other: Position) -> bool {
+        self.z == other.z && self.x <= other.x + 1 && self.x + 1 >= other.x
+    }
 }
 
-impl<'a> From<&'a (u8, u8, u8)> for Position {
+impl From<&(u8, u8, u8)> for Position {
     fn from(&(x, y, z): &(u8, u8, u8)) -> Self {
         Self { x, y, z }
     }
@@ -54,16 +80,107 @@ pub enum Direction {
     Down,
     Right,
     Left,
+    Back,
+    Front,
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct Neighbour {
     pub id: usize,
     pub direction: Direction,
+    /// Whether the neighbour lies half a tile across and covers only part of the side.
+    pub offset: bool,
 }
 
 impl Neighbour {
     pub fn new(id: usize, direction: Direction) -> Self {
-        Self { id, direction }
+        Self { id, direction, offset: false }
+    }
+
+    pub fn offset(id: usize, direction: Direction) -> Self {
+        Self { id, direction, offset: true }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Direction::*,
+        Position,
+    };
+
+    fn pos(x: u8, y: u8, z: u8) -> Position {
+        Position { x, y, z }
+    }
+
+    #[test]
+    fn neighbours_up_and_down() {
+        let tile = pos(4, 4, 0);
+
+        assert_eq!(tile.neighbours(pos(4, 4, 1)), Some(Up));
+        assert_eq!(pos(4, 4, 1).neighbours(tile), Some(Down));
+    }
+
+    #[test]
+    fn neighbours_vertically_with_half_tile_offset() {
+        let tile = pos(4, 4, 0);
+
+        for &(x, y) in &[(3, 3), (3, 5), (5, 3), (5, 5), (4, 3), (5, 4)] {
+            assert_eq!(tile.neighbours(pos(x, y, 1)), Some(Up), "({}, {})", x, y);
+            assert_eq!(pos(x, y, 1).neighbours(tile), Some(Down), "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn neighbours_left_and_right() {
+        let tile = pos(4, 4, 0);
+
+        assert_eq!(tile.neighbours(pos(2, 4, 0)), Some(Left));
+        assert_eq!(tile.neighbours(pos(6, 4, 0)), Some(Right));
+    }
+
+    #[test]
+    fn neighbours_horizontally_with_half_tile_offset() {
+        let tile = pos(4, 4, 0);
+
+        assert_eq!(tile.neighbours(pos(2, 3, 0)), Some(Left));
+        assert_eq!(tile.neighbours(pos(2, 5, 0)), Some(Left));
+        assert_eq!(tile.neighbours(pos(6, 3, 0)), Some(Right));
+        assert_eq!(tile.neighbours(pos(6, 5, 0)), Some(Right));
+        assert!(tile.is_offset(pos(2, 3, 0)));
+        assert!(tile.is_offset(pos(6, 5, 0)));
+        assert!(!tile.is_offset(pos(6, 4, 0)));
+    }
+
+    #[test]
+    fn tails_lie_between_rows() {
+        assert!(pos(0, 7, 0).is_between_rows());
+        assert!(pos(28, 7, 0).is_between_rows());
+        assert!(!pos(26, 6, 0).is_between_rows());
+    }
+
+    #[test]
+    fn neighbours_back_and_front() {
+        let tile = pos(4, 4, 0);
+
+        assert_eq!(tile.neighbours(pos(4, 2, 0)), Some(Back));
+        assert_eq!(tile.neighbours(pos(4, 6, 0)), Some(Front));
+        assert_eq!(tile.neighbours(pos(3, 2, 0)), Some(Back));
+        assert_eq!(tile.neighbours(pos(5, 6, 0)), Some(Front));
+    }
+
+    #[test]
+    fn does_not_neighbour_distant_tiles() {
+        let tile = pos(4, 4, 1);
+
+        assert_eq!(tile.neighbours(tile), None);
+        assert_eq!(tile.neighbours(pos(8, 4, 1)), None);
+        assert_eq!(tile.neighbours(pos(0, 4, 1)), None);
+        assert_eq!(tile.neighbours(pos(2, 6, 1)), None);
+        assert_eq!(tile.neighbours(pos(4, 7, 1)), None);
+        assert_eq!(tile.neighbours(pos(6, 6, 1)), None);
+        assert_eq!(tile.neighbours(pos(4, 4, 3)), None);
+        assert_eq!(tile.neighbours(pos(6, 4, 2)), None);
+        assert_eq!(tile.neighbours(pos(2, 4, 0)), None);
     }
 }
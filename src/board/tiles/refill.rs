@@ -0,0 +1,349 @@
+use {
+    std::cmp::Reverse,
+    rand::{
+        rngs::SmallRng,
+        seq::SliceRandom,
+        Rng,
+        SeedableRng,
+    },
+    super::{
+        FreeRule,
+        Neighbour,
+        Position,
+        TileSet,
+        TileType,
+    },
+};
+
+const MAX_REFILL_ATTEMPTS: usize = 20;
+const MAX_PAIR_RETRIES: usize = 2;
+const MAX_PLACEMENT_ATTEMPTS: usize = 500;
+const MAX_SEARCH_STEPS: usize = 10_000;
+
+type Pair = (usize, usize);
+
+/// Deals new tiles into the empty positions while the tiles left on the board keep their type,
+/// such that the whole board can be cleared. Fails when no such deal is found, so the tiles left
+/// are never dealt anew. The same seed always deals the same tiles.
+pub fn get_refilled_types(
+    positions: &[Position],
+    neighbours: &[Vec<Neighbour>],
+    rule: &dyn FreeRule,
+    tile_set: &TileSet,
+    current: &[Option<TileType>],
+    seed: u64,
+) -> Result<Vec<TileType>, &'static str> {
+    let mut rng = SmallRng::seed_from_u64(seed);
+
+    refill(positions, neighbours, rule, tile_set, current, &mut rng)
+        .map(|(types, _)| types)
+        .ok_or("no refill found for the tiles left")
+}
+
+/**
+ * Looks for an order to play the full board in, the same way the shuffler does, where a pair of
+ * tiles left on the board has to match. A new tile is paired with a tile left on the board by
+ * giving it the same type, and two new tiles get a random matching pair from the tile set.
+ * When the random attempts run into dead ends, the play orders are searched one after another
+ * instead. Returns the types and the pairs in the order they can be played.
+ */
+fn refill<R: Rng>(
+    positions: &[Position],
+    neighbours: &[Vec<Neighbour>],
+    rule: &dyn FreeRule,
+    tile_set: &TileSet,
+    current: &[Option<TileType>],
+    rng: &mut R,
+) -> Option<(Vec<TileType>, Vec<Pair>)> {
+    let mut play_order = PlayOrder {
+        positions,
+        neighbours,
+        rule,
+        tile_set,
+        current,
+        played: vec![false; current.len()],
+        pairs: Vec::with_capacity(current.len() / 2),
+        rng,
+    };
+
+    let found = (0..MAX_REFILL_ATTEMPTS).any(|_| {
+        let mut attempts_left = MAX_PLACEMENT_ATTEMPTS;
+        play_order.play_remaining_pairs(&mut attempts_left)
+    }) || {
+        let mut steps_left = MAX_SEARCH_STEPS;
+        play_order.search_remaining_pairs(&mut steps_left)
+    };
+    if !found {
+        return None;
+    }
+
+    let pairs = play_order.pairs;
+    Some((assign_types(tile_set, current, &pairs, play_order.rng), pairs))
+}
+
+struct PlayOrder<'a, R: Rng> {
+    positions: &'a [Position],
+    neighbours: &'a [Vec<Neighbour>],
+    rule: &'a dyn FreeRule,
+    tile_set: &'a TileSet,
+    current: &'a [Option<TileType>],
+    played: Vec<bool>,
+    pairs: Vec<Pair>,
+    rng: &'a mut R,
+}
+
+impl<R: Rng> PlayOrder<'_, R> {
+    /// Plays random pairs until the board is cleared, retrying the last pairs on a dead end the
+    /// same way the shuffler does.
+    fn play_remaining_pairs(&mut self, attempts_left: &mut usize) -> bool {
+        if self.pairs.len() * 2 == self.current.len() {
+            return true;
+        }
+
+        for _ in 0..MAX_PAIR_RETRIES {
+            if *attempts_left == 0 {
+                return false;
+            }
+            let (tile1, tile2) = match self.random_pair() {
+                Some(pair) => pair,
+                None => return false,
+            };
+            *attempts_left -= 1;
+
+            self.played[tile1] = true;
+            self.played[tile2] = true;
+            self.pairs.push((tile1, tile2));
+
+            if self.play_remaining_pairs(attempts_left) {
+                return true;
+            }
+
+            self.played[tile1] = false;
+            self.played[tile2] = false;
+            self.pairs.pop();
+        }
+
+        false
+    }
+
+    /**
+     * Tries every pair in turn until the board is cleared, backing out of dead ends. The tiles
+     * left on the board keep their types, so the choices are mostly about which empty positions
+     * are paired with them. Gives up after a number of steps.
+     */
+    fn search_remaining_pairs(&mut self, steps_left: &mut usize) -> bool {
+        if self.pairs.len() * 2 == self.current.len() {
+            return true;
+        }
+
+        let mut candidates = self.candidate_pairs();
+        candidates.shuffle(self.rng);
+        candidates.sort_by_key(|&pair| self.priority(pair));
+
+        for (tile1, tile2) in candidates {
+            if *steps_left == 0 {
+                return false;
+            }
+            *steps_left -= 1;
+
+            self.played[tile1] = true;
+            self.played[tile2] = true;
+            self.pairs.push((tile1, tile2));
+
+            if self.search_remaining_pairs(steps_left) {
+                return true;
+            }
+
+            self.played[tile1] = false;
+            self.played[tile2] = false;
+            self.pairs.pop();
+        }
+
+        false
+    }
+
+    /**
+     * Tiles left on the board are the hardest to pair, so pairs of them go first and new tiles
+     * are saved for the tiles left that can't be paired with each other. Like in the shuffler
+     * higher tiles are preferred so stacks don't end up as the last tiles.
+     */
+    fn priority(&self, (tile1, tile2): Pair) -> (u8, Reverse<u8>) {
+        let new_tiles = self.current[tile1].is_none() as u8 + self.current[tile2].is_none() as u8;
+        let z = self.positions[tile1].z.max(self.positions[tile2].z);
+        (new_tiles, Reverse(z))
+    }
+
+    fn random_pair(&mut self) -> Option<Pair> {
+        let mut candidates = self.candidate_pairs();
+
+        let best = candidates.iter().map(|&pair| self.priority(pair)).min()?;
+        candidates.retain(|&pair| self.priority(pair) == best);
+        candidates.choose(self.rng).cloned()
+    }
+
+    /// Returns the pairs of free tiles that can be played together.
+    fn candidate_pairs(&self) -> Vec<Pair> {
+        let played = &self.played;
+        let is_present = |id: usize| !played[id];
+        let free_tiles = (0..self.current.len())
+            .filter(|&tile| {
+                !played[tile]
+                    && self.rule.is_free(self.positions[tile], &self.neighbours[tile], &is_present)
+            })
+            .collect::<Vec<_>>();
+
+        let mut candidates = Vec::new();
+        for (index, &tile1) in free_tiles.iter().enumerate() {
+            for &tile2 in &free_tiles[index + 1..] {
+                let can_pair = match (self.current[tile1], self.current[tile2]) {
+                    (Some(type1), Some(type2)) => self.tile_set.matches(type1, type2),
+                    _ => true,
+                };
+                if can_pair {
+                    candidates.push((tile1, tile2));
+                }
+            }
+        }
+        candidates
+    }
+}
+
+fn assign_types<R: Rng>(
+    tile_set: &TileSet,
+    current: &[Option<TileType>],
+    pairs: &[Pair],
+    rng: &mut R,
+) -> Vec<TileType> {
+    let new_types = tile_set.types();
+    let mut types = current.to_vec();
+
+    for &(tile1, tile2) in pairs {
+        match (current[tile1], current[tile2]) {
+            (Some(_), Some(_)) => {}
+            (Some(tile_type), None) => types[tile2] = Some(tile_type),
+            (None, Some(tile_type)) => types[tile1] = Some(tile_type),
+            (None, None) => {
+                let random_index = rng.gen_range(0, new_types.len() / 2) * 2;
+                types[tile1] = Some(new_types[random_index]);
+                types[tile2] = Some(new_types[random_index + 1]);
+            }
+        }
+    }
+
+    types.into_iter().map(|tile_type| tile_type.expect("tile without type")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        rand::{
+            rngs::SmallRng,
+            SeedableRng,
+        },
+        crate::board::get_raw_positios,
+        super::{
+            refill,
+            super::{
+                PlayState,
+                RuleSet,
+                TileId,
+                Tiles,
+                TileType,
+            },
+        },
+    };
+
+    const SEEDS: u64 = 50;
+
+    /// Plays random matching pairs until fewer than `tiles_left` tiles are left or no pair is
+    /// left to play.
+    fn play_until(tiles: &mut Tiles, tiles_left: usize) {
+        while tiles.tiles_left() > tiles_left {
+            let playable = tiles.playable_tiles().collect::<Vec<_>>();
+            let pair = playable
+                .iter()
+                .enumerate()
+                .flat_map(|(index, &tile1)| {
+                    playable[index + 1..].iter().map(move |&tile2| (tile1, tile2))
+                }).find(|&(tile1, tile2)| tiles.are_matching(tile1, tile2));
+
+            match pair {
+                Some((tile1, tile2)) => {
+                    tiles.play_tile(tile1);
+                    tiles.play_tile(tile2);
+                }
+                None => return,
+            }
+        }
+    }
+
+    #[test]
+    fn refills_keep_tiles_and_are_solvable() {
+        for &rules in &[RuleSet::Classic, RuleSet::ThreeDimensional] {
+            // some stuck boards can't be cleared whatever is dealt into them
+            let mut failures = 0;
+
+            for seed in 0..SEEDS {
+                let mut tiles = Tiles::new(&mut get_raw_positios(), rules);
+                tiles.reset_with_seed(seed).unwrap();
+                play_until(&mut tiles, 30 + seed as usize);
+
+                let current = (0..tiles.len())
+                    .map(|tile| match tiles.states[tile] {
+                        PlayState::Played => None,
+                        _ => Some(tiles.types[tile]),
+                    }).collect::<Vec<_>>();
+
+                let mut rng = SmallRng::seed_from_u64(seed);
+                let refilled = refill(
+                    &tiles.positions,
+                    &tiles.neighbours,
+                    rules.free_rule(),
+                    &tiles.tile_set,
+                    &current,
+                    &mut rng,
+                );
+                let (types, pairs) = match refilled {
+                    Some(refilled) => refilled,
+                    None => {
+                        failures += 1;
+                        continue;
+                    }
+                };
+
+                for (tile, tile_type) in current.iter().enumerate() {
+                    if let Some(tile_type) = tile_type {
+                        assert_eq!(types[tile], *tile_type);
+                    }
+                }
+
+                tiles.types = types;
+                tiles.reset_states();
+                for &(tile1, tile2) in &pairs {
+                    let context = format!("{:?} seed {}", rules, seed);
+                    assert_eq!(tiles.states[tile1], PlayState::Playable, "{}", context);
+                    assert_eq!(tiles.states[tile2], PlayState::Playable, "{}", context);
+                    assert!(tiles.are_matching(TileId(tile1), TileId(tile2)), "{}", context);
+
+                    tiles.play_tile(TileId(tile1));
+                    tiles.play_tile(TileId(tile2));
+                }
+                assert!(tiles.is_cleared());
+            }
+
+            assert!(failures <= SEEDS / 10, "{:?} failed {} times", rules, failures);
+        }
+    }
+
+    #[test]
+    fn boards_that_cannot_be_cleared_are_kept() {
+        let mut tiles = Tiles::new(&mut get_raw_positios(), RuleSet::Classic);
+        // with no empty position the odd tile out is never matched
+        tiles.types = vec![TileType::BallOne; tiles.len()];
+        tiles.types[0] = TileType::BallTwo;
+        let types = tiles.types.clone();
+
+        assert!(tiles.refill(0).is_err());
+        assert_eq!(tiles.types, types);
+    }
+}
@@ -0,0 +1,273 @@
+use super::{
+    Direction::{self, *},
+    Neighbour,
+    Position,
+};
+
+/// Decides whether a tile is free, and thus can be played, based on which of its neighbours are
+/// still on the board.
+pub trait FreeRule {
+    fn is_free(
+        &self,
+        position: Position,
+        neighbours: &[Neighbour],
+        is_present: &dyn Fn(usize) -> bool,
+    ) -> bool;
+}
+
+/// Free when nothing lies on top of the tile and at least one side is open.
+pub struct Classic;
+
+impl FreeRule for Classic {
+    fn is_free(
+        &self,
+        _: Position,
+        neighbours: &[Neighbour],
+        is_present: &dyn Fn(usize) -> bool,
+    ) -> bool {
+        let any = |direction| any_present_in_direction(neighbours, direction, is_present);
+
+        !(any(Up) || (any(Left) && any(Right)))
+    }
+}
+
+/**
+ * Like `Classic`, but a tile on the edge of the layout that lies half a row across from the
+ * rows, like the tails of the turtle, is only free once the row tiles beside it are gone. The
+ * shift is the tile's own, so this works the same on either side and on any layout. Tiles beside
+ * it that lie in the same half row, like the rest of a longer tail, do not hold it.
+ */
+pub struct Strict;
+
+impl FreeRule for Strict {
+    fn is_free(
+        &self,
+        position: Position,
+        neighbours: &[Neighbour],
+        is_present: &dyn Fn(usize) -> bool,
+    ) -> bool {
+        let any = |direction| any_present_in_direction(neighbours, direction, is_present);
+        let has = |direction| neighbours.iter().any(|n| n.direction == direction);
+        let any_offset = |direction| {
+            neighbours
+                .iter()
+                .any(|n| n.direction == direction && n.offset && is_present(n.id))
+        };
+
+        let edge_offset = position.is_between_rows() && !(has(Left) && has(Right));
+
+        if edge_offset {
+            !(any(Up) || any_offset(Left) || any_offset(Right))
+        } else {
+            !(any(Up) || (any(Left) && any(Right)))
+        }
+    }
+}
+
+/// Free when nothing lies on top of the tile and at least one side, the front or the back is
+/// open.
+pub struct ThreeDimensional;
+
+impl FreeRule for ThreeDimensional {
+    fn is_free(
+        &self,
+        _: Position,
+        neighbours: &[Neighbour],
+        is_present: &dyn Fn(usize) -> bool,
+    ) -> bool {
+        let any = |direction| any_present_in_direction(neighbours, direction, is_present);
+
+        !(any(Up) || (any(Left) && any(Right) && any(Back) && any(Front)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RuleSet {
+    #[default]
+    Classic,
+    Strict,
+    ThreeDimensional,
+}
+
+impl RuleSet {
+    pub fn free_rule(self) -> &'static dyn FreeRule {
+        match self {
+            RuleSet::Classic => &Classic,
+            RuleSet::Strict => &Strict,
+            RuleSet::ThreeDimensional => &ThreeDimensional,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RuleSet::Classic => "Classic",
+            RuleSet::Strict => "Strict",
+            RuleSet::ThreeDimensional => "3D",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RuleSet> {
+        match name {
+            "Classic" => Some(RuleSet::Classic),
+            "Strict" => Some(RuleSet::Strict),
+            "3D" => Some(RuleSet::ThreeDimensional),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> RuleSet {
+        match self {
+            RuleSet::Classic => RuleSet::Strict,
+            RuleSet::Strict => RuleSet::ThreeDimensional,
+            RuleSet::ThreeDimensional => RuleSet::Classic,
+        }
+    }
+}
+
+fn any_present_in_direction(
+    neighbours: &[Neighbour],
+    direction: Direction,
+    is_present: &dyn Fn(usize) -> bool,
+) -> bool {
+    neighbours
+        .iter()
+        .filter(|neighbour| neighbour.direction == direction)
+        .any(|neighbour| is_present(neighbour.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            Direction::*,
+            Neighbour,
+            Position,
+        },
+        RuleSet,
+    };
+
+    fn is_free(rules: RuleSet, neighbours: &[Neighbour], present: &[usize]) -> bool {
+        is_free_at(rules, Position::default(), neighbours, present)
+    }
+
+    fn is_free_at(
+        rules: RuleSet,
+        position: Position,
+        neighbours: &[Neighbour],
+        present: &[usize],
+    ) -> bool {
+        rules.free_rule().is_free(position, neighbours, &|id| present.contains(&id))
+    }
+
+    #[test]
+    fn covered_tiles_are_never_free() {
+        let neighbours = [Neighbour::new(0, Up)];
+
+        for &rules in &[RuleSet::Classic, RuleSet::Strict, RuleSet::ThreeDimensional] {
+            assert!(!is_free(rules, &neighbours, &[0]));
+            assert!(is_free(rules, &neighbours, &[]));
+        }
+    }
+
+    #[test]
+    fn classic_needs_one_open_side() {
+        let neighbours = [Neighbour::new(0, Left), Neighbour::new(1, Right)];
+
+        assert!(!is_free(RuleSet::Classic, &neighbours, &[0, 1]));
+        assert!(is_free(RuleSet::Classic, &neighbours, &[0]));
+        assert!(is_free(RuleSet::Classic, &neighbours, &[1]));
+    }
+
+    #[test]
+    fn strict_needs_both_sides_open_for_edge_offset_tiles() {
+        let tail = Position::from(&(0, 7, 0));
+        let neighbours = [Neighbour::offset(0, Right), Neighbour::offset(1, Right)];
+
+        assert!(!is_free_at(RuleSet::Strict, tail, &neighbours, &[0]));
+        assert!(!is_free_at(RuleSet::Strict, tail, &neighbours, &[1]));
+        assert!(is_free_at(RuleSet::Strict, tail, &neighbours, &[]));
+        assert!(is_free_at(RuleSet::Classic, tail, &neighbours, &[0, 1]));
+    }
+
+    #[test]
+    fn strict_behaves_classic_for_aligned_tiles() {
+        let neighbours = [Neighbour::offset(0, Left), Neighbour::new(1, Right)];
+
+        assert!(!is_free(RuleSet::Strict, &neighbours, &[0, 1]));
+        assert!(is_free(RuleSet::Strict, &neighbours, &[0]));
+        assert!(is_free(RuleSet::Strict, &neighbours, &[1]));
+    }
+
+    /**
+     * A small layout like the middle of the turtle, with a long tail on the left and a short one
+     * on the right:
+     *
+     *           A
+     *   T   M       U
+     *           B
+     */
+    fn staggered_layout() -> Vec<Position> {
+        [(0, 1, 0), (2, 1, 0), (4, 0, 0), (4, 2, 0), (6, 1, 0)]
+            .iter()
+            .map(Position::from)
+            .collect()
+    }
+
+    fn neighbours_of(positions: &[Position], tile: usize) -> Vec<Neighbour> {
+        positions
+            .iter()
+            .enumerate()
+            .filter_map(|(id, &other)| {
+                let direction = positions[tile].neighbours(other)?;
+                Some(if positions[tile].is_offset(other) {
+                    Neighbour::offset(id, direction)
+                } else {
+                    Neighbour::new(id, direction)
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn strict_behaves_classic_between_rows_with_both_sides_taken() {
+        let positions = staggered_layout();
+        let middle = neighbours_of(&positions, 1);
+
+        for &rules in &[RuleSet::Classic, RuleSet::Strict] {
+            assert!(is_free_at(rules, positions[1], &middle, &[0]));
+            assert!(is_free_at(rules, positions[1], &middle, &[2, 3]));
+            assert!(!is_free_at(rules, positions[1], &middle, &[0, 3]));
+        }
+    }
+
+    #[test]
+    fn strict_holds_tails_on_either_side_by_the_rows_beside_them() {
+        let positions = staggered_layout();
+
+        for &(tail, wedge) in &[(4, 2), (4, 3)] {
+            let neighbours = neighbours_of(&positions, tail);
+            let position = positions[tail];
+            assert!(!is_free_at(RuleSet::Strict, position, &neighbours, &[wedge]));
+            assert!(is_free_at(RuleSet::Strict, position, &neighbours, &[]));
+            assert!(is_free_at(RuleSet::Classic, position, &neighbours, &[wedge]));
+        }
+
+        let long_tail = neighbours_of(&positions, 0);
+        assert!(is_free_at(RuleSet::Strict, positions[0], &long_tail, &[1]));
+    }
+
+    #[test]
+    fn three_dimensional_frees_from_front_or_back() {
+        let neighbours = [
+            Neighbour::new(0, Left),
+            Neighbour::new(1, Right),
+            Neighbour::new(2, Back),
+            Neighbour::new(3, Front),
+        ];
+
+        assert!(!is_free(RuleSet::ThreeDimensional, &neighbours, &[0, 1, 2, 3]));
+        assert!(is_free(RuleSet::ThreeDimensional, &neighbours, &[0, 1, 2]));
+        assert!(is_free(RuleSet::ThreeDimensional, &neighbours, &[0, 1, 3]));
+        assert!(!is_free(RuleSet::Classic, &neighbours, &[0, 1, 3]));
+    }
+}
@@ -1,37 +1,66 @@
 use {
     std::default::Default,
     rand::{
-        thread_rng,
         Rng,
         SeedableRng,
         FromEntropy,
         rngs::SmallRng,
     },
     super::{
-        Direction,
+        rules::Classic,
+        FreeRule,
         Neighbour,
         Position,
+        TileSet,
         TileType,
     },
     self::ShuffleState::*,
 };
 
-pub fn get_shuffled_types(positions: &[Position], neighbours: &[Vec<Neighbour>]) -> Vec<TileType> {
-    let mut shuffler: TypeShuffler<SmallRng> = ShufflerBuilder::new(positions, neighbours)
-        .build()
-        .unwrap_or_else(|err| panic!("{}", err));
-
-    for _ in 0..neighbours.len() / 2 {
-        shuffler.place_random_type_pair()
-    }
+const MAX_GROUP_RETRIES: usize = 2;
+const MAX_PLACEMENT_ATTEMPTS: usize = 500;
+const MAX_DEALS: usize = 100;
+
+/// Deals the types in groups of the given size that can be played together, where the types are
+/// ordered so that each group of consecutive types belongs together.
+pub fn get_shuffled_groups(
+    positions: &[Position],
+    neighbours: &[Vec<Neighbour>],
+    rule: &dyn FreeRule,
+    types: Vec<TileType>,
+    group_size: usize,
+) -> Result<Vec<TileType>, &'static str> {
+    let shuffler: TypeShuffler<SmallRng> = ShufflerBuilder::new(positions, neighbours)
+        .free_rule(rule)
+        .types(types)
+        .group_size(group_size)
+        .build()?;
+
+    shuffler.shuffle()
+}
 
-    shuffler.set_types.iter().filter_map(|t| *t).collect()
+pub fn get_seeded_shuffled_types(
+    positions: &[Position],
+    neighbours: &[Vec<Neighbour>],
+    rule: &dyn FreeRule,
+    tile_set: &TileSet,
+    seed: u64,
+) -> Result<Vec<TileType>, &'static str> {
+    let shuffler: TypeShuffler<SmallRng> = ShufflerBuilder::new(positions, neighbours)
+        .free_rule(rule)
+        .types(tile_set.types())
+        .seed_rng(seed)
+        .build()?;
+
+    shuffler.shuffle()
 }
 
 struct ShufflerBuilder<'td, R: Rng> {
     positions: &'td [Position],
     neighbours: &'td [Vec<Neighbour>],
+    rule: Option<&'td dyn FreeRule>,
     types: Option<Vec<TileType>>,
+    group_size: usize,
     rng: Option<R>,
 }
 
@@ -40,18 +69,30 @@ impl<'td, R> ShufflerBuilder<'td, R> where R: Rng + SeedableRng + FromEntropy {
         Self {
             positions,
             neighbours,
+            rule: None,
             types: None,
+            group_size: 2,
             rng: None,
         }
     }
 
-    #[allow(dead_code)]
+    pub fn free_rule(mut self, rule: &'td dyn FreeRule) -> Self {
+        self.rule = Some(rule);
+        self
+    }
+
+    /// Sets the types to deal, ordered so that each group of consecutive types matches.
     pub fn types(mut self, types: Vec<TileType>) -> Self {
         self.types = Some(types);
         self
     }
 
-    #[allow(dead_code)]
+    /// Sets the number of tiles that are played together, pairs by default.
+    pub fn group_size(mut self, group_size: usize) -> Self {
+        self.group_size = group_size;
+        self
+    }
+
     pub fn seed_rng(mut self, seed: u64) -> Self {
         let rng = R::seed_from_u64(seed);
         self.rng = Some(rng);
@@ -65,85 +106,179 @@ impl<'td, R> ShufflerBuilder<'td, R> where R: Rng + SeedableRng + FromEntropy {
             return Err("neighbours length does not match positions length");
         }
 
-        let available_types = self.types.unwrap_or_else(get_tile_types);
+        let available_types = self.types.unwrap_or_else(|| TileSet::default().types());
 
         if available_types.len() != num_tiles {
             return Err("types length does not match positions length");
         }
 
+        if self.group_size == 0 || !num_tiles.is_multiple_of(self.group_size) {
+            return Err("number of tiles is not a multiple of the group size");
+        }
+
         let rng = self.rng.unwrap_or_else(R::from_entropy);
+        let rule = self.rule.unwrap_or(&Classic);
 
         let mut type_shuffler = TypeShuffler {
             tiles_left: num_tiles,
             positions: self.positions,
             neighbours: self.neighbours,
-            states: vec![Default::default(); num_tiles],
-            available_types,
-            set_types: vec![None; num_tiles],
+            rule,
+            states: Vec::new(),
+            placed: Vec::new(),
+            types: available_types,
+            group_size: self.group_size,
             rng,
         };
 
-        for tile_id in 0..num_tiles {
-            type_shuffler.update_unplaced_neighbours_shuffle_states(tile_id)
-        }
+        type_shuffler.reset();
 
         Ok(type_shuffler)
     }
 }
 
-#[derive(Debug)]
 struct TypeShuffler<'td, R: Rng> {
     tiles_left: usize,
     positions: &'td [Position],
     neighbours: &'td [Vec<Neighbour>],
+    rule: &'td dyn FreeRule,
     states: Vec<ShuffleState>,
-    available_types: Vec<TileType>,
-    set_types: Vec<Option<TileType>>,
+    placed: Vec<Vec<usize>>,
+    types: Vec<TileType>,
+    group_size: usize,
     rng: R,
 }
 
 impl<R> TypeShuffler<'_, R> where R: Rng {
+    fn shuffle(mut self) -> Result<Vec<TileType>, &'static str> {
+        self.place_all_groups_within_deals()?;
+        Ok(self.assign_types())
+    }
+
+    /// Deals until every group is placed, and gives up after a number of deals since not every
+    /// layout can be dealt with every free tile rule.
+    fn place_all_groups_within_deals(&mut self) -> Result<(), &'static str> {
+        for _ in 0..MAX_DEALS {
+            if self.place_all_groups() {
+                return Ok(());
+            }
+            self.reset();
+        }
+
+        Err("no deal found for the layout and free tile rules")
+    }
+
+    fn reset(&mut self) {
+        let num_tiles = self.positions.len();
+
+        self.tiles_left = num_tiles;
+        self.states = vec![Default::default(); num_tiles];
+        self.placed = Vec::new();
+
+        for tile_id in 0..num_tiles {
+            self.update_unplaced_neighbours_shuffle_states(tile_id)
+        }
+    }
+
+    /**
+     * Places groups until every tile is placed. Some free tile rules let a tile only become free
+     * after its neighbours are gone, so the random process can run into a state where fewer tiles
+     * than a group are placable. The last groups are then retried with other choices, and if that
+     * takes too long the caller restarts the deal with the same random number generator so
+     * seeded deals stay reproducible.
+     */
+    fn place_all_groups(&mut self) -> bool {
+        let mut attempts_left = MAX_PLACEMENT_ATTEMPTS;
+        self.place_remaining_groups(&mut attempts_left)
+    }
+
+    fn place_remaining_groups(&mut self, attempts_left: &mut usize) -> bool {
+        if self.tiles_left == 0 {
+            return true;
+        }
+
+        let states = self.states.clone();
+
+        for _ in 0..MAX_GROUP_RETRIES {
+            if *attempts_left == 0 || !self.place_random_group() {
+                return false;
+            }
+            *attempts_left -= 1;
+
+            if self.place_remaining_groups(attempts_left) {
+                return true;
+            }
+
+            self.states = states.clone();
+            self.placed.pop();
+            self.tiles_left += self.group_size;
+        }
+
+        false
+    }
+
     /**
-     * Tile shuffle strategy is to assign a random type pair to two random tiles according to the
+     * Tile shuffle strategy is to assign a random type group to random tiles according to the
      * same rules as they can be played. Additionally to make sure the process does not enter an
      * invalid state the Z position of tiles is checked to see if some tiles need to be prioritized
      * for assignment.
      */
-    fn place_random_type_pair(&mut self) {
+    fn place_random_group(&mut self) -> bool {
         let mut placable_tiles = self.get_placable_tiles();
 
-        self.tiles_left -= 2;
+        if placable_tiles.len() < self.group_size {
+            return false;
+        }
 
-        let placable_tile_index = placable_tiles
-            .iter()
-            .enumerate()
-            .find(|(_, &tile)| self.tiles_left == usize::from(self.positions[tile].z) * 2)
-            .map(|(index, _)| index)
-            .unwrap_or_else(|| thread_rng().gen_range(0, placable_tiles.len()));
+        self.tiles_left -= self.group_size;
 
-        let tile_id1 = placable_tiles.swap_remove(placable_tile_index);
+        let group = (0..self.group_size)
+            .map(|_| self.take_placable_tile(&mut placable_tiles))
+            .collect::<Vec<_>>();
 
-        let placable_tile_index = placable_tiles
-            .iter()
-            .enumerate()
-            .find(|(_, &tile)| self.tiles_left == usize::from(self.positions[tile].z) * 2)
-            .map(|(index, _)| index)
-            .unwrap_or_else(|| thread_rng().gen_range(0, placable_tiles.len()));
+        for &tile_id in &group {
+            self.states[tile_id] = Placed;
+        }
+        for &tile_id in &group {
+            self.update_unplaced_neighbours_shuffle_states(tile_id);
+        }
 
-        let tile_id2 = placable_tiles.swap_remove(placable_tile_index);
+        self.placed.push(group);
 
-        self.states[tile_id1] = Placed;
-        self.states[tile_id2] = Placed;
+        true
+    }
 
-        self.update_unplaced_neighbours_shuffle_states(tile_id1);
-        self.update_unplaced_neighbours_shuffle_states(tile_id2);
+    fn assign_types(&mut self) -> Vec<TileType> {
+        let mut type_groups = self
+            .types
+            .chunks(self.group_size)
+            .map(<[TileType]>::to_vec)
+            .collect::<Vec<_>>();
+        let mut set_types = vec![None; self.positions.len()];
 
-        let random_index = thread_rng().gen_range(0, self.available_types.len() / 2) * 2;
-        let tile_type1 = self.available_types.swap_remove(random_index + 1);
-        let tile_type2 = self.available_types.swap_remove(random_index);
+        for group in &self.placed {
+            let random_index = self.rng.gen_range(0, type_groups.len());
+            let type_group = type_groups.swap_remove(random_index);
 
-        self.set_types[tile_id1] = Some(tile_type1);
-        self.set_types[tile_id2] = Some(tile_type2);
+            for (&tile_id, &tile_type) in group.iter().zip(type_group.iter()) {
+                set_types[tile_id] = Some(tile_type);
+            }
+        }
+
+        set_types.iter().filter_map(|t| *t).collect()
+    }
+
+    fn take_placable_tile(&mut self, placable_tiles: &mut Vec<usize>) -> usize {
+        let tiles_left = self.tiles_left;
+        let positions = self.positions;
+        let group_size = self.group_size;
+
+        let placable_tile_index = placable_tiles
+            .iter()
+            .position(|&tile| tiles_left == usize::from(positions[tile].z) * group_size)
+            .unwrap_or_else(|| self.rng.gen_range(0, placable_tiles.len()));
+
+        placable_tiles.swap_remove(placable_tile_index)
     }
 
     fn get_placable_tiles(&self) -> Vec<usize> {
@@ -156,49 +291,204 @@ impl<R> TypeShuffler<'_, R> where R: Rng {
     }
 
     fn update_unplaced_neighbours_shuffle_states(&mut self, tile_id: usize) {
-        use self::Direction::*;
-
         for neighbour in &self.neighbours[tile_id] {
             if self.states[neighbour.id] == Blocked {
-                let any_up = self.any_unplaced_neighbour_in_direction(neighbour.id, Up);
-                let any_left = self.any_unplaced_neighbour_in_direction(neighbour.id, Left);
-                let any_right = self.any_unplaced_neighbour_in_direction(neighbour.id, Right);
+                let states = &self.states;
+                let is_unplaced = |id: usize| states[id] != Placed;
+
+                let (position, neighbours) =
+                    (self.positions[neighbour.id], &self.neighbours[neighbour.id]);
 
-                if !(any_up || (any_left && any_right)) {
-                    
+                if self.rule.is_free(position, neighbours, &is_unplaced) {
                     self.states[neighbour.id] = Placable;
                 }
             }
         }
     }
-
-    fn any_unplaced_neighbour_in_direction(&self, tile: usize, direction: Direction) -> bool {
-        self.neighbours[tile]
-            .iter()
-            .filter(|neighbour| neighbour.direction == direction)
-            .any(|neighbour| self.states[neighbour.id] != Placed)
-    }
-}
-
-fn get_tile_types() -> Vec<TileType> {
-    let mut tile_types = Vec::new();
-    for tile_type in TileType::iter() {
-        for _ in 0..tile_type.max_allowed() {
-            tile_types.push(*tile_type);
-        }
-    }
-    tile_types
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 enum ShuffleState {
+    #[default]
     Blocked,
     Placable,
     Placed,
 }
 
-impl Default for ShuffleState {
-    fn default() -> Self {
-        Blocked
+#[cfg(test)]
+mod tests {
+    use {
+        std::collections::HashMap,
+        rand::rngs::SmallRng,
+        crate::board::get_raw_positios,
+        super::{
+            get_seeded_shuffled_types,
+            ShufflerBuilder,
+            super::{
+                Direction::*,
+                Neighbour,
+                PlayState,
+                Position,
+                RuleSet,
+                TileId,
+                Tiles,
+                TileSet,
+                TileType,
+            },
+        },
+    };
+
+    const SEEDS: u64 = 2000;
+    const OTHER_SEEDS: u64 = 500;
+
+    /// Deals with the given seed and returns the dealt types together with the pairs in the
+    /// order the shuffler placed them.
+    fn deal(tiles: &Tiles, seed: u64) -> (Vec<TileType>, Vec<Vec<usize>>) {
+        deal_groups(tiles, tiles.tile_set.types(), 2, seed)
+    }
+
+    fn deal_groups(
+        tiles: &Tiles,
+        types: Vec<TileType>,
+        group_size: usize,
+        seed: u64,
+    ) -> (Vec<TileType>, Vec<Vec<usize>>) {
+        let mut shuffler = ShufflerBuilder::<SmallRng>::new(&tiles.positions, &tiles.neighbours)
+            .free_rule(tiles.rules.free_rule())
+            .types(types)
+            .group_size(group_size)
+            .seed_rng(seed)
+            .build()
+            .unwrap();
+
+        shuffler.place_all_groups_within_deals().unwrap();
+
+        let types = shuffler.assign_types();
+        (types, shuffler.placed)
+    }
+
+    static RULE_SETS: [RuleSet; 3] = [RuleSet::Classic, RuleSet::Strict, RuleSet::ThreeDimensional];
+
+    #[test]
+    fn seeded_deals_are_reproducible() {
+        let tiles = Tiles::new(&mut get_raw_positios(), RuleSet::Classic);
+        let rule = tiles.rules.free_rule();
+        let deal = |seed| {
+            let tile_set = &tiles.tile_set;
+            get_seeded_shuffled_types(&tiles.positions, &tiles.neighbours, rule, tile_set, seed)
+                .unwrap()
+        };
+
+        for seed in 0..10 {
+            assert_eq!(deal(seed), deal(seed));
+        }
+    }
+
+    fn count_types(types: Vec<TileType>) -> HashMap<TileType, usize> {
+        let mut counts = HashMap::new();
+        for tile_type in types {
+            *counts.entry(tile_type).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn deals_contain_full_tile_set() {
+        let tiles = Tiles::new(&mut get_raw_positios(), RuleSet::Classic);
+        let expected = count_types(TileSet::standard().types());
+
+        for seed in 0..SEEDS {
+            let (types, _) = deal(&tiles, seed);
+            assert_eq!(count_types(types), expected, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn deals_follow_tile_set() {
+        for tile_set in TileSet::presets() {
+            let mut tiles = Tiles::new(&mut get_raw_positios(), RuleSet::Classic);
+            tiles.set_tile_set(tile_set.clone()).unwrap();
+            let expected = count_types(tile_set.types());
+
+            for seed in 0..OTHER_SEEDS {
+                let (types, pairs) = deal(&tiles, seed);
+
+                for pair in &pairs {
+                    assert!(tile_set.matches(types[pair[0]], types[pair[1]]), "seed {}", seed);
+                }
+                assert_eq!(count_types(types), expected, "{} seed {}", tile_set.name(), seed);
+            }
+        }
+    }
+
+    #[test]
+    fn deals_are_solvable() {
+        for &rules in &RULE_SETS {
+            let mut tiles = Tiles::new(&mut get_raw_positios(), rules);
+            let seeds = if rules == RuleSet::Classic { SEEDS } else { OTHER_SEEDS };
+
+            for seed in 0..seeds {
+                let (types, pairs) = deal(&tiles, seed);
+                tiles.types = types;
+                tiles.reset_states();
+
+                for pair in &pairs {
+                    let (tile1, tile2) = (pair[0], pair[1]);
+                    let context = format!("{:?} seed {}", rules, seed);
+                    assert_eq!(tiles.states[tile1], PlayState::Playable, "{}", context);
+                    assert_eq!(tiles.states[tile2], PlayState::Playable, "{}", context);
+                    assert!(tiles.are_matching(TileId(tile1), TileId(tile2)), "{}", context);
+
+                    tiles.play_tile(TileId(tile1));
+                    tiles.play_tile(TileId(tile2));
+                }
+
+                assert!(tiles.states.iter().all(|&state| state == PlayState::Played));
+            }
+        }
+    }
+
+    #[test]
+    fn triple_deals_are_playable_together() {
+        let tiles = Tiles::new(&mut get_raw_positios(), RuleSet::Classic);
+        let types = TileType::iter()
+            .cycle()
+            .take(48)
+            .flat_map(|&tile_type| vec![tile_type; 3])
+            .collect::<Vec<_>>();
+
+        for seed in 0..OTHER_SEEDS {
+            let (dealt, triples) = deal_groups(&tiles, types.clone(), 3, seed);
+            let mut tiles = Tiles::new(&mut get_raw_positios(), RuleSet::Classic);
+            tiles.types = dealt;
+            tiles.reset_states();
+
+            for triple in &triples {
+                for &tile in triple {
+                    assert_eq!(tiles.states[tile], PlayState::Playable, "seed {}", seed);
+                    assert_eq!(tiles.types[tile], tiles.types[triple[0]], "seed {}", seed);
+                }
+                for &tile in triple {
+                    tiles.play_tile(TileId(tile));
+                }
+            }
+
+            assert!(tiles.is_cleared());
+        }
+    }
+
+    #[test]
+    fn layouts_that_cannot_be_dealt_give_up() {
+        // a tile on top of another is the only free one until it is gone, so no pair is placable
+        let positions = [(0, 0, 0), (0, 0, 1)].iter().map(Position::from).collect::<Vec<_>>();
+        let neighbours = vec![vec![Neighbour::new(1, Up)], vec![Neighbour::new(0, Down)]];
+
+        let shuffler = ShufflerBuilder::<SmallRng>::new(&positions, &neighbours)
+            .types(vec![TileType::BallOne; 2])
+            .seed_rng(0)
+            .build()
+            .unwrap();
+
+        assert!(shuffler.shuffle().is_err());
     }
 }
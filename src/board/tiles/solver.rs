@@ -0,0 +1,280 @@
+use {
+    std::{
+        cmp::Reverse,
+        collections::HashSet,
+    },
+    rand::{
+        rngs::SmallRng,
+        seq::SliceRandom,
+        FromEntropy,
+    },
+    super::{
+        position::Direction,
+        PlayState::Played,
+        TileId,
+        Tiles,
+    },
+};
+
+const FIRST_RESTART_POSITIONS: usize = 200;
+
+/// Returns whether the tiles left can be cleared, or `None` when the search gave up after
+/// visiting `max_positions` board positions.
+pub fn is_solvable(tiles: &mut Tiles, max_positions: usize) -> Option<bool> {
+    let mut solver = Solver::new(tiles);
+
+    let mut positions_left = max_positions;
+    let mut restart_positions = FIRST_RESTART_POSITIONS;
+    while positions_left > 0 {
+        let search_positions = restart_positions.min(positions_left);
+        let mut search_positions_left = search_positions;
+        if let Some(solvable) = solver.solve(&mut search_positions_left) {
+            return Some(solvable);
+        }
+
+        positions_left -= search_positions;
+        restart_positions *= 2;
+    }
+    None
+}
+
+/// Returns every pair of free tiles that match.
+pub fn matching_pairs(tiles: &Tiles) -> Vec<(TileId, TileId)> {
+    let playable = tiles.playable_tiles().collect::<Vec<_>>();
+
+    let mut pairs = Vec::new();
+    for (index, &tile1) in playable.iter().enumerate() {
+        for &tile2 in &playable[index + 1..] {
+            if tiles.are_matching(tile1, tile2) {
+                pairs.push((tile1, tile2));
+            }
+        }
+    }
+    pairs
+}
+
+/**
+ * A depth-first search over the pairs that can be played, which plays and takes back the pairs
+ * on the tiles themselves. Positions that were searched without success are remembered, as many
+ * orders of playing the same pairs lead to the same position.
+ *
+ * When every tile left of a kind is free, playing them can't block anything, so such a pair is
+ * the only one tried. Otherwise the tiles blocking the most others go first, and as a search
+ * can get lost in a hopeless part of the game, it is restarted with a larger budget and a
+ * different order of equally good pairs. The last two tiles of a kind that lie on top of each
+ * other can never be played, which cuts a search short.
+ */
+struct Solver<'a> {
+    tiles: &'a mut Tiles,
+    kinds: Vec<usize>,
+    below: Vec<Vec<usize>>,
+    dead_ends: HashSet<Vec<u64>>,
+    rng: SmallRng,
+}
+
+impl<'a> Solver<'a> {
+    fn new(tiles: &'a mut Tiles) -> Self {
+        // the first tile that matches a tile stands for its kind
+        let kinds = (0..tiles.len())
+            .map(|tile| {
+                (0..tile)
+                    .find(|&other| tiles.are_matching(TileId(other), TileId(tile)))
+                    .unwrap_or(tile)
+            }).collect();
+        let below = (0..tiles.len()).map(|tile| tiles_below(tiles, tile)).collect();
+
+        Solver {
+            tiles,
+            kinds,
+            below,
+            dead_ends: HashSet::new(),
+            rng: SmallRng::from_entropy(),
+        }
+    }
+
+    fn solve(&mut self, positions_left: &mut usize) -> Option<bool> {
+        if self.tiles.is_cleared() {
+            return Some(true);
+        }
+        let key = self.played_key();
+        if self.dead_ends.contains(&key) || self.has_buried_pair() {
+            return Some(false);
+        }
+        if *positions_left == 0 {
+            return None;
+        }
+        *positions_left -= 1;
+
+        let mut gave_up = false;
+        for (tile1, tile2) in self.pairs_to_try() {
+            self.tiles.play_tile(tile1);
+            self.tiles.play_tile(tile2);
+            let result = self.solve(positions_left);
+            self.tiles.reset_tile(tile2);
+            self.tiles.reset_tile(tile1);
+
+            match result {
+                Some(true) => return Some(true),
+                Some(false) => {}
+                None => gave_up = true,
+            }
+            if *positions_left == 0 {
+                return None;
+            }
+        }
+
+        if gave_up {
+            return None;
+        }
+        self.dead_ends.insert(key);
+        Some(false)
+    }
+
+    fn has_buried_pair(&self) -> bool {
+        let mut last_tiles = vec![Vec::new(); self.kinds.len()];
+        for (tile, &state) in self.tiles.states.iter().enumerate() {
+            if state != Played {
+                last_tiles[self.kinds[tile]].push(tile);
+            }
+        }
+
+        last_tiles.iter().any(|tiles| match tiles[..] {
+            [tile1, tile2] => {
+                self.below[tile1].contains(&tile2) || self.below[tile2].contains(&tile1)
+            }
+            _ => false,
+        })
+    }
+
+    fn pairs_to_try(&mut self) -> Vec<(TileId, TileId)> {
+        let mut left = vec![0; self.kinds.len()];
+        let mut free = vec![0; self.kinds.len()];
+        for (tile, &state) in self.tiles.states.iter().enumerate() {
+            if state != Played {
+                left[self.kinds[tile]] += 1;
+            }
+        }
+        for TileId(tile) in self.tiles.playable_tiles() {
+            free[self.kinds[tile]] += 1;
+        }
+
+        let mut pairs = matching_pairs(self.tiles);
+        let safe_pair = pairs.iter().find(|&&(TileId(tile), _)| {
+            let kind = self.kinds[tile];
+            left[kind] == free[kind]
+        });
+        if let Some(&pair) = safe_pair {
+            return vec![pair];
+        }
+
+        // ties are broken at random so each search takes a different path
+        pairs.shuffle(&mut self.rng);
+        let mut scored = pairs
+            .into_iter()
+            .map(|(tile1, tile2)| {
+                (self.blocked_tiles(tile1) + self.blocked_tiles(tile2), (tile1, tile2))
+            }).collect::<Vec<_>>();
+        scored.sort_by_key(|&(blocked, _)| Reverse(blocked));
+        scored.into_iter().map(|(_, pair)| pair).collect()
+    }
+
+    /// Counts the tiles left below a tile and the tiles left in its row.
+    fn blocked_tiles(&self, TileId(tile): TileId) -> usize {
+        let tiles = &self.tiles;
+        let below = self.below[tile].iter().filter(|&&id| tiles.states[id] != Played).count();
+
+        let mut in_row = 0;
+        let mut to_visit = vec![tile];
+        let mut visited = vec![tile];
+        while let Some(tile) = to_visit.pop() {
+            for neighbour in &tiles.neighbours[tile] {
+                let sideways = matches!(neighbour.direction, Direction::Left | Direction::Right);
+                let id = neighbour.id;
+                if sideways && tiles.states[id] != Played && !visited.contains(&id) {
+                    visited.push(id);
+                    to_visit.push(id);
+                    in_row += 1;
+                }
+            }
+        }
+        below + in_row
+    }
+
+    fn played_key(&self) -> Vec<u64> {
+        let mut key = vec![0; self.tiles.states.len().div_ceil(64)];
+        for (index, &state) in self.tiles.states.iter().enumerate() {
+            if state == Played {
+                key[index / 64] |= 1 << (index % 64);
+            }
+        }
+        key
+    }
+}
+
+/// Returns every tile under a tile, all the way down.
+fn tiles_below(tiles: &Tiles, tile: usize) -> Vec<usize> {
+    let mut below = Vec::new();
+    let mut to_visit = vec![tile];
+    while let Some(tile) = to_visit.pop() {
+        for neighbour in &tiles.neighbours[tile] {
+            if neighbour.direction == Direction::Down && !below.contains(&neighbour.id) {
+                below.push(neighbour.id);
+                to_visit.push(neighbour.id);
+            }
+        }
+    }
+    below
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::board::get_raw_positios,
+        super::{
+            is_solvable,
+            matching_pairs,
+            super::{
+                RuleSet,
+                Tiles,
+                TileType,
+            },
+        },
+    };
+
+    const MAX_POSITIONS: usize = 20_000;
+
+    #[test]
+    fn deals_are_never_found_stuck() {
+        for seed in 0..5 {
+            let mut tiles = Tiles::new(&mut get_raw_positios(), RuleSet::Classic);
+            tiles.reset_with_seed(seed).unwrap();
+            let states = tiles.states.clone();
+
+            assert_ne!(is_solvable(&mut tiles, MAX_POSITIONS), Some(false));
+            assert_eq!(tiles.states, states);
+        }
+    }
+
+    #[test]
+    fn board_of_one_kind_is_solvable() {
+        let mut tiles = Tiles::new(&mut get_raw_positios(), RuleSet::Classic);
+        tiles.types = vec![TileType::BallOne; tiles.len()];
+
+        assert_eq!(is_solvable(&mut tiles, MAX_POSITIONS), Some(true));
+    }
+
+    #[test]
+    fn stuck_board_is_not_solvable() {
+        let mut tiles = Tiles::new(&mut get_raw_positios(), RuleSet::Classic);
+        tiles.reset_with_seed(0).unwrap();
+
+        // play the first pair found until no pair is left
+        while let Some(&(tile1, tile2)) = matching_pairs(&tiles).first() {
+            tiles.play_tile(tile1);
+            tiles.play_tile(tile2);
+        }
+
+        let expected = if tiles.is_cleared() { Some(true) } else { Some(false) };
+        assert_eq!(is_solvable(&mut tiles, MAX_POSITIONS), expected);
+    }
+}
@@ -0,0 +1,434 @@
+use {
+    std::{
+        cell::RefCell,
+        collections::HashMap,
+        path::PathBuf,
+    },
+    sdl2::{
+        image::LoadSurface,
+        pixels::{
+            Color,
+            PixelFormatEnum,
+        },
+        rect::Rect,
+        render::{
+            BlendMode,
+            Texture,
+            TextureCreator,
+            WindowCanvas,
+        },
+        surface::Surface,
+        video::WindowContext,
+    },
+    crate::assets,
+    super::{
+        Theme,
+        TileType,
+    },
+};
+
+static ERROR_MESSAGE: &str = "error loading texture";
+
+static BACK_WIDTH: u32 = 46;
+static BACK_HEIGHT: u32 = 57;
+static BACK_BORDER: u32 = 3;
+static BACK_COLOR: Color = Color { r: 40, g: 110, b: 70, a: 255 };
+static BACK_BORDER_COLOR: Color = Color { r: 70, g: 150, b: 100, a: 255 };
+
+/// The widest the atlas gets before images go on a new row.
+static ATLAS_WIDTH: u32 = 1024;
+/// The space between images in the atlas, so scaled images don't take on their neighbours.
+static ATLAS_PADDING: u32 = 2;
+
+/// How many layers are kept, enough for two boards drawn side by side.
+static LAYER_COUNT: usize = 2;
+
+/**
+ * The images of a theme, packed into a single texture so drawing the tiles doesn't switch
+ * textures. Highlighted faces are drawn as the face with the highlight colour multiplied over it.
+ * The images are scaled down when the atlas would be larger than the renderer takes.
+ */
+pub struct TileTextures<'tc> {
+    texture_creator: &'tc TextureCreator<WindowContext>,
+    /// The atlas, whose colour is changed while highlighted faces are drawn.
+    atlas: RefCell<Texture<'tc>>,
+    images: HashMap<TextureId, Rect>,
+    highlight: Color,
+    /// The layers drawn most recently, the latest first.
+    layers: RefCell<Vec<Layer<'tc>>>,
+}
+
+impl<'tc> TileTextures<'tc> {
+    pub fn new(
+        texture_creator: &'tc TextureCreator<WindowContext>,
+        theme: &Theme,
+        max_size: (u32, u32),
+    ) -> Self {
+        use self::TextureId::*;
+
+        // an image of the theme that can't be loaded is replaced by the built in one
+        let load = |path: Option<PathBuf>, default: &[u8]| {
+            path.and_then(|path| Surface::from_file(path).ok())
+                .map_or_else(|| assets::load_surface(default), Ok)
+                .expect(ERROR_MESSAGE)
+        };
+
+        let mut surfaces = TileType::iter()
+            .map(|&tile_type| {
+                (Face(tile_type), load(theme.face(tile_type), assets::tile_face(tile_type)))
+            }).collect::<Vec<_>>();
+        let back = theme
+            .back()
+            .and_then(|path| Surface::from_file(path).ok())
+            .unwrap_or_else(create_back_surface);
+        surfaces.push((Back, back));
+        surfaces.push((Side, load(theme.side(), assets::TILE_SIDE)));
+        surfaces.push((Bottom, load(theme.bottom(), assets::TILE_BOTTOM)));
+
+        let sizes = surfaces.iter().map(|(_, surface)| surface.size()).collect::<Vec<_>>();
+        let (rects, (width, height)) = pack_within(&sizes, max_size);
+        let mut atlas =
+            Surface::new(width, height, PixelFormatEnum::RGBA8888).expect(ERROR_MESSAGE);
+        for ((_, surface), &rect) in surfaces.iter_mut().zip(rects.iter()) {
+            // copy the pixels as they are, alpha included, rather than blending them in
+            surface.set_blend_mode(BlendMode::None).expect(ERROR_MESSAGE);
+            if surface.size() == rect.size() {
+                surface.blit(None, &mut atlas, rect).expect(ERROR_MESSAGE);
+            } else {
+                surface.blit_scaled(None, &mut atlas, rect).expect(ERROR_MESSAGE);
+            }
+        }
+
+        let mut texture = texture_creator
+            .create_texture_from_surface(&atlas)
+            .expect(ERROR_MESSAGE);
+        texture.set_blend_mode(BlendMode::Blend);
+
+        let [r, g, b] = theme.highlight();
+        TileTextures {
+            texture_creator,
+            atlas: RefCell::new(texture),
+            images: surfaces.iter().map(|&(id, _)| id).zip(rects).collect(),
+            highlight: Color::RGB(r, g, b),
+            layers: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn face(&self, tile_type: TileType) -> Image<'_, 'tc> {
+        self.image(TextureId::Face(tile_type))
+    }
+
+    pub fn side(&self) -> Image<'_, 'tc> {
+        self.image(TextureId::Side)
+    }
+
+    pub fn bottom(&self) -> Image<'_, 'tc> {
+        self.image(TextureId::Bottom)
+    }
+
+    /// The image of a tile lying face-down.
+    pub fn back(&self) -> Image<'_, 'tc> {
+        self.image(TextureId::Back)
+    }
+
+    /// Draws the face of the tile, tinted in the highlight colour when it is highlighted.
+    pub fn draw_face(
+        &self,
+        canvas: &mut WindowCanvas,
+        tile_type: TileType,
+        highlighted: bool,
+        rect: Rect,
+    ) {
+        let highlight = if highlighted { u8::MAX } else { 0 };
+        self.draw(canvas, Sprite::face(tile_type, highlight, rect));
+    }
+
+    pub fn draw(&self, canvas: &mut WindowCanvas, sprite: Sprite) {
+        let image = self.image(sprite.image);
+        if sprite.highlight == 0 {
+            image.draw_flipped(canvas, sprite.rect, sprite.flip_x, sprite.flip_y);
+            return;
+        }
+
+        // a faint highlight multiplies with a colour closer to white; only the colour of the
+        // image is changed, so its transparent corners stay transparent
+        let strength = u32::from(sprite.highlight);
+        let tint = |channel: u8| 255 - ((255 - u32::from(channel)) * strength / 255) as u8;
+        let Color { r, g, b, .. } = self.highlight;
+        self.atlas.borrow_mut().set_color_mod(tint(r), tint(g), tint(b));
+        image.draw_flipped(canvas, sprite.rect, sprite.flip_x, sprite.flip_y);
+        self.atlas.borrow_mut().set_color_mod(u8::MAX, u8::MAX, u8::MAX);
+    }
+
+    /**
+     * Draws the sprites through a layer that keeps the picture they make, so sprites that are
+     * the same as in a recent frame take a single copy instead of drawing every one of them
+     * again. Sprites are drawn straight to the canvas when it can't draw to textures.
+     *
+     * The layer is drawn first, onto the cleared canvas: its colours already had the alpha of
+     * the sprites applied when they were drawn into it, so the part of it the sprites cover is
+     * copied as it is rather than blended in a second time.
+     */
+    pub fn draw_layer(&self, canvas: &mut WindowCanvas, sprites: Vec<Sprite>) {
+        let size = canvas.output_size().unwrap_or_else(|_| canvas.logical_size());
+        let mut layers = self.layers.borrow_mut();
+
+        let cached = layers.iter().position(|layer| layer.size == size && layer.sprites == sprites);
+        let layer = match cached {
+            Some(index) => layers.remove(index),
+            None => {
+                // the texture of the least recently drawn layer is drawn over once there are
+                // enough layers
+                let texture = if layers.len() < LAYER_COUNT {
+                    None
+                } else {
+                    layers.pop().filter(|layer| layer.size == size).map(|layer| layer.texture)
+                };
+                match self.render_layer(canvas, texture, size, &sprites) {
+                    Some(texture) => Layer { sprites, size, texture },
+                    None => {
+                        for sprite in sprites {
+                            self.draw(canvas, sprite);
+                        }
+                        return;
+                    }
+                }
+            }
+        };
+
+        let (scale_x, scale_y) = layer_scale(canvas, size);
+        let canvas_width = (size.0 as f32 / scale_x) as u32;
+        let canvas_height = (size.1 as f32 / scale_y) as u32;
+        let covered = sprites_bounds(&layer.sprites)
+            .and_then(|bounds| bounds.intersection(Rect::new(0, 0, canvas_width, canvas_height)));
+        if let Some(covered) = covered {
+            let source = Rect::new(
+                (covered.x() as f32 * scale_x) as i32,
+                (covered.y() as f32 * scale_y) as i32,
+                (covered.width() as f32 * scale_x).ceil() as u32,
+                (covered.height() as f32 * scale_y).ceil() as u32,
+            );
+            let _ = canvas.copy(&layer.texture, source, covered);
+        }
+        layers.insert(0, layer);
+    }
+
+    fn render_layer(
+        &self,
+        canvas: &mut WindowCanvas,
+        texture: Option<Texture<'tc>>,
+        (width, height): (u32, u32),
+        sprites: &[Sprite],
+    ) -> Option<Texture<'tc>> {
+        let mut texture = match texture {
+            Some(texture) => texture,
+            None => self
+                .texture_creator
+                .create_texture_target(PixelFormatEnum::RGBA8888, width, height)
+                .ok()?,
+        };
+        texture.set_blend_mode(BlendMode::None);
+
+        let (scale_x, scale_y) = layer_scale(canvas, (width, height));
+
+        canvas
+            .with_texture_canvas(&mut texture, |canvas| {
+                canvas.set_draw_color(Color::RGBA(0, 0, 0, 0));
+                canvas.clear();
+                let _ = canvas.set_scale(scale_x, scale_y);
+                for &sprite in sprites {
+                    self.draw(canvas, sprite);
+                }
+            }).ok()?;
+        Some(texture)
+    }
+
+    fn image(&self, id: TextureId) -> Image<'_, 'tc> {
+        Image {
+            atlas: &self.atlas,
+            rect: self.images[&id],
+        }
+    }
+}
+
+/// Returns how many pixels of a layer of the size there are to a point of the canvas, where the
+/// layer has a pixel for every pixel of the window, so it is as sharp as the canvas.
+fn layer_scale(canvas: &WindowCanvas, (width, height): (u32, u32)) -> (f32, f32) {
+    let (canvas_width, canvas_height) = match canvas.logical_size() {
+        (0, _) | (_, 0) => (width, height),
+        size => size,
+    };
+    (width as f32 / canvas_width as f32, height as f32 / canvas_height as f32)
+}
+
+/// Returns the part of the canvas the sprites are drawn on, if any.
+fn sprites_bounds(sprites: &[Sprite]) -> Option<Rect> {
+    sprites.iter().map(|sprite| sprite.rect).reduce(|bounds, rect| bounds.union(rect))
+}
+
+/// An image of the atlas where it is drawn on the canvas.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {
+    image: TextureId,
+    rect: Rect,
+    flip_x: bool,
+    flip_y: bool,
+    /// How strongly a face is highlighted, from 0 for not at all.
+    highlight: u8,
+}
+
+impl Sprite {
+    pub fn face(tile_type: TileType, highlight: u8, rect: Rect) -> Self {
+        Sprite::new(TextureId::Face(tile_type), rect, (false, false), highlight)
+    }
+
+    pub fn back(rect: Rect) -> Self {
+        Sprite::new(TextureId::Back, rect, (false, false), 0)
+    }
+
+    pub fn side(rect: Rect, (flip_x, flip_y): (bool, bool)) -> Self {
+        Sprite::new(TextureId::Side, rect, (flip_x, flip_y), 0)
+    }
+
+    pub fn bottom(rect: Rect, (flip_x, flip_y): (bool, bool)) -> Self {
+        Sprite::new(TextureId::Bottom, rect, (flip_x, flip_y), 0)
+    }
+
+    fn new(image: TextureId, rect: Rect, flips: (bool, bool), highlight: u8) -> Self {
+        let (flip_x, flip_y) = flips;
+        Sprite { image, rect, flip_x, flip_y, highlight }
+    }
+}
+
+/// A picture of sprites drawn into a texture the size of the window.
+struct Layer<'tc> {
+    sprites: Vec<Sprite>,
+    size: (u32, u32),
+    texture: Texture<'tc>,
+}
+
+/// An image in the atlas.
+#[derive(Clone, Copy)]
+pub struct Image<'a, 'tc> {
+    atlas: &'a RefCell<Texture<'tc>>,
+    rect: Rect,
+}
+
+impl Image<'_, '_> {
+    pub fn draw(self, canvas: &mut WindowCanvas, rect: Rect) {
+        let _ = canvas.copy(&self.atlas.borrow(), self.rect, rect);
+    }
+
+    pub fn draw_flipped(self, canvas: &mut WindowCanvas, rect: Rect, flip_x: bool, flip_y: bool) {
+        let atlas = self.atlas.borrow();
+        let _ = canvas.copy_ex(&atlas, self.rect, rect, 0.0, None, flip_x, flip_y);
+    }
+}
+
+/**
+ * Places images of the sizes in rows no wider than the atlas may be, scaled down until the whole
+ * is no larger than the size, and returns where each goes and the size of the whole.
+ */
+fn pack_within(
+    sizes: &[(u32, u32)],
+    (max_width, max_height): (u32, u32),
+) -> (Vec<Rect>, (u32, u32)) {
+    let row_width = ATLAS_WIDTH.min(max_width);
+    let mut scale = 1.0;
+    loop {
+        let scaled = sizes
+            .iter()
+            .map(|&(width, height)| {
+                let size = |length: u32| ((f64::from(length) * scale) as u32).max(1);
+                (size(width).min(row_width), size(height))
+            }).collect::<Vec<_>>();
+        let (rects, (width, height)) = pack(&scaled, row_width);
+        let fits = width <= max_width && height <= max_height;
+        if fits || scaled.iter().all(|&size| size == (1, 1)) {
+            return (rects, (width, height));
+        }
+        // shrink by at least a little, so the images get smaller every time round
+        scale *= (f64::from(max_height) / f64::from(height)).sqrt().min(0.9);
+    }
+}
+
+/// Places images of the sizes in rows, and returns where each goes and the size of the whole.
+fn pack(sizes: &[(u32, u32)], row_width: u32) -> (Vec<Rect>, (u32, u32)) {
+    let (mut x, mut y, mut row_height, mut width) = (0, 0, 0, 1);
+    let rects = sizes
+        .iter()
+        .map(|&(image_width, image_height)| {
+            if x > 0 && x + image_width > row_width {
+                x = 0;
+                y += row_height + ATLAS_PADDING;
+                row_height = 0;
+            }
+            let rect = Rect::new(x as i32, y as i32, image_width, image_height);
+            x += image_width + ATLAS_PADDING;
+            row_height = row_height.max(image_height);
+            width = width.max(x - ATLAS_PADDING);
+            rect
+        }).collect();
+    (rects, (width, (y + row_height).max(1)))
+}
+
+fn create_back_surface() -> Surface<'static> {
+    let mut surface = Surface::new(BACK_WIDTH, BACK_HEIGHT, PixelFormatEnum::RGBA8888)
+        .expect(ERROR_MESSAGE);
+    let inner = Rect::new(
+        BACK_BORDER as i32,
+        BACK_BORDER as i32,
+        BACK_WIDTH - 2 * BACK_BORDER,
+        BACK_HEIGHT - 2 * BACK_BORDER,
+    );
+    surface.fill_rect(None, BACK_BORDER_COLOR).expect(ERROR_MESSAGE);
+    surface.fill_rect(inner, BACK_COLOR).expect(ERROR_MESSAGE);
+    surface
+}
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+enum TextureId {
+    Face(TileType),
+    Back,
+    Bottom,
+    Side,
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        sdl2::rect::Rect,
+        super::{
+            pack,
+            pack_within,
+            ATLAS_PADDING,
+            ATLAS_WIDTH,
+        },
+    };
+
+    #[test]
+    fn images_are_packed_in_rows_without_overlapping() {
+        let sizes = vec![(46, 57); 30];
+        let (rects, (width, height)) = pack(&sizes, ATLAS_WIDTH);
+
+        let per_row = ((ATLAS_WIDTH + ATLAS_PADDING) / (46 + ATLAS_PADDING)) as usize;
+        assert_eq!(rects[per_row], Rect::new(0, 57 + ATLAS_PADDING as i32, 46, 57));
+        assert!(width <= ATLAS_WIDTH);
+        assert!(rects.iter().all(|rect| rect.right() <= width as i32));
+        assert!(rects.iter().all(|rect| rect.bottom() <= height as i32));
+        for (index, rect) in rects.iter().enumerate() {
+            assert!(rects[..index].iter().all(|other| !other.contains_point(rect.top_left())));
+        }
+    }
+
+    #[test]
+    fn images_are_scaled_down_to_fit_a_small_renderer() {
+        let sizes = vec![(46, 57); 30];
+        let (rects, (width, height)) = pack_within(&sizes, (256, 256));
+
+        assert!(width <= 256 && height <= 256);
+        assert!(rects.iter().all(|rect| rect.width() < 46 && rect.height() < 57));
+        assert!(rects.iter().all(|rect| rect.right() <= width as i32));
+        assert!(rects.iter().all(|rect| rect.bottom() <= height as i32));
+    }
+}
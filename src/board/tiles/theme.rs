@@ -0,0 +1,241 @@
+use {
+    std::{
+        collections::HashMap,
+        fs,
+        path::{
+            Path,
+            PathBuf,
+        },
+    },
+    serde::Deserialize,
+    super::TileType,
+};
+
+/// Where theme packs are looked for, each in a directory of its own.
+pub static THEME_DIR: &str = "themes";
+
+/// The file in a theme pack that describes it.
+static MANIFEST: &str = "theme.json";
+
+static SIDE_FILE: &str = "TileSide.png";
+static BOTTOM_FILE: &str = "TileBottom.png";
+
+/// The size of a tile at full scale, with the strips that show its side and bottom.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct TileSize {
+    pub width: u32,
+    pub height: u32,
+    pub side_width: u32,
+    pub bottom_height: u32,
+}
+
+impl Default for TileSize {
+    fn default() -> Self {
+        TileSize {
+            width: 46,
+            height: 57,
+            side_width: 5,
+            bottom_height: 5,
+        }
+    }
+}
+
+/// The manifest of a theme pack, with the images given relative to the pack.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Manifest {
+    name: Option<String>,
+    /// The face images by tile type, such as `"BallOne": "ball-1.png"`.
+    faces: HashMap<String, String>,
+    back: Option<String>,
+    side: Option<String>,
+    bottom: Option<String>,
+    tile_size: Option<TileSize>,
+    /// The colour the faces of highlighted tiles are multiplied with.
+    highlight: Option<[u8; 3]>,
+}
+
+/**
+ * How the tiles look: the images of their faces, back and edges, their size and how highlighted
+ * tiles stand out. The images of the default theme are built in. A theme pack is a directory with
+ * a `theme.json` manifest that names its images; any image the pack leaves out or doesn't have is
+ * taken from the default theme.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    name: String,
+    /// The directory of the pack, which the default theme doesn't have.
+    dir: Option<PathBuf>,
+    faces: HashMap<TileType, PathBuf>,
+    back: Option<PathBuf>,
+    side: PathBuf,
+    bottom: PathBuf,
+    tile_size: TileSize,
+    highlight: [u8; 3],
+}
+
+impl Theme {
+    pub fn new() -> Self {
+        Theme {
+            name: "Classic".to_string(),
+            dir: None,
+            faces: HashMap::new(),
+            back: None,
+            side: PathBuf::from(SIDE_FILE),
+            bottom: PathBuf::from(BOTTOM_FILE),
+            tile_size: TileSize::default(),
+            highlight: [255, 127, 127],
+        }
+    }
+
+    /// Reads the theme pack in the directory.
+    pub fn load(dir: &Path) -> Result<Self, &'static str> {
+        let json = fs::read_to_string(dir.join(MANIFEST)).map_err(|_| "theme can't be read")?;
+        let manifest: Manifest = serde_json::from_str(&json).map_err(|_| "theme is invalid")?;
+
+        let tile_size = manifest.tile_size.unwrap_or_default();
+        if tile_size.width < 2 || tile_size.height < 2 {
+            return Err("theme has invalid tile sizes");
+        }
+
+        let mut faces = HashMap::new();
+        for (name, file) in manifest.faces {
+            let tile_type = TileType::iter()
+                .find(|tile_type| tile_type.name() == name)
+                .ok_or("theme has a face for an unknown tile type")?;
+            faces.insert(*tile_type, PathBuf::from(file));
+        }
+
+        let default = Theme::new();
+        let name = dir.file_name().map(|name| name.to_string_lossy().into_owned());
+        Ok(Theme {
+            name: manifest.name.or(name).unwrap_or(default.name),
+            dir: Some(dir.to_path_buf()),
+            faces,
+            back: manifest.back.map(PathBuf::from),
+            side: manifest.side.map_or(default.side, PathBuf::from),
+            bottom: manifest.bottom.map_or(default.bottom, PathBuf::from),
+            tile_size,
+            highlight: manifest.highlight.unwrap_or(default.highlight),
+        })
+    }
+
+    /// Returns the default theme, followed by every theme pack that can be read.
+    pub fn presets() -> Vec<Theme> {
+        let mut dirs = fs::read_dir(THEME_DIR)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.is_dir())
+                    .collect::<Vec<_>>()
+            }).unwrap_or_default();
+        dirs.sort();
+
+        let packs = dirs.iter().filter_map(|dir| Theme::load(dir).ok());
+        Some(Theme::new()).into_iter().chain(packs).collect()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn tile_size(&self) -> TileSize {
+        self.tile_size
+    }
+
+    pub fn highlight(&self) -> [u8; 3] {
+        self.highlight
+    }
+
+    /// Returns the image of the face in the pack, or `None` when the built in one is used.
+    pub fn face(&self, tile_type: TileType) -> Option<PathBuf> {
+        let file = self.faces.get(&tile_type).map(PathBuf::as_path);
+        self.image(file.unwrap_or_else(|| Path::new(tile_type.filename_texture())))
+    }
+
+    pub fn back(&self) -> Option<PathBuf> {
+        self.back.as_ref().and_then(|file| self.image(file))
+    }
+
+    pub fn side(&self) -> Option<PathBuf> {
+        self.image(&self.side)
+    }
+
+    pub fn bottom(&self) -> Option<PathBuf> {
+        self.image(&self.bottom)
+    }
+
+    /// Returns the image of the pack, if it has it.
+    fn image(&self, file: &Path) -> Option<PathBuf> {
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(file))
+            .filter(|path| path.is_file())
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::{
+            env,
+            fs,
+        },
+        super::{
+            Theme,
+            TileSize,
+            TileType,
+        },
+    };
+
+    #[test]
+    fn packs_fall_back_to_the_default_images() {
+        let dir = env::temp_dir().join(format!("mahjong-theme-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("dragon.png"), "").unwrap();
+        fs::write(
+            dir.join("theme.json"),
+            r#"{
+                "name": "Dragons",
+                "faces": { "DragonRed": "dragon.png", "DragonGreen": "missing.png" },
+                "tile_size": { "width": 60, "height": 80, "side_width": 6, "bottom_height": 7 }
+            }"#,
+        ).unwrap();
+
+        let theme = Theme::load(&dir).unwrap();
+        assert_eq!(theme.name(), "Dragons");
+        assert_eq!(theme.face(TileType::DragonRed), Some(dir.join("dragon.png")));
+        assert_eq!(theme.face(TileType::DragonGreen), None);
+        assert_eq!(theme.face(TileType::DragonWhite), None);
+        assert_eq!(theme.back(), None);
+        assert_eq!(Theme::new().face(TileType::DragonRed), None);
+        assert_eq!(theme.highlight(), Theme::new().highlight());
+        let tile_size = TileSize { width: 60, height: 80, side_width: 6, bottom_height: 7 };
+        assert_eq!(theme.tile_size(), tile_size);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_packs_are_rejected() {
+        let dir = env::temp_dir().join(format!("mahjong-bad-theme-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut results = Vec::new();
+        for manifest in &[r#"{ "faces": { "Joker": "joker.png" } }"#, "{ faces"] {
+            fs::write(dir.join("theme.json"), manifest).unwrap();
+            results.push(Theme::load(&dir));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(results[0], Err("theme has a face for an unknown tile type"));
+        assert_eq!(results[1], Err("theme is invalid"));
+        assert_eq!(Theme::load(&dir), Err("theme can't be read"));
+    }
+}
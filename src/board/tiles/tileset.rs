@@ -0,0 +1,293 @@
+use super::TileType::{self, *};
+
+/// Defines which tile types are dealt, how many copies of each, and which types match each other.
+#[derive(Clone, Debug)]
+pub struct TileSet {
+    name: String,
+    groups: Vec<TileGroup>,
+}
+
+#[derive(Clone, Debug)]
+struct TileGroup {
+    types: Vec<TileType>,
+    copies: u8,
+    class: MatchClass,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MatchClass {
+    /// A tile only matches tiles of the same type.
+    SameType,
+    /// A tile matches any tile of its group.
+    Group,
+}
+
+impl TileSet {
+    pub fn new(name: &str) -> Self {
+        TileSet {
+            name: name.to_owned(),
+            groups: Vec::new(),
+        }
+    }
+
+    pub fn group(mut self, types: &[TileType], copies: u8, class: MatchClass) -> Self {
+        self.groups.push(TileGroup {
+            types: types.to_vec(),
+            copies,
+            class,
+        });
+        self
+    }
+
+    /// The full 144 tile set: suits and honours in four copies, with the flowers and the seasons
+    /// matching within their group.
+    pub fn standard() -> Self {
+        TileSet::new("Standard")
+            .group(&BALLS, 4, MatchClass::SameType)
+            .group(&BAMBOOS, 4, MatchClass::SameType)
+            .group(&CHARACTERS, 4, MatchClass::SameType)
+            .group(&WINDS, 4, MatchClass::SameType)
+            .group(&DRAGONS, 4, MatchClass::SameType)
+            .group(&FLOWERS, 1, MatchClass::Group)
+            .group(&SEASONS, 1, MatchClass::Group)
+    }
+
+    /// The standard set where any wind or dragon matches any other wind or dragon.
+    pub fn any_honour() -> Self {
+        TileSet::new("Any honour")
+            .group(&BALLS, 4, MatchClass::SameType)
+            .group(&BAMBOOS, 4, MatchClass::SameType)
+            .group(&CHARACTERS, 4, MatchClass::SameType)
+            .group(&[&WINDS[..], &DRAGONS[..]].concat(), 4, MatchClass::Group)
+            .group(&FLOWERS, 1, MatchClass::Group)
+            .group(&SEASONS, 1, MatchClass::Group)
+    }
+
+    /// A simplified set with only the balls and bamboos, each in eight copies.
+    pub fn children() -> Self {
+        TileSet::new("Children")
+            .group(&BALLS, 8, MatchClass::SameType)
+            .group(&BAMBOOS, 8, MatchClass::SameType)
+    }
+
+    pub fn presets() -> Vec<TileSet> {
+        vec![TileSet::standard(), TileSet::any_honour(), TileSet::children()]
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups
+            .iter()
+            .map(|group| group.types.len() * usize::from(group.copies))
+            .sum()
+    }
+
+    pub fn matches(&self, type1: TileType, type2: TileType) -> bool {
+        if type1 == type2 {
+            return true;
+        }
+
+        self.group_of(type1)
+            .filter(|group| group.class == MatchClass::Group)
+            .is_some_and(|group| group.types.contains(&type2))
+    }
+
+    /// Returns every tile to deal, ordered so that each two consecutive tiles match.
+    pub fn types(&self) -> Vec<TileType> {
+        let mut types = Vec::with_capacity(self.len());
+
+        for group in &self.groups {
+            match group.class {
+                MatchClass::SameType => {
+                    for &tile_type in &group.types {
+                        for _ in 0..group.copies {
+                            types.push(tile_type);
+                        }
+                    }
+                }
+                MatchClass::Group => {
+                    for _ in 0..group.copies {
+                        types.extend_from_slice(&group.types);
+                    }
+                }
+            }
+        }
+
+        types
+    }
+
+    pub fn validate(&self, num_tiles: usize) -> Result<(), &'static str> {
+        if self.len() != num_tiles {
+            return Err("tile set size does not match number of positions");
+        }
+
+        for (index, group) in self.groups.iter().enumerate() {
+            if group.types.is_empty() {
+                return Err("tile set contains an empty group");
+            }
+
+            let unpaired = match group.class {
+                MatchClass::SameType => group.copies % 2 != 0,
+                MatchClass::Group => group.types.len() * usize::from(group.copies) % 2 != 0,
+            };
+            if unpaired {
+                return Err("tile set contains tiles that can not be paired");
+            }
+
+            let duplicate = self.groups[..index]
+                .iter()
+                .any(|other| other.types.iter().any(|t| group.types.contains(t)));
+            if duplicate {
+                return Err("tile set contains a type in more than one group");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn group_of(&self, tile_type: TileType) -> Option<&TileGroup> {
+        self.groups
+            .iter()
+            .find(|group| group.types.contains(&tile_type))
+    }
+}
+
+impl Default for TileSet {
+    fn default() -> Self {
+        TileSet::standard()
+    }
+}
+
+static BALLS: [TileType; 9] = [
+    BallOne, BallTwo, BallThree, BallFour, BallFive, BallSix, BallSeven, BallEight, BallNine,
+];
+static BAMBOOS: [TileType; 9] = [
+    BambooOne, BambooTwo, BambooThree, BambooFour, BambooFive,
+    BambooSix, BambooSeven, BambooEight, BambooNine,
+];
+static CHARACTERS: [TileType; 9] = [
+    CharacterOne, CharacterTwo, CharacterThree, CharacterFour, CharacterFive,
+    CharacterSix, CharacterSeven, CharacterEight, CharacterNine,
+];
+static WINDS: [TileType; 4] = [WindNorth, WindEast, WindSouth, WindWest];
+static DRAGONS: [TileType; 3] = [DragonRed, DragonGreen, DragonWhite];
+static FLOWERS: [TileType; 4] = [FlowerPlum, FlowerOrchid, FlowerBamboo, FlowerChrysanthemum];
+static SEASONS: [TileType; 4] = [SeasonSpring, SeasonSummer, SeasonAutumn, SeasonWinter];
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        MatchClass,
+        TileSet,
+        TileType::{self, *},
+        DRAGONS,
+        FLOWERS,
+        SEASONS,
+        WINDS,
+    };
+
+    #[test]
+    fn flowers_match_any_flower() {
+        let tile_set = TileSet::standard();
+
+        for &flower1 in &FLOWERS {
+            for &flower2 in &FLOWERS {
+                assert!(tile_set.matches(flower1, flower2), "{:?} {:?}", flower1, flower2);
+            }
+        }
+    }
+
+    #[test]
+    fn seasons_match_any_season() {
+        let tile_set = TileSet::standard();
+
+        for &season1 in &SEASONS {
+            for &season2 in &SEASONS {
+                assert!(tile_set.matches(season1, season2), "{:?} {:?}", season1, season2);
+            }
+        }
+    }
+
+    #[test]
+    fn flowers_and_seasons_do_not_match() {
+        let tile_set = TileSet::standard();
+
+        for &flower in &FLOWERS {
+            for &season in &SEASONS {
+                assert!(!tile_set.matches(flower, season));
+                assert!(!tile_set.matches(season, flower));
+            }
+        }
+    }
+
+    #[test]
+    fn other_types_only_match_themselves() {
+        let tile_set = TileSet::standard();
+
+        for &type1 in TileType::iter() {
+            if FLOWERS.contains(&type1) || SEASONS.contains(&type1) {
+                continue;
+            }
+            for &type2 in TileType::iter() {
+                let matches = tile_set.matches(type1, type2);
+                assert_eq!(matches, type1 == type2, "{:?} {:?}", type1, type2);
+            }
+        }
+    }
+
+    #[test]
+    fn any_honour_matches_winds_and_dragons() {
+        let tile_set = TileSet::any_honour();
+
+        for &wind in &WINDS {
+            for &dragon in &DRAGONS {
+                assert!(tile_set.matches(wind, dragon));
+                assert!(tile_set.matches(dragon, wind));
+            }
+        }
+        assert!(!tile_set.matches(WindNorth, BallOne));
+        assert!(!tile_set.matches(WindNorth, FlowerPlum));
+    }
+
+    #[test]
+    fn presets_fill_the_turtle() {
+        for tile_set in TileSet::presets() {
+            assert_eq!(tile_set.len(), 144, "{}", tile_set.name());
+            assert_eq!(tile_set.validate(144), Ok(()), "{}", tile_set.name());
+        }
+    }
+
+    #[test]
+    fn consecutive_types_match() {
+        for tile_set in TileSet::presets() {
+            for pair in tile_set.types().chunks(2) {
+                assert!(tile_set.matches(pair[0], pair[1]), "{} {:?}", tile_set.name(), pair);
+            }
+        }
+    }
+
+    #[test]
+    fn children_set_has_fewer_types() {
+        let mut types = TileSet::children().types();
+        types.dedup();
+
+        assert_eq!(types.len(), 18);
+    }
+
+    #[test]
+    fn validate_rejects_unpairable_sets() {
+        let odd_copies = TileSet::new("odd").group(&[BallOne, BallTwo], 3, MatchClass::SameType);
+        let odd_group = TileSet::new("odd").group(&DRAGONS, 1, MatchClass::Group);
+        let duplicate = TileSet::new("duplicate")
+            .group(&[BallOne], 2, MatchClass::SameType)
+            .group(&[BallOne], 2, MatchClass::SameType);
+
+        assert!(odd_copies.validate(6).is_err());
+        assert!(odd_group.validate(3).is_err());
+        assert!(duplicate.validate(4).is_err());
+        assert!(TileSet::standard().validate(140).is_err());
+    }
+}
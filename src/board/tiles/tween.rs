@@ -0,0 +1,169 @@
+use std::{
+    f32::consts::PI,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// How the progress of a tween speeds up and slows down.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    /// Starts slow and speeds up towards the end.
+    EaseIn,
+    /// Starts fast and slows down towards the end.
+    EaseOut,
+}
+
+impl Easing {
+    /// Shapes the fraction of the duration that has passed into the progress, both from 0 to 1.
+    pub fn apply(self, fraction: f32) -> f32 {
+        match self {
+            Easing::Linear => fraction,
+            Easing::EaseIn => fraction * fraction,
+            Easing::EaseOut => 1.0 - (1.0 - fraction) * (1.0 - fraction),
+        }
+    }
+}
+
+/**
+ * A change that plays out over a duration from the time it starts. A tween is worked out from the
+ * time it is asked about rather than stepped every frame, so it needs no updating and gives the
+ * same answer for the same time.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tween {
+    start: Instant,
+    duration: Duration,
+    easing: Easing,
+}
+
+impl Tween {
+    pub fn new(start: Instant, duration: Duration, easing: Easing) -> Self {
+        Tween {
+            start,
+            duration,
+            easing,
+        }
+    }
+
+    /// Starts the tween later by the delay.
+    pub fn delayed(self, delay: Duration) -> Self {
+        Tween {
+            start: self.start + delay,
+            ..self
+        }
+    }
+
+    pub fn has_started(&self, now: Instant) -> bool {
+        now >= self.start
+    }
+
+    pub fn is_done(&self, now: Instant) -> bool {
+        self.fraction(now) >= 1.0
+    }
+
+    /// Returns the fraction of the duration that has passed, from 0 to 1.
+    pub fn fraction(&self, now: Instant) -> f32 {
+        let elapsed = now.saturating_duration_since(self.start);
+        if self.duration == Duration::from_secs(0) {
+            return if self.has_started(now) { 1.0 } else { 0.0 };
+        }
+        (elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+
+    /// Returns how many whole steps of the length have passed since the tween started.
+    pub fn steps(&self, now: Instant, step: Duration) -> u32 {
+        (now.saturating_duration_since(self.start).as_millis() / step.as_millis().max(1)) as u32
+    }
+
+    /// Returns when the step of the length that is under way at the time started.
+    pub fn step_start(&self, now: Instant, step: Duration) -> Instant {
+        self.start + step * self.steps(now, step)
+    }
+
+    /// Returns how far the change has come, from 0 to 1.
+    pub fn progress(&self, now: Instant) -> f32 {
+        self.easing.apply(self.fraction(now))
+    }
+
+    /// Returns a wave that starts at 1, falls to 0 halfway through the duration and rises back to
+    /// 1, over and over, for changes that repeat until they are stopped.
+    pub fn wave(&self, now: Instant) -> f32 {
+        let elapsed = now.saturating_duration_since(self.start).as_secs_f32();
+        let cycle = (elapsed / self.duration.as_secs_f32()).fract();
+        0.5 + 0.5 * (2.0 * PI * cycle).cos()
+    }
+
+    /// Returns a tween of the same duration that plays the change backwards from where this one
+    /// is now, so a change turned around halfway doesn't jump.
+    pub fn reversed(self, now: Instant, easing: Easing) -> Self {
+        let done = self.duration.mul_f32(1.0 - self.fraction(now));
+        Tween {
+            start: now.checked_sub(done).unwrap_or(now),
+            duration: self.duration,
+            easing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::time::{
+            Duration,
+            Instant,
+        },
+        super::{
+            Easing,
+            Tween,
+        },
+    };
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn tweens_progress_from_their_start_to_their_end() {
+        let start = Instant::now();
+        let tween = Tween::new(start, millis(200), Easing::Linear).delayed(millis(100));
+
+        assert!(!tween.has_started(start));
+        assert_eq!(tween.progress(start), 0.0);
+        assert_eq!(tween.progress(start + millis(200)), 0.5);
+        assert!(!tween.is_done(start + millis(200)));
+        assert_eq!(tween.progress(start + millis(400)), 1.0);
+        assert!(tween.is_done(start + millis(400)));
+
+        let eased = Tween::new(start, millis(200), Easing::EaseOut);
+        assert_eq!(eased.progress(start + millis(100)), 0.75);
+        assert_eq!(Tween::new(start, millis(0), Easing::EaseIn).progress(start), 1.0);
+    }
+
+    #[test]
+    fn reversed_tweens_turn_around_where_they_are() {
+        let start = Instant::now();
+        let tween = Tween::new(start, millis(200), Easing::Linear);
+
+        let now = start + millis(150);
+        let reversed = tween.reversed(now, Easing::Linear);
+        assert_eq!(reversed.progress(now), 0.25);
+        assert!(reversed.is_done(now + millis(150)));
+
+        let waiting = tween.delayed(millis(500)).reversed(now, Easing::Linear);
+        assert!(waiting.is_done(now));
+    }
+
+    #[test]
+    fn waves_repeat() {
+        let start = Instant::now();
+        let tween = Tween::new(start, millis(1000), Easing::Linear);
+
+        assert_eq!(tween.wave(start), 1.0);
+        assert!(tween.wave(start + millis(500)) < 0.001);
+        assert!((tween.wave(start + millis(2250)) - 0.5).abs() < 0.001);
+        assert!(!tween.is_done(start + millis(500)));
+    }
+}
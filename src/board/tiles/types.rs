@@ -16,6 +16,12 @@ macro_rules! iterable_enum {
                 ENUM_VARIANTS.iter()
             }
 
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    $( $variant => stringify!($variant),)*
+                }
+            }
+
             pub fn filename_texture(&self) -> &'static str {
                 match *self {
                     $( $variant => concat!(stringify!($variant), ".png"),)*
@@ -40,33 +46,3 @@ iterable_enum!{
         SeasonSpring, SeasonSummer, SeasonAutumn, SeasonWinter,
     }
 }
-
-impl TileType {
-    pub fn max_allowed(self) -> u8 {
-        match self {
-            FlowerPlum | FlowerOrchid | FlowerChrysanthemum | FlowerBamboo
-            | SeasonSpring | SeasonSummer | SeasonAutumn | SeasonWinter => 1,
-            _ => 4,
-        }
-    }
-
-    pub fn matches(self, other: TileType) -> bool {
-        match self {
-            FlowerPlum | FlowerOrchid | FlowerChrysanthemum | FlowerBamboo => match other {
-                FlowerPlum | FlowerOrchid | FlowerChrysanthemum | FlowerBamboo => true,
-                _ => false,
-            },
-            SeasonSpring | SeasonSummer | SeasonAutumn | SeasonWinter => match other {
-                SeasonSpring | SeasonSummer | SeasonAutumn | SeasonWinter => true,
-                _ => false,
-            },
-            _ => self == other,
-        }
-    }
-}
-
-impl Default for TileType {
-    fn default() -> TileType {
-        BallOne
-    }
-}
@@ -0,0 +1,440 @@
+use {
+    rand::{
+        rngs::SmallRng,
+        seq::SliceRandom,
+        FromEntropy,
+        Rng,
+    },
+    sdl2::{
+        keyboard::Keycode,
+        pixels::Color,
+        rect::Rect,
+        render::WindowCanvas,
+    },
+    crate::game::{
+        Game,
+        GameOver,
+        Scale,
+        MIN_CANVAS,
+    },
+    super::{
+        get_raw_positios,
+        tiles::{
+            Camera,
+            TileId,
+            Tiles,
+        },
+        RuleSet,
+        TileSize,
+        TileTextures,
+        TileType,
+        ViewAngle,
+    },
+};
+
+static TRAY_SIZE: usize = 7;
+
+static SLOT_WIDTH: u32 = 31;
+static SLOT_HEIGHT: u32 = 39;
+static SLOT_SIDE_WIDTH: u32 = 3;
+static SLOT_BOTTOM_HEIGHT: u32 = 3;
+static SLOT_SPACING: u32 = 5;
+
+static TRAY_COLOR: Color = Color { r: 40, g: 40, b: 40, a: 255 };
+static SLOT_COLOR: Color = Color { r: 90, g: 90, b: 90, a: 255 };
+
+/// Match three: free tiles are moved into a tray of seven slots, where three identical tiles
+/// clear. The game is lost when the tray fills up.
+pub struct Tray {
+    tiles: Tiles,
+    tray: Vec<TileId>,
+    played: Vec<(TileId, Vec<TileId>)>,
+    hint: Option<TileId>,
+    rng: SmallRng,
+    canvas_size: (u32, u32),
+}
+
+impl Tray {
+    pub fn new() -> Self {
+        let tiles = Tiles::new(&mut get_raw_positios(), RuleSet::default());
+
+        let mut tray = Tray {
+            tiles,
+            tray: Vec::new(),
+            played: Vec::new(),
+            hint: None,
+            rng: SmallRng::from_entropy(),
+            canvas_size: MIN_CANVAS,
+        };
+        Game::reset(&mut tray);
+        tray.resize(MIN_CANVAS.0, MIN_CANVAS.1);
+        tray
+    }
+
+    /// Switches the free tile rules and starts a new game with them. The current rules and game
+    /// are kept when the new rules leave no deal.
+    fn set_rules(&mut self, rules: RuleSet) -> Result<(), &'static str> {
+        let current = self.tiles.rules();
+        self.tiles.set_rules(rules);
+        self.deal().inspect_err(|_| self.tiles.set_rules(current))
+    }
+
+    fn deal(&mut self) -> Result<(), &'static str> {
+        self.stop_hint();
+        let types = triple_types(self.tiles.len(), &mut self.rng);
+        self.tiles.reset_with_triples(types)?;
+        self.tray = Vec::new();
+        self.played = Vec::new();
+        Ok(())
+    }
+
+    /// Moves a free tile into the tray and clears the tray of three identical tiles.
+    fn take_tile(&mut self, tile: TileId) -> Result<(), GameOver> {
+        self.stop_hint();
+
+        self.played.push((tile, self.tray.clone()));
+        self.tiles.remove_tile(tile);
+
+        // keep identical tiles next to each other in the tray
+        let tile_type = self.tiles.tile_type(tile);
+        let index = self
+            .tray
+            .iter()
+            .rposition(|&other| self.tiles.tile_type(other) == tile_type)
+            .map_or(self.tray.len(), |index| index + 1);
+        self.tray.insert(index, tile);
+
+        if self.count_in_tray(tile_type) == 3 {
+            let tiles = &self.tiles;
+            self.tray.retain(|&other| tiles.tile_type(other) != tile_type);
+        }
+
+        if self.tiles.is_cleared() {
+            return Err(GameOver::Won);
+        }
+        if self.tray.len() == TRAY_SIZE {
+            return Err(GameOver::TrayFull);
+        }
+        if self.tiles.playable_tiles().next().is_none() {
+            return Err(GameOver::Stuck);
+        }
+        Ok(())
+    }
+
+    fn count_in_tray(&self, tile_type: TileType) -> usize {
+        self.tray
+            .iter()
+            .filter(|&&tile| self.tiles.tile_type(tile) == tile_type)
+            .count()
+    }
+
+    fn stop_hint(&mut self) {
+        if let Some(tile) = self.hint.take() {
+            self.tiles.dehighlight_tile(tile);
+        }
+    }
+
+    fn scale(&self) -> Scale {
+        Scale::of_canvas(self.canvas_size.0, self.canvas_size.1)
+    }
+
+    /// Returns where the tray is drawn, centered at the bottom of the canvas.
+    fn tray_rect(&self) -> Rect {
+        let (canvas_width, canvas_height) = self.canvas_size;
+        let scale = self.scale();
+        let width = scale.size(TRAY_SIZE as u32 * (SLOT_WIDTH + SLOT_SPACING) + SLOT_SPACING);
+        let height = scale.size(SLOT_HEIGHT + SLOT_BOTTOM_HEIGHT + SLOT_SPACING * 2);
+        Rect::new(
+            (canvas_width as i32 - width as i32) / 2,
+            canvas_height as i32 - height as i32,
+            width,
+            height,
+        )
+    }
+
+    fn slot_rect(&self, index: usize) -> Rect {
+        let tray = self.tray_rect();
+        let scale = self.scale();
+        let x = SLOT_SPACING + index as u32 * (SLOT_WIDTH + SLOT_SPACING);
+        Rect::new(
+            tray.x() + scale.distance(x as i32),
+            tray.y() + scale.distance(SLOT_SPACING as i32),
+            scale.size(SLOT_WIDTH),
+            scale.size(SLOT_HEIGHT),
+        )
+    }
+}
+
+impl Default for Tray {
+    fn default() -> Self {
+        Tray::new()
+    }
+}
+
+impl Game for Tray {
+    fn title(&self) -> String {
+        format!("Match Three - {} rules", self.tiles.rules().name())
+    }
+
+    /// Starts a new game, or keeps the current one when the rules leave no deal.
+    fn reset(&mut self) {
+        let _ = self.deal();
+    }
+
+    /// Takes back the last tile, which also brings back the tiles it cleared from the tray.
+    fn undo(&mut self) {
+        self.stop_hint();
+
+        if let Some((tile, tray)) = self.played.pop() {
+            self.tiles.put_back_tile(tile);
+            self.tray = tray;
+        }
+    }
+
+    /// Highlights the free tile that completes the most of the tray.
+    fn hint(&mut self) {
+        self.stop_hint();
+
+        let tiles = &self.tiles;
+        let best_tile = tiles.playable_tiles().max_by_key(|&tile| {
+            let tile_type = tiles.tile_type(tile);
+            let playable = tiles
+                .playable_tiles()
+                .filter(|&other| tiles.tile_type(other) == tile_type)
+                .count();
+            (self.count_in_tray(tile_type), playable)
+        });
+
+        if let Some(tile) = best_tile {
+            self.tiles.highlight_tile(tile);
+            self.hint = Some(tile);
+        }
+    }
+
+    fn click(&mut self, x: i32, y: i32) -> Result<(), GameOver> {
+        if self.tray_rect().contains_point((x, y)) {
+            return Ok(());
+        }
+
+        match self.tiles.find_playable_tile_by_coord(x, y) {
+            Some(tile) => self.take_tile(tile),
+            None => Ok(()),
+        }
+    }
+
+    fn key(&mut self, keycode: Keycode) -> bool {
+        match keycode {
+            Keycode::R => {
+                let _ = self.set_rules(self.tiles.rules().next());
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Lays the board out above the tray, so the tray never covers a tile.
+    fn resize(&mut self, width: u32, height: u32) {
+        self.canvas_size = (width, height);
+        let tray = self.tray_rect();
+        self.tiles.fit_canvas(width, tray.y().max(1) as u32);
+    }
+
+    fn camera(&mut self) -> Option<&mut Camera> {
+        Some(self.tiles.camera_mut())
+    }
+
+    fn set_view_angle(&mut self, angle: ViewAngle) {
+        self.tiles.set_view_angle(angle);
+    }
+
+    fn set_tile_size(&mut self, tile_size: TileSize) {
+        self.tiles.set_tile_size(tile_size);
+    }
+
+    fn update(&mut self) -> Result<bool, GameOver> {
+        Ok(self.tiles.update())
+    }
+
+    fn is_animating(&self) -> bool {
+        self.tiles.is_animating()
+    }
+
+    fn render(&self, canvas: &mut WindowCanvas, textures: &TileTextures) {
+        self.tiles.render(canvas, textures);
+
+        canvas.set_draw_color(TRAY_COLOR);
+        let _ = canvas.fill_rect(self.tray_rect());
+
+        let scale = self.scale();
+        let side_width = scale.size(SLOT_SIDE_WIDTH);
+        let bottom_height = scale.size(SLOT_BOTTOM_HEIGHT);
+        for index in 0..TRAY_SIZE {
+            let face = self.slot_rect(index);
+
+            let tile = match self.tray.get(index) {
+                Some(&tile) => tile,
+                None => {
+                    canvas.set_draw_color(SLOT_COLOR);
+                    let _ = canvas.draw_rect(face);
+                    continue;
+                }
+            };
+
+            let side = Rect::new(
+                face.x() - side_width as i32,
+                face.y(),
+                side_width,
+                face.height() + bottom_height,
+            );
+            let bottom = Rect::new(face.x(), face.bottom(), face.width(), bottom_height);
+
+            textures.side().draw(canvas, side);
+            textures.bottom().draw(canvas, bottom);
+            textures.face(self.tiles.tile_type(tile)).draw(canvas, face);
+        }
+    }
+}
+
+/// Returns a triple of every tile type, repeating random types until there are enough tiles.
+fn triple_types<R: Rng>(num_tiles: usize, rng: &mut R) -> Vec<TileType> {
+    let mut types = TileType::iter().cloned().collect::<Vec<_>>();
+    types.shuffle(rng);
+
+    types
+        .iter()
+        .cycle()
+        .take(num_tiles / 3)
+        .flat_map(|&tile_type| vec![tile_type; 3])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::{
+            board::TileType,
+            game::{
+                Game,
+                GameOver,
+            },
+        },
+        super::{
+            Tray,
+            TRAY_SIZE,
+        },
+    };
+
+    #[test]
+    fn the_board_is_fitted_above_the_tray() {
+        let mut tray = Tray::new();
+        tray.resize(2780, 1872);
+
+        let rect = tray.tray_rect();
+        assert_eq!(rect.bottom(), 1872);
+        assert!((rect.x() + rect.right() - 2780).abs() <= 1);
+        assert!(tray.slot_rect(TRAY_SIZE - 1).right() < rect.right());
+        for tile in tray.tiles.playable_tiles() {
+            assert!(tray.tiles.face_rect(tile).bottom() <= rect.y());
+        }
+    }
+
+    fn tray_with_types(types: Vec<TileType>) -> Tray {
+        let mut tray = Tray::new();
+        tray.tiles.reset_with_triples(types).unwrap();
+        tray
+    }
+
+    #[test]
+    fn three_identical_tiles_clear() {
+        let mut tray = tray_with_types(vec![TileType::BallOne; 144]);
+
+        for count in 1..3 {
+            let tile = tray.tiles.playable_tiles().next().unwrap();
+            tray.take_tile(tile).unwrap();
+            assert_eq!(tray.tray.len(), count);
+        }
+
+        let tile = tray.tiles.playable_tiles().next().unwrap();
+        tray.take_tile(tile).unwrap();
+        assert!(tray.tray.is_empty());
+    }
+
+    #[test]
+    fn clearing_the_board_wins() {
+        let mut tray = tray_with_types(vec![TileType::BallOne; 144]);
+
+        let mut result = Ok(());
+        while result.is_ok() {
+            let tile = tray.tiles.playable_tiles().next().unwrap();
+            result = tray.take_tile(tile);
+        }
+
+        assert_eq!(result, Err(GameOver::Won));
+    }
+
+    #[test]
+    fn full_tray_loses() {
+        let mut tray = Tray::new();
+
+        let mut result = Ok(());
+        while tray.tray.len() < TRAY_SIZE - 1 {
+            let tile = tray
+                .tiles
+                .playable_tiles()
+                .find(|&tile| tray.count_in_tray(tray.tiles.tile_type(tile)) == 0)
+                .unwrap();
+            result = tray.take_tile(tile);
+        }
+        assert_eq!(result, Ok(()));
+
+        let tile = tray
+            .tiles
+            .playable_tiles()
+            .find(|&tile| tray.count_in_tray(tray.tiles.tile_type(tile)) == 0)
+            .unwrap();
+        assert_eq!(tray.take_tile(tile), Err(GameOver::TrayFull));
+    }
+
+    #[test]
+    fn undo_brings_back_cleared_tiles() {
+        let mut tray = tray_with_types(vec![TileType::BallOne; 144]);
+
+        for _ in 0..3 {
+            let tile = tray.tiles.playable_tiles().next().unwrap();
+            tray.take_tile(tile).unwrap();
+        }
+        assert!(tray.tray.is_empty());
+
+        tray.undo();
+        assert_eq!(tray.tray.len(), 2);
+
+        tray.undo();
+        tray.undo();
+        assert!(tray.tray.is_empty());
+        assert_eq!(tray.tiles.playable_tiles().count(), 35);
+    }
+
+    #[test]
+    fn identical_tiles_are_grouped_in_tray() {
+        let types = [TileType::BallOne, TileType::BallTwo]
+            .iter()
+            .cycle()
+            .take(48)
+            .flat_map(|&tile_type| vec![tile_type; 3])
+            .collect();
+        let mut tray = tray_with_types(types);
+
+        for _ in 0..4 {
+            let tile = tray
+                .tiles
+                .playable_tiles()
+                .find(|&tile| tray.count_in_tray(tray.tiles.tile_type(tile)) < 2)
+                .unwrap();
+            tray.take_tile(tile).unwrap();
+        }
+
+        let types = tray.tray.iter().map(|&tile| tray.tiles.tile_type(tile)).collect::<Vec<_>>();
+        assert_eq!(types[0], types[1]);
+        assert_eq!(types[2], types[3]);
+    }
+}
@@ -0,0 +1,557 @@
+use {
+    std::time::{
+        Duration,
+        Instant,
+    },
+    rand::{
+        rngs::SmallRng,
+        FromEntropy,
+    },
+    sdl2::{
+        keyboard::Keycode,
+        pixels::Color,
+        rect::Rect,
+        render::WindowCanvas,
+    },
+    crate::game::{
+        Game,
+        GameOver,
+        Scale,
+    },
+    super::{
+        computer::{
+            choose_pair,
+            Strength,
+        },
+        next_tile_set,
+        tiles::TileId,
+        Board,
+        Camera,
+        ViewAngle,
+        TileSize,
+        TileTextures,
+    },
+};
+
+/// The turn time limits to choose from, in seconds, where zero means no limit.
+static TURN_LIMITS: [u64; 4] = [20, 30, 10, 0];
+
+/// The number of pairs a pass costs.
+static PASS_PENALTY: i32 = 1;
+
+/// The opponents to choose from for player 2, where `None` is a human.
+static OPPONENTS: [Option<Strength>; 4] = [
+    None,
+    Some(Strength::Random),
+    Some(Strength::Greedy),
+    Some(Strength::Solver),
+];
+
+/// How long the computer thinks before it shows its pair, and how long each tile of the pair is
+/// shown before it is played.
+static THINK_DURATION: Duration = Duration::from_millis(800);
+static SHOW_DURATION: Duration = Duration::from_millis(400);
+
+static PLAYER_COLORS: [Color; 2] = [
+    Color { r: 80, g: 140, b: 255, a: 255 },
+    Color { r: 255, g: 160, b: 60, a: 255 },
+];
+static HUD_X: i32 = 5;
+static HUD_Y: i32 = 100;
+static HUD_WIDTH: u32 = 55;
+static HUD_HEIGHT: u32 = 10;
+static TIMER_HEIGHT: u32 = 4;
+
+/// Two players take turns on the same board, each turn is a single pair. A player that can't
+/// or won't find a pair passes, which costs a pair. Player 2 can be played by the computer.
+pub struct Versus {
+    board: Board,
+    current_player: usize,
+    passes: [u32; 2],
+    turn_limit_index: usize,
+    turn_start: Instant,
+    opponent_index: usize,
+    computer_move: Option<ComputerMove>,
+    rng: SmallRng,
+    scale: Scale,
+}
+
+/// The stage of the move the computer is making, so it plays at a pace that can be followed.
+#[derive(Clone, Copy, Debug)]
+enum ComputerMove {
+    Thinking(Instant),
+    ShowingFirst((TileId, TileId), Instant),
+    ShowingBoth((TileId, TileId), Instant),
+}
+
+impl Versus {
+    pub fn new() -> Self {
+        Versus {
+            board: Board::new(),
+            current_player: 0,
+            passes: [0; 2],
+            turn_limit_index: 0,
+            turn_start: Instant::now(),
+            opponent_index: 0,
+            computer_move: None,
+            rng: SmallRng::from_entropy(),
+            scale: Scale::default(),
+        }
+    }
+
+    fn opponent(&self) -> Option<Strength> {
+        OPPONENTS[self.opponent_index]
+    }
+
+    /// Returns the strength of the computer when it is the computer's turn.
+    fn computer_strength(&self) -> Option<Strength> {
+        match self.current_player {
+            1 => self.opponent(),
+            _ => None,
+        }
+    }
+
+    fn turn_limit(&self) -> Option<Duration> {
+        match TURN_LIMITS[self.turn_limit_index] {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        }
+    }
+
+    fn time_left(&self) -> Option<Duration> {
+        let elapsed = self.turn_start.elapsed();
+        self.turn_limit()
+            .map(|turn_limit| turn_limit.checked_sub(elapsed).unwrap_or_default())
+    }
+
+    fn score(&self, player: usize) -> i32 {
+        self.board.pairs_played_by(player) as i32 - self.passes[player] as i32 * PASS_PENALTY
+    }
+
+    fn start_turn(&mut self, player: usize) {
+        self.stop_computer_move();
+        self.current_player = player;
+        self.board.set_player(player);
+        self.turn_start = Instant::now();
+    }
+
+    fn pass(&mut self) {
+        self.board.deselect_tile();
+        self.passes[self.current_player] += 1;
+        self.start_turn(1 - self.current_player);
+    }
+
+    /// Takes the computer's move a stage further once the stage has lasted long enough.
+    fn play_computer_move(&mut self, strength: Strength) -> Result<(), GameOver> {
+        let computer_move = match self.computer_move {
+            Some(computer_move) => computer_move,
+            None => {
+                self.computer_move = Some(ComputerMove::Thinking(Instant::now()));
+                return Ok(());
+            }
+        };
+
+        match computer_move {
+            ComputerMove::Thinking(start) if start.elapsed() >= THINK_DURATION => {
+                match choose_pair(&mut self.board.tiles, strength, &mut self.rng) {
+                    Some((tile1, tile2)) => {
+                        self.board.select_tile(tile1);
+                        let stage = ComputerMove::ShowingFirst((tile1, tile2), Instant::now());
+                        self.computer_move = Some(stage);
+                    }
+                    None => self.pass(),
+                }
+            }
+            ComputerMove::ShowingFirst(pair, start) if start.elapsed() >= SHOW_DURATION => {
+                self.board.tiles.highlight_tile(pair.1);
+                self.computer_move = Some(ComputerMove::ShowingBoth(pair, Instant::now()));
+            }
+            ComputerMove::ShowingBoth(pair, start) if start.elapsed() >= SHOW_DURATION => {
+                let (tile1, tile2) = pair;
+                self.board.tiles.dehighlight_tile(tile2);
+                self.computer_move = None;
+
+                let pairs_played = self.board.pairs_played();
+                let result = self.board.play_pair(tile1, tile2);
+                return self.finish_move(pairs_played, result);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Drops the move the computer is making, along with the tiles it shows.
+    fn stop_computer_move(&mut self) {
+        if let Some(ComputerMove::ShowingBoth((_, tile2), _)) = self.computer_move {
+            self.board.tiles.dehighlight_tile(tile2);
+        }
+        if self.computer_move.take().is_some() {
+            self.board.deselect_tile();
+        }
+    }
+
+    /// Ends the turn when a pair was played, and decides the match when the board is done.
+    fn finish_move(
+        &mut self,
+        pairs_played: usize,
+        result: Result<(), GameOver>,
+    ) -> Result<(), GameOver> {
+        if self.board.pairs_played() > pairs_played {
+            self.start_turn(1 - self.current_player);
+        }
+
+        result.map_err(|_| {
+            let (score1, score2) = (self.score(0), self.score(1));
+            if score1 > score2 {
+                GameOver::PlayerWon(1)
+            } else if score2 > score1 {
+                GameOver::PlayerWon(2)
+            } else {
+                GameOver::Draw
+            }
+        })
+    }
+}
+
+impl Default for Versus {
+    fn default() -> Self {
+        Versus::new()
+    }
+}
+
+impl Game for Versus {
+    fn title(&self) -> String {
+        let time_left = match self.time_left() {
+            Some(time_left) => format!(" - {}s left", time_left.as_secs() + 1),
+            None => String::new(),
+        };
+
+        let opponent = match self.opponent() {
+            Some(strength) => format!(" - vs {} computer", strength.name()),
+            None => String::new(),
+        };
+        let to_play = match self.opponent() {
+            Some(_) if self.current_player == 1 => "computer".to_string(),
+            Some(_) => "you".to_string(),
+            None => format!("player {}", self.current_player + 1),
+        };
+
+        format!(
+            "Versus - {} rules{} - {} to play{} - score {} : {}",
+            self.board.rules().name(),
+            opponent,
+            to_play,
+            time_left,
+            self.score(0),
+            self.score(1),
+        )
+    }
+
+    fn reset(&mut self) {
+        self.stop_computer_move();
+        self.board.reset();
+        self.passes = [0; 2];
+        self.start_turn(0);
+    }
+
+    /// Takes back the last pair and gives the turn back to the player that played it. Against
+    /// the computer its pairs are taken back too, up to the last pair of the human.
+    fn undo(&mut self) {
+        self.stop_computer_move();
+
+        while let Some(player) = self.board.last_player() {
+            self.board.undo();
+            self.start_turn(player);
+            if self.computer_strength().is_none() {
+                break;
+            }
+        }
+    }
+
+    fn hint(&mut self) {
+        if self.computer_strength().is_none() {
+            self.board.highlight_possible_matches();
+        }
+    }
+
+    fn click(&mut self, x: i32, y: i32) -> Result<(), GameOver> {
+        if self.computer_strength().is_some() {
+            return Ok(());
+        }
+
+        let pairs_played = self.board.pairs_played();
+        let result = self.board.try_select_tile(x, y);
+        self.finish_move(pairs_played, result)
+    }
+
+    fn key(&mut self, keycode: Keycode) -> bool {
+        match keycode {
+            Keycode::P => {
+                if self.computer_strength().is_none() {
+                    self.pass();
+                }
+            }
+            Keycode::L => {
+                self.turn_limit_index = (self.turn_limit_index + 1) % TURN_LIMITS.len();
+                self.turn_start = Instant::now();
+            }
+            Keycode::O => {
+                self.opponent_index = (self.opponent_index + 1) % OPPONENTS.len();
+                Game::reset(self);
+            }
+            Keycode::B | Keycode::R | Keycode::T => {
+                // the board's settings start a new game, unless it can't be dealt
+                self.stop_computer_move();
+                let dealt = match keycode {
+                    Keycode::B => self.board.set_blind(!self.board.is_blind()),
+                    Keycode::R => self.board.set_rules(self.board.rules().next()),
+                    _ => self.board.set_tile_set(next_tile_set(self.board.tile_set())),
+                };
+                if dealt.is_ok() {
+                    self.passes = [0; 2];
+                    self.start_turn(0);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.board.fit_canvas(width, height);
+        self.scale = Scale::of_canvas(width, height);
+    }
+
+    fn camera(&mut self) -> Option<&mut Camera> {
+        Some(self.board.camera_mut())
+    }
+
+    fn set_view_angle(&mut self, angle: ViewAngle) {
+        self.board.set_view_angle(angle);
+    }
+
+    fn set_tile_size(&mut self, tile_size: TileSize) {
+        self.board.set_tile_size(tile_size);
+    }
+
+    fn update(&mut self) -> Result<bool, GameOver> {
+        let changed = self.board.update();
+
+        // nobody plays on once the board is cleared or stuck
+        if self.board.get_available_matches().is_err() {
+            return Ok(changed);
+        }
+
+        // the computer's moves and the turns running out are drawn as they are animated
+        if let Some(strength) = self.computer_strength() {
+            return self.play_computer_move(strength).map(|()| changed);
+        }
+        if self.time_left() == Some(Duration::from_secs(0)) {
+            self.pass();
+        }
+        Ok(changed)
+    }
+
+    /// The computer's move plays out and the time left runs down on screen.
+    fn is_animating(&self) -> bool {
+        self.computer_move.is_some() || self.turn_limit().is_some() || self.board.is_animating()
+    }
+
+    fn summary(&self) -> Option<String> {
+        let lines = (0..2)
+            .map(|player| {
+                format!(
+                    "Player {}: {} pairs, {} passes, score {}",
+                    player + 1,
+                    self.board.pairs_played_by(player),
+                    self.passes[player],
+                    self.score(player),
+                )
+            }).collect::<Vec<_>>();
+
+        Some(lines.join("\n"))
+    }
+
+    fn render(&self, canvas: &mut WindowCanvas, textures: &TileTextures) {
+        self.board.render(canvas, textures);
+
+        // the color of the player to play, with a bar below it for the time left
+        canvas.set_draw_color(PLAYER_COLORS[self.current_player]);
+        let _ = canvas.fill_rect(self.scale.rect(Rect::new(HUD_X, HUD_Y, HUD_WIDTH, HUD_HEIGHT)));
+
+        if let (Some(turn_limit), Some(time_left)) = (self.turn_limit(), self.time_left()) {
+            let fraction = time_left.as_secs_f32() / turn_limit.as_secs_f32();
+            let timer = Rect::new(
+                HUD_X,
+                HUD_Y + HUD_HEIGHT as i32 + 2,
+                ((HUD_WIDTH as f32 * fraction) as u32).max(1),
+                TIMER_HEIGHT,
+            );
+            let _ = canvas.fill_rect(self.scale.rect(timer));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::time::Instant,
+        sdl2::keyboard::Keycode,
+        crate::game::{
+            Game,
+            GameOver,
+        },
+        super::{
+            ComputerMove,
+            Versus,
+            SHOW_DURATION,
+            THINK_DURATION,
+        },
+    };
+
+    fn play_any_pair(versus: &mut Versus) -> Result<(), GameOver> {
+        let hints = versus.board.get_available_matches().ok().expect("no pair to play");
+        let (tile1, tile2) = (hints[0].0[0], hints[0].0[1]);
+
+        let pairs_played = versus.board.pairs_played();
+        let result = versus.board.play_pair(tile1, tile2);
+        versus.finish_move(pairs_played, result)
+    }
+
+    /// Lets the computer's move go through all its stages without waiting.
+    fn play_computer_move(versus: &mut Versus) -> Result<(), GameOver> {
+        let strength = versus.computer_strength().expect("not the computer's turn");
+
+        versus.play_computer_move(strength)?;
+        while let Some(stage) = versus.computer_move {
+            let stage = match stage {
+                ComputerMove::Thinking(start) => ComputerMove::Thinking(start - THINK_DURATION),
+                ComputerMove::ShowingFirst(pair, start) => {
+                    ComputerMove::ShowingFirst(pair, start - SHOW_DURATION)
+                }
+                ComputerMove::ShowingBoth(pair, start) => {
+                    ComputerMove::ShowingBoth(pair, start - SHOW_DURATION)
+                }
+            };
+            versus.computer_move = Some(stage);
+            versus.play_computer_move(strength)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn turns_alternate_after_each_pair() {
+        let mut versus = Versus::new();
+
+        play_any_pair(&mut versus).unwrap();
+        assert_eq!(versus.current_player, 1);
+        play_any_pair(&mut versus).unwrap();
+        assert_eq!(versus.current_player, 0);
+
+        assert_eq!(versus.board.pairs_played_by(0), 1);
+        assert_eq!(versus.board.pairs_played_by(1), 1);
+    }
+
+    #[test]
+    fn passing_costs_a_pair() {
+        let mut versus = Versus::new();
+
+        play_any_pair(&mut versus).unwrap();
+        versus.pass();
+
+        assert_eq!(versus.current_player, 0);
+        assert_eq!(versus.score(0), 1);
+        assert_eq!(versus.score(1), -1);
+    }
+
+    #[test]
+    fn undo_gives_the_turn_back() {
+        let mut versus = Versus::new();
+
+        play_any_pair(&mut versus).unwrap();
+        play_any_pair(&mut versus).unwrap();
+        versus.undo();
+
+        assert_eq!(versus.current_player, 1);
+        assert_eq!(versus.board.pairs_played_by(1), 0);
+        assert_eq!(versus.board.last_player(), Some(0));
+    }
+
+    #[test]
+    fn best_score_wins() {
+        let mut versus = Versus::new();
+        // player 2 is ahead after three passes and plays the first pair
+        versus.pass();
+        versus.pass();
+        versus.pass();
+
+        let mut result = Ok(());
+        while result.is_ok() {
+            result = play_any_pair(&mut versus);
+        }
+
+        assert_eq!(result, Err(GameOver::PlayerWon(2)));
+        assert!(versus.summary().unwrap().starts_with("Player 1: "));
+    }
+
+    #[test]
+    fn computer_plays_a_pair_after_thinking() {
+        let mut versus = Versus::new();
+        versus.opponent_index = 3;
+
+        play_any_pair(&mut versus).unwrap();
+        assert_eq!(versus.current_player, 1);
+
+        versus.update().unwrap();
+        match versus.computer_move {
+            Some(ComputerMove::Thinking(start)) => assert!(start <= Instant::now()),
+            stage => panic!("unexpected stage {:?}", stage),
+        }
+        assert_eq!(versus.board.pairs_played_by(1), 0);
+
+        play_computer_move(&mut versus).unwrap();
+        assert_eq!(versus.current_player, 0);
+        assert_eq!(versus.board.pairs_played_by(1), 1);
+    }
+
+    #[test]
+    fn undo_takes_back_the_computer_pair_too() {
+        let mut versus = Versus::new();
+        versus.opponent_index = 1;
+
+        play_any_pair(&mut versus).unwrap();
+        play_computer_move(&mut versus).unwrap();
+        versus.undo();
+
+        assert_eq!(versus.current_player, 0);
+        assert_eq!(versus.board.pairs_played(), 0);
+    }
+
+    #[test]
+    fn human_cannot_pass_for_the_computer() {
+        let mut versus = Versus::new();
+        versus.opponent_index = 2;
+
+        play_any_pair(&mut versus).unwrap();
+        versus.key(Keycode::P);
+
+        assert_eq!(versus.current_player, 1);
+        assert_eq!(versus.passes, [0; 2]);
+    }
+
+    #[test]
+    fn only_new_deals_start_the_match_over() {
+        let mut versus = Versus::new();
+        play_any_pair(&mut versus).unwrap();
+        versus.pass();
+
+        assert!(!versus.key(Keycode::V));
+        assert_eq!(versus.passes, [0, 1]);
+        assert_eq!(versus.board.pairs_played(), 1);
+
+        assert!(versus.key(Keycode::B));
+        assert_eq!(versus.passes, [0; 2]);
+        assert_eq!(versus.board.pairs_played(), 0);
+        assert_eq!(versus.current_player, 0);
+    }
+}
@@ -0,0 +1,592 @@
+use {
+    std::{
+        fs,
+        path::{
+            Path,
+            PathBuf,
+        },
+        time::{
+            Duration,
+            Instant,
+        },
+    },
+    sdl2::{
+        keyboard::Keycode,
+        pixels::Color,
+        rect::Rect,
+        render::WindowCanvas,
+    },
+    crate::game::{
+        Game,
+        GameOver,
+        Scale,
+        MIN_CANVAS,
+    },
+    super::{
+        replay::{
+            apply,
+            Action,
+            Replay,
+            REPLAY_DIR,
+        },
+        tiles::TileId,
+        Board,
+        Camera,
+        ViewAngle,
+        TileSize,
+        TileTextures,
+    },
+};
+
+/// The speeds a replay can be played at, relative to the game.
+static SPEEDS: [f64; 5] = [0.5, 1.0, 2.0, 4.0, 8.0];
+static DEFAULT_SPEED: usize = 1;
+
+/// How long a pair is shown before it is removed, at the speed of the game.
+static SHOW_DURATION: Duration = Duration::from_millis(500);
+
+static TIMELINE_COLOR: Color = Color { r: 80, g: 140, b: 255, a: 255 };
+static TIMELINE_BACKGROUND_COLOR: Color = Color { r: 60, g: 60, b: 60, a: 255 };
+/// The space left and right of the timeline, which runs along the bottom of the canvas.
+static TIMELINE_MARGIN: i32 = 20;
+/// The space below the timeline.
+static TIMELINE_BOTTOM: i32 = 4;
+static TIMELINE_HEIGHT: u32 = 8;
+
+/// A pair that is highlighted before the replay removes it.
+#[derive(Clone, Copy, Debug)]
+struct Showing {
+    tiles: (TileId, TileId),
+    start: Instant,
+}
+
+/**
+ * Watches the saved replays on the board they were played on. A replay plays at a choice of
+ * speeds and can be paused, stepped through and scrubbed with the timeline below the board.
+ * Every pair is highlighted for a moment before it is removed.
+ */
+pub struct Viewer {
+    /// The directory the replays are opened from.
+    dir: PathBuf,
+    path: Option<PathBuf>,
+    replay: Option<Replay>,
+    error: Option<&'static str>,
+    board: Board,
+    /// The number of steps of the replay done on the board.
+    position: usize,
+    /// How far the replay has been played, in the time of the game.
+    clock: Duration,
+    playing: bool,
+    speed: usize,
+    last_update: Instant,
+    showing: Option<Showing>,
+    timeline: Rect,
+}
+
+impl Viewer {
+    /// Creates a viewer with the newest saved replay, if there is one.
+    pub fn new() -> Self {
+        Viewer::with_dir(Path::new(REPLAY_DIR))
+    }
+
+    /// Creates a viewer with the newest replay in the directory, if there is one.
+    pub fn with_dir(dir: &Path) -> Self {
+        let mut viewer = Viewer {
+            dir: dir.to_path_buf(),
+            path: None,
+            replay: None,
+            error: None,
+            board: Board::new(),
+            position: 0,
+            clock: Duration::from_secs(0),
+            playing: false,
+            speed: DEFAULT_SPEED,
+            last_update: Instant::now(),
+            showing: None,
+            timeline: timeline_rect(MIN_CANVAS.0, MIN_CANVAS.1),
+        };
+        viewer.open_next();
+        viewer
+    }
+
+    pub fn open(&mut self, path: &Path) {
+        self.path = Some(path.to_path_buf());
+        match Replay::load(path) {
+            Ok(replay) => self.watch(replay),
+            Err(err) => {
+                self.replay = None;
+                self.error = Some(err);
+            }
+        }
+    }
+
+    /// Opens the next older saved replay, or the newest after the oldest.
+    pub fn open_next(&mut self) {
+        let mut paths = fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.extension().is_some_and(|extension| extension == "replay"))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        if paths.is_empty() {
+            self.error = Some("no saved replays");
+            return;
+        }
+
+        // replays are named after the time they were saved
+        paths.sort();
+        paths.reverse();
+        let index = self
+            .path
+            .as_ref()
+            .and_then(|current| paths.iter().position(|path| path == current))
+            .map_or(0, |index| (index + 1) % paths.len());
+        self.open(&paths[index].clone());
+    }
+
+    fn watch(&mut self, replay: Replay) {
+        self.replay = Some(replay);
+        self.error = None;
+        self.playing = false;
+        self.seek(0);
+    }
+
+    /// Deals the board again and does the first steps of the replay on it, the board is shown
+    /// as it was.
+    fn seek(&mut self, position: usize) {
+        let replay = match self.replay.as_ref() {
+            Some(replay) => replay,
+            None => return,
+        };
+        if let Some(Showing { tiles: (tile1, tile2), .. }) = self.showing.take() {
+            self.board.tiles.dehighlight_tile(tile1);
+            self.board.tiles.dehighlight_tile(tile2);
+        }
+        self.error = None;
+
+        if let Err(err) = replay.deal_on(&mut self.board) {
+            self.error = Some(err);
+            return;
+        }
+        self.position = 0;
+        for step in &replay.steps[..position] {
+            if let Err(err) = apply(&mut self.board, step.action) {
+                self.error = Some(err);
+                self.playing = false;
+                break;
+            }
+            self.position += 1;
+        }
+
+        self.board.finish_animations();
+        self.clock = self.step_time(self.position);
+    }
+
+    /// Returns the time of the step before the given position.
+    fn step_time(&self, position: usize) -> Duration {
+        let millis = match (self.replay.as_ref(), position.checked_sub(1)) {
+            (Some(replay), Some(index)) => replay.steps[index].millis,
+            _ => 0,
+        };
+        Duration::from_millis(millis)
+    }
+
+    fn num_steps(&self) -> usize {
+        self.replay.as_ref().map_or(0, |replay| replay.steps.len())
+    }
+
+    fn total_time(&self) -> Duration {
+        self.step_time(self.num_steps())
+    }
+
+    /// Does the next step of the replay, a pair is only shown at first and removed by the step
+    /// after.
+    fn step_forward(&mut self) {
+        if self.showing.is_some() {
+            self.finish_showing();
+            return;
+        }
+
+        let action = match self.next_action() {
+            Some(action) if self.error.is_none() => action,
+            _ => return,
+        };
+
+        if let Action::Match(tile1, tile2) = action {
+            let tiles = (self.board.free_tile(tile1), self.board.free_tile(tile2));
+            if let (Ok(tile1), Ok(tile2)) = tiles {
+                self.board.stop_hints();
+                self.board.tiles.highlight_tile(tile1);
+                self.board.tiles.highlight_tile(tile2);
+                self.showing = Some(Showing { tiles: (tile1, tile2), start: Instant::now() });
+                return;
+            }
+        }
+        self.do_step(action);
+    }
+
+    fn finish_showing(&mut self) {
+        if let Some(Showing { tiles: (tile1, tile2), .. }) = self.showing.take() {
+            self.board.tiles.dehighlight_tile(tile1);
+            self.board.tiles.dehighlight_tile(tile2);
+            if let Some(action) = self.next_action() {
+                self.do_step(action);
+            }
+        }
+    }
+
+    fn next_action(&self) -> Option<Action> {
+        let replay = self.replay.as_ref()?;
+        replay.steps.get(self.position).map(|step| step.action)
+    }
+
+    fn do_step(&mut self, action: Action) {
+        match apply(&mut self.board, action) {
+            Ok(()) => {
+                self.position += 1;
+                self.clock = self.clock.max(self.step_time(self.position));
+            }
+            Err(err) => {
+                self.error = Some(err);
+                self.playing = false;
+            }
+        }
+    }
+
+    fn step_back(&mut self) {
+        self.playing = false;
+        if self.showing.is_some() {
+            self.seek(self.position);
+        } else {
+            self.seek(self.position.saturating_sub(1));
+        }
+    }
+
+    /// Moves the replay to the time under the timeline at the given x coordinate.
+    fn scrub(&mut self, x: i32) {
+        let fraction = f64::from((x - self.timeline.x()).max(0)) / f64::from(self.timeline.width());
+        let time = self.total_time().mul_f64(fraction.min(1.0));
+
+        let position = self.replay.as_ref().map_or(0, |replay| {
+            replay
+                .steps
+                .iter()
+                .take_while(|step| Duration::from_millis(step.millis) <= time)
+                .count()
+        });
+        self.seek(position);
+        self.clock = time;
+    }
+
+    fn speed(&self) -> f64 {
+        SPEEDS[self.speed]
+    }
+
+    /// Plays the replay on, and returns whether the board looks different while nothing is
+    /// played or shown.
+    fn update(&mut self) -> bool {
+        let elapsed = self.last_update.elapsed();
+        self.last_update = Instant::now();
+        let changed = self.board.update();
+
+        if let Some(showing) = self.showing {
+            if showing.start.elapsed() < SHOW_DURATION.div_f64(self.speed()) {
+                return changed;
+            }
+            self.finish_showing();
+        }
+        if !self.playing {
+            return changed;
+        }
+
+        self.clock += elapsed.mul_f64(self.speed());
+        while self.showing.is_none() && self.playing && self.position < self.num_steps() {
+            if self.step_time(self.position + 1) > self.clock {
+                return changed;
+            }
+            self.step_forward();
+        }
+        if self.position >= self.num_steps() {
+            self.playing = false;
+        }
+        changed
+    }
+}
+
+/// Returns where the timeline is drawn on a canvas of the given size.
+fn timeline_rect(width: u32, height: u32) -> Rect {
+    let scale = Scale::of_canvas(width, height);
+    let margin = scale.distance(TIMELINE_MARGIN);
+    let timeline_height = scale.size(TIMELINE_HEIGHT);
+    Rect::new(
+        margin,
+        height as i32 - scale.distance(TIMELINE_BOTTOM) - timeline_height as i32,
+        (width as i32 - 2 * margin).max(1) as u32,
+        timeline_height,
+    )
+}
+
+impl Default for Viewer {
+    fn default() -> Self {
+        Viewer::new()
+    }
+}
+
+impl Game for Viewer {
+    fn title(&self) -> String {
+        let name = self
+            .path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        if let Some(err) = self.error {
+            return format!("Replay {} - {} - press O for the next replay", name, err);
+        }
+
+        let secs = self.clock.as_secs();
+        format!(
+            "Replay {} - {} at {}x - step {} of {} - {}:{:02}",
+            name,
+            if self.playing { "playing" } else { "paused, press space to play" },
+            self.speed(),
+            self.position,
+            self.num_steps(),
+            secs / 60,
+            secs % 60,
+        )
+    }
+
+    fn reset(&mut self) {
+        self.seek(0);
+        self.playing = true;
+    }
+
+    fn undo(&mut self) {
+        self.step_back();
+    }
+
+    fn hint(&mut self) {
+        self.playing = false;
+        self.step_forward();
+    }
+
+    fn click(&mut self, x: i32, y: i32) -> Result<(), GameOver> {
+        // the timeline is easier to hit a little outside of it
+        let timeline = self.timeline;
+        let slack = timeline.height() / 2;
+        let target = Rect::new(
+            timeline.x() - slack as i32,
+            timeline.y() - slack as i32,
+            timeline.width() + 2 * slack,
+            timeline.height() + 2 * slack,
+        );
+        if target.contains_point((x, y)) {
+            self.scrub(x);
+        }
+        Ok(())
+    }
+
+    fn key(&mut self, keycode: Keycode) -> bool {
+        match keycode {
+            Keycode::Space => {
+                if self.position >= self.num_steps() {
+                    self.seek(0);
+                }
+                self.playing = !self.playing && self.error.is_none();
+            }
+            Keycode::Right => {
+                self.playing = false;
+                self.step_forward();
+            }
+            Keycode::Left => self.step_back(),
+            Keycode::Up => self.speed = (self.speed + 1).min(SPEEDS.len() - 1),
+            Keycode::Down => self.speed = self.speed.saturating_sub(1),
+            Keycode::O => self.open_next(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.board.fit_canvas(width, height);
+        self.timeline = timeline_rect(width, height);
+    }
+
+    fn camera(&mut self) -> Option<&mut Camera> {
+        Some(self.board.camera_mut())
+    }
+
+    fn set_view_angle(&mut self, angle: ViewAngle) {
+        self.board.set_view_angle(angle);
+    }
+
+    fn set_tile_size(&mut self, tile_size: TileSize) {
+        self.board.set_tile_size(tile_size);
+    }
+
+    fn update(&mut self) -> Result<bool, GameOver> {
+        Ok(Viewer::update(self))
+    }
+
+    fn is_animating(&self) -> bool {
+        self.playing || self.showing.is_some() || self.board.is_animating()
+    }
+
+    fn render(&self, canvas: &mut WindowCanvas, textures: &TileTextures) {
+        if self.replay.is_none() {
+            return;
+        }
+        self.board.render(canvas, textures);
+
+        let timeline = self.timeline;
+        canvas.set_draw_color(TIMELINE_BACKGROUND_COLOR);
+        let _ = canvas.fill_rect(timeline);
+
+        let total = self.total_time().as_millis() as u64;
+        if total > 0 {
+            let played = (self.clock.as_millis() as u64).min(total);
+            let width = (u64::from(timeline.width()) * played / total) as u32;
+            if width > 0 {
+                canvas.set_draw_color(TIMELINE_COLOR);
+                let played = Rect::new(timeline.x(), timeline.y(), width, timeline.height());
+                let _ = canvas.fill_rect(played);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::{
+            env,
+            fs,
+            process,
+            time::{
+                Duration,
+                Instant,
+            },
+        },
+        crate::game::Game,
+        super::{
+            super::{
+                tiles::matching_pairs,
+                Board,
+            },
+            Viewer,
+            SHOW_DURATION,
+        },
+    };
+
+    /**
+     * Returns a viewer of a game where a tile was selected and two pairs were played, a step
+     * each second. The replay is saved in a directory of the test's own and opened from there.
+     */
+    fn viewer(test: &str) -> Viewer {
+        let mut board = Board::new();
+        board.reset_with_seed(5).unwrap();
+        board.finish_animations();
+        for _ in 0..2 {
+            let (tile1, tile2) = matching_pairs(&board.tiles)[0];
+            for tile in [tile1, tile2].iter() {
+                let center = board.tiles.face_rect(*tile).center();
+                let _ = board.click(center.x(), center.y());
+            }
+        }
+
+        let mut replay = board.replay();
+        for (index, step) in replay.steps.iter_mut().enumerate() {
+            step.millis = (index as u64 + 1) * 1000;
+        }
+
+        let dir = env::temp_dir().join(format!("mahjong-viewer-{}-{}", test, process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        replay.save(&dir.join("game.replay")).unwrap();
+        let viewer = Viewer::with_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(viewer.replay, Some(replay));
+        viewer
+    }
+
+    #[test]
+    fn pairs_are_shown_before_they_are_removed() {
+        let mut viewer = viewer("showing");
+
+        viewer.hint();
+        assert_eq!(viewer.position, 1);
+        viewer.hint();
+        assert!(viewer.showing.is_some());
+        assert_eq!(viewer.board.tiles_left(), 144);
+
+        viewer.hint();
+        assert!(viewer.showing.is_none());
+        assert_eq!(viewer.position, 2);
+        assert_eq!(viewer.board.tiles_left(), 142);
+
+        viewer.undo();
+        assert_eq!(viewer.position, 1);
+        assert_eq!(viewer.board.tiles_left(), 144);
+    }
+
+    #[test]
+    fn playing_goes_through_the_whole_replay() {
+        let mut viewer = viewer("playing");
+        viewer.reset();
+
+        // steps are done once their time has come, and pairs once they were shown long enough
+        while viewer.position < 4 {
+            viewer.clock += Duration::from_secs(1);
+            if let Some(showing) = viewer.showing.as_mut() {
+                showing.start = Instant::now() - SHOW_DURATION;
+            }
+            Game::update(&mut viewer).unwrap();
+        }
+        assert_eq!(viewer.board.tiles_left(), 140);
+        assert!(!viewer.playing);
+    }
+
+    #[test]
+    fn scrubbing_moves_to_the_time_on_the_timeline() {
+        let mut viewer = viewer("scrubbing");
+
+        viewer.scrub(viewer.timeline.right());
+        assert_eq!(viewer.position, 4);
+        assert_eq!(viewer.board.tiles_left(), 140);
+
+        viewer.scrub(0);
+        assert_eq!(viewer.board.tiles_left(), 144);
+    }
+
+    #[test]
+    fn the_timeline_runs_along_the_bottom_of_the_canvas() {
+        let mut viewer = viewer("timeline");
+        viewer.resize(2780, 1872);
+
+        let timeline = viewer.timeline;
+        assert!(timeline.bottom() < 1872 && timeline.bottom() > 1872 - 40);
+        assert_eq!(timeline.x() + timeline.right(), 2780);
+        viewer.click(timeline.right(), timeline.center().y()).unwrap();
+        assert_eq!(viewer.position, 4);
+        viewer.click(timeline.x(), timeline.y() - 100).unwrap();
+        assert_eq!(viewer.position, 4);
+    }
+
+    #[test]
+    fn seeking_keeps_the_view_of_the_board() {
+        let mut viewer = viewer("seeking");
+        viewer.resize(1200, 900);
+        viewer.camera().unwrap().zoom_at(2.0, 300, 200);
+        let camera = *viewer.camera().unwrap();
+        let (tile, _) = matching_pairs(&viewer.board.tiles)[0];
+        let face = viewer.board.tiles.face_rect(tile);
+
+        viewer.scrub(viewer.timeline.right());
+        viewer.undo();
+        assert_eq!(*viewer.camera().unwrap(), camera);
+        viewer.scrub(0);
+        assert_eq!(viewer.board.tiles.face_rect(tile), face);
+    }
+}
@@ -0,0 +1,129 @@
+use {
+    sdl2::{
+        keyboard::Keycode,
+        render::WindowCanvas,
+    },
+    crate::game::{
+        Game,
+        GameOver,
+    },
+    super::{
+        Board,
+        Camera,
+        ViewAngle,
+        TileSize,
+        TileTextures,
+    },
+};
+
+/// The number of tiles left at which new tiles are dealt.
+static REFILL_TILES_LEFT: usize = 36;
+
+/// The number of refills tried before a whole new board is dealt.
+static REFILL_ATTEMPTS: usize = 3;
+
+/// Zen mode: the board never runs out. New tiles are dealt into the empty positions whenever
+/// the board runs low or no pair can be played.
+pub struct Zen {
+    board: Board,
+    pairs_cleared: usize,
+}
+
+impl Zen {
+    pub fn new() -> Self {
+        Zen {
+            board: Board::new(),
+            pairs_cleared: 0,
+        }
+    }
+
+    /// Deals new tiles into the empty positions. When no refill is found a whole new board is
+    /// dealt instead, so the game never ends and the pairs cleared are kept.
+    fn refill(&mut self) {
+        let refilled = (0..REFILL_ATTEMPTS).any(|_| self.board.refill().is_ok());
+        if !refilled {
+            self.board.reset();
+        }
+    }
+}
+
+impl Default for Zen {
+    fn default() -> Self {
+        Zen::new()
+    }
+}
+
+impl Game for Zen {
+    fn title(&self) -> String {
+        format!(
+            "Zen - {} rules - {} pairs cleared",
+            self.board.rules().name(),
+            self.pairs_cleared,
+        )
+    }
+
+    fn reset(&mut self) {
+        self.board.reset();
+        self.pairs_cleared = 0;
+    }
+
+    fn undo(&mut self) {
+        let pairs_played = self.board.pairs_played();
+        self.board.undo();
+        self.pairs_cleared -= pairs_played - self.board.pairs_played();
+    }
+
+    fn hint(&mut self) {
+        self.board.highlight_possible_matches();
+    }
+
+    fn click(&mut self, x: i32, y: i32) -> Result<(), GameOver> {
+        let pairs_played = self.board.pairs_played();
+        let result = self.board.try_select_tile(x, y);
+        self.pairs_cleared += self.board.pairs_played() - pairs_played;
+
+        if result.is_err() || self.board.tiles_left() <= REFILL_TILES_LEFT {
+            self.refill();
+        }
+        Ok(())
+    }
+
+    fn key(&mut self, keycode: Keycode) -> bool {
+        match keycode {
+            Keycode::R => {
+                let _ = self.board.set_rules(self.board.rules().next());
+            }
+            _ => return false,
+        }
+        self.pairs_cleared = 0;
+        true
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.board.fit_canvas(width, height);
+    }
+
+    fn camera(&mut self) -> Option<&mut Camera> {
+        Some(self.board.camera_mut())
+    }
+
+    fn set_view_angle(&mut self, angle: ViewAngle) {
+        self.board.set_view_angle(angle);
+    }
+
+    fn set_tile_size(&mut self, tile_size: TileSize) {
+        self.board.set_tile_size(tile_size);
+    }
+
+    fn update(&mut self) -> Result<bool, GameOver> {
+        Ok(self.board.update())
+    }
+
+    fn is_animating(&self) -> bool {
+        self.board.is_animating()
+    }
+
+    fn render(&self, canvas: &mut WindowCanvas, textures: &TileTextures) {
+        self.board.render(canvas, textures);
+    }
+}
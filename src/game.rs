@@ -0,0 +1,167 @@
+use {
+    sdl2::{
+        keyboard::Keycode,
+        rect::Rect,
+        render::WindowCanvas,
+    },
+    crate::{
+        board::{
+            Camera,
+            Replay,
+            TileSize,
+            TileTextures,
+            ViewAngle,
+        },
+        leaderboard::Submission,
+    },
+};
+
+/// The smallest canvas every game is laid out to fit in, which the sizes of the parts of the
+/// games that aren't fitted to the canvas are given for.
+pub static MIN_CANVAS: (u32, u32) = (730, 500);
+
+/**
+ * How much larger than the smallest canvas a canvas is, in the direction it is the least larger
+ * in. Buttons, bars and the like are sized for the smallest canvas and scaled with this, so they
+ * keep their size next to the board on any window and any display.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scale(f64);
+
+impl Scale {
+    pub fn of_canvas(width: u32, height: u32) -> Self {
+        let x = f64::from(width) / f64::from(MIN_CANVAS.0);
+        let y = f64::from(height) / f64::from(MIN_CANVAS.1);
+        Scale(x.min(y))
+    }
+
+    /// Scales a size, which stays at least a pixel.
+    pub fn size(self, size: u32) -> u32 {
+        ((f64::from(size) * self.0).round() as u32).max(1)
+    }
+
+    pub fn distance(self, distance: i32) -> i32 {
+        (f64::from(distance) * self.0).round() as i32
+    }
+
+    pub fn rect(self, rect: Rect) -> Rect {
+        Rect::new(
+            self.distance(rect.x()),
+            self.distance(rect.y()),
+            self.size(rect.width()),
+            self.size(rect.height()),
+        )
+    }
+}
+
+impl Default for Scale {
+    fn default() -> Self {
+        Scale(1.0)
+    }
+}
+
+/// A game mode that can be played in the main window.
+pub trait Game {
+    fn title(&self) -> String;
+
+    fn reset(&mut self);
+
+    fn undo(&mut self);
+
+    fn hint(&mut self);
+
+    fn click(&mut self, x: i32, y: i32) -> Result<(), GameOver>;
+
+    /// Handles a key specific to the game mode, returns whether the key was used.
+    fn key(&mut self, _keycode: Keycode) -> bool {
+        false
+    }
+
+    /// Lays the game out again for a canvas of the given size.
+    fn resize(&mut self, _width: u32, _height: u32) {}
+
+    /// Shows the board as seen from the direction, for modes with a stacked board.
+    fn set_view_angle(&mut self, _angle: ViewAngle) {}
+
+    /// Lays the board out for tiles of the size the theme draws them at.
+    fn set_tile_size(&mut self, _tile_size: TileSize) {}
+
+    /// Returns the camera of the board view, for modes that can zoom and pan it.
+    fn camera(&mut self) -> Option<&mut Camera> {
+        None
+    }
+
+    /// Advances anything that moves on its own, which can end the game, and returns whether the
+    /// game looks different since it was last drawn.
+    fn update(&mut self) -> Result<bool, GameOver> {
+        Ok(false)
+    }
+
+    /// Returns whether anything on screen moves on its own, so the game is drawn every frame
+    /// rather than only when something happens.
+    fn is_animating(&self) -> bool {
+        false
+    }
+
+    /// Returns a summary of how the game went to show when it is over.
+    fn summary(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns the finished game to put on the leaderboard, for modes that have one.
+    fn submission(&self) -> Option<Submission> {
+        None
+    }
+
+    /// Returns the recording of the game so far, for modes that record one.
+    fn replay(&self) -> Option<Replay> {
+        None
+    }
+
+    fn render(&self, canvas: &mut WindowCanvas, textures: &TileTextures);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameOver {
+    Won,
+    Stuck,
+    TrayFull,
+    /// One of the players of a multiplayer game won, players are numbered from 1.
+    PlayerWon(u8),
+    Draw,
+}
+
+impl GameOver {
+    pub fn message(self) -> String {
+        match self {
+            GameOver::Won => "You cleared the board".to_string(),
+            GameOver::Stuck => "You have no possible moves left".to_string(),
+            GameOver::TrayFull => "Your tray is full".to_string(),
+            GameOver::PlayerWon(player) => format!("Player {} won", player),
+            GameOver::Draw => "The game ended in a draw".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        sdl2::rect::Rect,
+        super::{
+            Scale,
+            MIN_CANVAS,
+        },
+    };
+
+    #[test]
+    fn scales_follow_the_side_that_grew_the_least() {
+        assert_eq!(Scale::of_canvas(MIN_CANVAS.0, MIN_CANVAS.1), Scale::default());
+
+        // a maximized window on a high DPI display
+        let scale = Scale::of_canvas(2780, 1872);
+        assert_eq!(scale.size(500), 1872);
+        assert_eq!(scale.rect(Rect::new(5, 5, 60, 25)), Rect::new(19, 19, 225, 94));
+        assert_eq!(Scale::of_canvas(1460, 2000).distance(-10), -20);
+        assert_eq!(Scale::of_canvas(2, 2).size(10), 1);
+    }
+}
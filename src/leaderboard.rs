@@ -0,0 +1,368 @@
+mod http;
+mod server;
+
+use {
+    std::{
+        fs,
+        path::PathBuf,
+        sync::mpsc::{
+            self,
+            Receiver,
+            Sender,
+        },
+        thread,
+        time::Duration,
+    },
+    serde::{
+        Deserialize,
+        Serialize,
+    },
+};
+
+pub use self::{
+    http::HttpBackend,
+    server::Server,
+};
+
+/// The layout every solitaire game is played on.
+pub static LAYOUT: &str = "turtle";
+
+/// The number of best games a leaderboard shows.
+pub static LEADERBOARD_SIZE: usize = 10;
+
+/// The seconds within which a cleared board earns a time bonus.
+static BONUS_SECONDS: u64 = 600;
+
+/// A finished game, with everything needed to play it again and check its score.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Submission {
+    pub layout: String,
+    pub rules: String,
+    pub tile_set: String,
+    pub seed: u64,
+    pub time_millis: u64,
+    pub score: u32,
+    pub moves: Vec<(usize, usize)>,
+}
+
+/// A game on a leaderboard.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub rules: String,
+    pub seed: u64,
+    pub time_millis: u64,
+    pub score: u32,
+}
+
+impl From<&Submission> for Entry {
+    fn from(submission: &Submission) -> Self {
+        Entry {
+            rules: submission.rules.clone(),
+            seed: submission.seed,
+            time_millis: submission.time_millis,
+            score: submission.score,
+        }
+    }
+}
+
+/// How a leaderboard answered a submission.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Response {
+    Accepted,
+    Rejected,
+}
+
+/// Where games are submitted to and leaderboards come from.
+pub trait Backend {
+    fn submit(&mut self, submission: &Submission) -> Result<Response, &'static str>;
+
+    /// Returns the best games on the layout, the best first.
+    fn fetch(&mut self, layout: &str) -> Result<Vec<Entry>, &'static str>;
+}
+
+/// Ten points for every pair, and a point for every second a cleared board is faster than ten
+/// minutes.
+pub fn score(pairs: usize, time: Duration, cleared: bool) -> u32 {
+    let bonus = if cleared { BONUS_SECONDS.saturating_sub(time.as_secs()) } else { 0 };
+    pairs as u32 * 10 + bonus as u32
+}
+
+/**
+ * Submits games to a backend. Games that can't be sent, because the backend can't be reached,
+ * wait in a queue that is stored in a file, and are sent again before anything else is sent or
+ * fetched later on.
+ */
+pub struct Leaderboard<B: Backend> {
+    backend: B,
+    queue: Vec<Submission>,
+    queue_path: Option<PathBuf>,
+}
+
+impl<B: Backend> Leaderboard<B> {
+    pub fn new(backend: B, queue_path: Option<PathBuf>) -> Self {
+        let queue = queue_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        Leaderboard {
+            backend,
+            queue,
+            queue_path,
+        }
+    }
+
+    /// The number of games waiting to be sent.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Queues the game and sends every queued game.
+    pub fn submit(&mut self, submission: Submission) -> Result<Response, &'static str> {
+        self.queue.push(submission);
+        self.retry()
+    }
+
+    /// Sends the queued games in order, until the backend can't be reached. Games the backend
+    /// rejects are dropped, as sending them again won't change its mind, and make this return
+    /// `Rejected`.
+    pub fn retry(&mut self) -> Result<Response, &'static str> {
+        let mut result = Ok(Response::Accepted);
+        while let Some(submission) = self.queue.first() {
+            match self.backend.submit(submission) {
+                Ok(response) => {
+                    self.queue.remove(0);
+                    if response == Response::Rejected {
+                        result = Ok(Response::Rejected);
+                    }
+                }
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+
+        self.save_queue();
+        result
+    }
+
+    pub fn fetch(&mut self, layout: &str) -> Result<Vec<Entry>, &'static str> {
+        let _ = self.retry();
+        self.backend.fetch(layout)
+    }
+
+    fn save_queue(&self) {
+        if let Some(path) = self.queue_path.as_ref() {
+            let json = serde_json::to_string(&self.queue).expect("queue can't be written");
+            let _ = fs::write(path, json);
+        }
+    }
+}
+
+/// What a worker was asked to do.
+enum Request {
+    Submit(Submission),
+    Fetch(String),
+}
+
+/// What came back from a worker, in the order it was asked.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reply {
+    Submitted(Result<Response, &'static str>),
+    Fetched(Result<Vec<Entry>, &'static str>),
+}
+
+/**
+ * Runs a leaderboard on a thread of its own, so the window never waits for the backend. Games
+ * still queued from before are sent first, and the replies to requests are picked up later.
+ */
+pub struct Worker {
+    requests: Sender<Request>,
+    replies: Receiver<Reply>,
+}
+
+impl Worker {
+    pub fn spawn<B: Backend + Send + 'static>(mut leaderboard: Leaderboard<B>) -> Self {
+        let (requests, pending) = mpsc::channel();
+        let (sender, replies) = mpsc::channel();
+
+        thread::spawn(move || {
+            if leaderboard.queued() > 0 {
+                let _ = leaderboard.retry();
+            }
+            for request in pending {
+                let reply = match request {
+                    Request::Submit(submission) => Reply::Submitted(leaderboard.submit(submission)),
+                    Request::Fetch(layout) => Reply::Fetched(leaderboard.fetch(&layout)),
+                };
+                if sender.send(reply).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Worker {
+            requests,
+            replies,
+        }
+    }
+
+    pub fn submit(&self, submission: Submission) {
+        let _ = self.requests.send(Request::Submit(submission));
+    }
+
+    pub fn fetch(&self, layout: &str) {
+        let _ = self.requests.send(Request::Fetch(layout.to_string()));
+    }
+
+    /// Returns the replies that came back since the last call, without waiting for more.
+    pub fn replies(&self) -> Vec<Reply> {
+        self.replies.try_iter().collect()
+    }
+}
+
+/// A game on the turtle with the Classic rules and the standard tiles, for tests.
+#[cfg(test)]
+fn submission(seed: u64, time_millis: u64, score: u32, moves: Vec<(usize, usize)>) -> Submission {
+    Submission {
+        layout: LAYOUT.to_string(),
+        rules: "Classic".to_string(),
+        tile_set: "Standard".to_string(),
+        seed,
+        time_millis,
+        score,
+        moves,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::{
+            env,
+            fs,
+            thread,
+            time::{
+                Duration,
+                Instant,
+            },
+        },
+        super::{
+            score,
+            submission,
+            Backend,
+            Entry,
+            Leaderboard,
+            Reply,
+            Response,
+            Submission,
+            Worker,
+        },
+    };
+
+    /// A backend that can be taken offline, and rejects games without moves.
+    #[derive(Default)]
+    struct FakeBackend {
+        offline: bool,
+        entries: Vec<Entry>,
+    }
+
+    impl Backend for FakeBackend {
+        fn submit(&mut self, submission: &Submission) -> Result<Response, &'static str> {
+            if self.offline {
+                return Err("offline");
+            }
+            if submission.moves.is_empty() {
+                return Ok(Response::Rejected);
+            }
+            self.entries.push(Entry::from(submission));
+            Ok(Response::Accepted)
+        }
+
+        fn fetch(&mut self, _layout: &str) -> Result<Vec<Entry>, &'static str> {
+            if self.offline {
+                return Err("offline");
+            }
+            Ok(self.entries.clone())
+        }
+    }
+
+    #[test]
+    fn offline_games_are_sent_later_in_order() {
+        let backend = FakeBackend { offline: true, ..Default::default() };
+        let mut leaderboard = Leaderboard::new(backend, None);
+
+        assert!(leaderboard.submit(submission(1, 1000, 10, vec![(0, 1)])).is_err());
+        assert!(leaderboard.submit(submission(2, 1000, 10, vec![(0, 1)])).is_err());
+        assert_eq!(leaderboard.queued(), 2);
+
+        leaderboard.backend.offline = false;
+        let seeds = leaderboard
+            .fetch("turtle")
+            .unwrap()
+            .iter()
+            .map(|entry| entry.seed)
+            .collect::<Vec<_>>();
+        assert_eq!(seeds, vec![1, 2]);
+        assert_eq!(leaderboard.queued(), 0);
+    }
+
+    #[test]
+    fn rejected_games_are_dropped() {
+        let mut leaderboard = Leaderboard::new(FakeBackend::default(), None);
+
+        let empty = submission(1, 1000, 10, Vec::new());
+        let played = submission(2, 1000, 10, vec![(0, 1)]);
+        assert_eq!(leaderboard.submit(empty), Ok(Response::Rejected));
+        assert_eq!(leaderboard.submit(played), Ok(Response::Accepted));
+        assert_eq!(leaderboard.queued(), 0);
+        assert_eq!(leaderboard.fetch("turtle").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn queue_survives_a_restart() {
+        let path = env::temp_dir().join(format!("mahjong-queue-{}.json", std::process::id()));
+        let offline = || FakeBackend { offline: true, ..Default::default() };
+
+        let mut leaderboard = Leaderboard::new(offline(), Some(path.clone()));
+        let _ = leaderboard.submit(submission(7, 1000, 10, vec![(0, 1)]));
+
+        let leaderboard = Leaderboard::new(offline(), Some(path.clone()));
+        assert_eq!(leaderboard.queue, vec![submission(7, 1000, 10, vec![(0, 1)])]);
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn workers_reply_in_order() {
+        let worker = Worker::spawn(Leaderboard::new(FakeBackend::default(), None));
+        worker.submit(submission(1, 1000, 10, Vec::new()));
+        worker.submit(submission(2, 1000, 10, vec![(0, 1)]));
+        worker.fetch("turtle");
+
+        let mut replies = Vec::new();
+        let start = Instant::now();
+        while replies.len() < 3 {
+            assert!(start.elapsed() < Duration::from_secs(2), "worker didn't reply");
+            replies.extend(worker.replies());
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(replies, vec![
+            Reply::Submitted(Ok(Response::Rejected)),
+            Reply::Submitted(Ok(Response::Accepted)),
+            Reply::Fetched(Ok(vec![Entry::from(&submission(2, 1000, 10, vec![(0, 1)]))])),
+        ]);
+    }
+
+    #[test]
+    fn faster_clears_score_higher() {
+        let fast = score(72, Duration::from_secs(100), true);
+        let slow = score(72, Duration::from_secs(500), true);
+
+        assert!(fast > slow);
+        assert_eq!(score(72, Duration::from_secs(700), true), 720);
+        assert_eq!(score(30, Duration::from_secs(10), false), 300);
+    }
+}
@@ -0,0 +1,196 @@
+use {
+    std::{
+        io::{
+            Read,
+            Write,
+        },
+        net::{
+            TcpStream,
+            ToSocketAddrs,
+        },
+        time::Duration,
+    },
+    super::{
+        Backend,
+        Entry,
+        Response,
+        Submission,
+    },
+};
+
+static TIMEOUT: Duration = Duration::from_secs(3);
+
+/**
+ * Talks JSON over plain HTTP/1.1 to a leaderboard at a URL like `http://host:port/path`. Games
+ * are posted to `path/scores` and leaderboards are fetched from `path/scores?layout=...`, which
+ * is what the reference server serves.
+ */
+pub struct HttpBackend {
+    host: String,
+    path: String,
+}
+
+impl HttpBackend {
+    pub fn new(url: &str) -> Result<Self, &'static str> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or("leaderboard url must start with http://")?;
+        let (host, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, ""),
+        };
+        if host.is_empty() {
+            return Err("leaderboard url has no host");
+        }
+
+        let host = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+        Ok(HttpBackend {
+            host,
+            path: path.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Sends a request and returns the status code and the body of the response.
+    fn request(
+        &self,
+        method: &str,
+        target: &str,
+        body: &str,
+    ) -> Result<(u16, String), &'static str> {
+        let address = self
+            .host
+            .to_socket_addrs()
+            .map_err(|_| "leaderboard host not found")?
+            .next()
+            .ok_or("leaderboard host not found")?;
+        let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)
+            .map_err(|_| "leaderboard can't be reached")?;
+        stream.set_read_timeout(Some(TIMEOUT)).map_err(|_| "leaderboard can't be reached")?;
+
+        let request = format!(
+            "{} {}{} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            self.path,
+            target,
+            self.host,
+            body.len(),
+            body,
+        );
+        stream.write_all(request.as_bytes()).map_err(|_| "leaderboard can't be reached")?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).map_err(|_| "leaderboard did not answer")?;
+        parse_response(&response)
+    }
+}
+
+impl Backend for HttpBackend {
+    fn submit(&mut self, submission: &Submission) -> Result<Response, &'static str> {
+        let body = serde_json::to_string(submission).expect("submission can't be written");
+        match self.request("POST", "/scores", &body)? {
+            (200..=299, _) => Ok(Response::Accepted),
+            (400..=499, _) => Ok(Response::Rejected),
+            _ => Err("leaderboard failed to store the game"),
+        }
+    }
+
+    fn fetch(&mut self, layout: &str) -> Result<Vec<Entry>, &'static str> {
+        match self.request("GET", &format!("/scores?layout={}", layout), "")? {
+            (200, body) => serde_json::from_str(&body).map_err(|_| "leaderboard sent invalid data"),
+            _ => Err("leaderboard failed to send the scores"),
+        }
+    }
+}
+
+/// Splits a response into its status code and body. The body is read up to the end of the
+/// connection, so chunked bodies are not supported.
+fn parse_response(response: &str) -> Result<(u16, String), &'static str> {
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or("leaderboard sent an incomplete response")?;
+
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or("leaderboard sent an invalid response")?;
+
+    let chunked = head
+        .lines()
+        .any(|line| line.to_ascii_lowercase().starts_with("transfer-encoding: chunked"));
+    if chunked {
+        return Err("leaderboard sent a chunked response");
+    }
+
+    Ok((status, body.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::thread,
+        super::{
+            parse_response,
+            HttpBackend,
+            super::{
+                submission,
+                Backend,
+                Response,
+                Server,
+            },
+        },
+    };
+
+    #[test]
+    fn urls_are_split_into_host_and_path() {
+        let backend = HttpBackend::new("http://scores.example:8080/mahjong/").unwrap();
+        assert_eq!(backend.host, "scores.example:8080");
+        assert_eq!(backend.path, "/mahjong");
+
+        let backend = HttpBackend::new("http://scores.example").unwrap();
+        assert_eq!(backend.host, "scores.example:80");
+        assert_eq!(backend.path, "");
+
+        assert!(HttpBackend::new("https://scores.example").is_err());
+        assert!(HttpBackend::new("http:///scores").is_err());
+    }
+
+    #[test]
+    fn responses_are_parsed() {
+        let response = "HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\n{}";
+        assert_eq!(parse_response(response), Ok((201, "{}".to_string())));
+
+        assert!(parse_response("HTTP/1.1 200 OK\r\n").is_err());
+        assert!(parse_response("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn games_are_submitted_to_the_reference_server() {
+        let mut server = Server::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://127.0.0.1:{}/", server.local_port().unwrap());
+        let handle = thread::spawn(move || {
+            for _ in 0..4 {
+                server.handle_next().unwrap();
+            }
+        });
+
+        let mut backend = HttpBackend::new(&url).unwrap();
+        let fast = submission(3, 1000, 0, Vec::new());
+        let slow = submission(3, 2000, 0, Vec::new());
+        assert_eq!(backend.submit(&slow), Ok(Response::Accepted));
+        assert_eq!(backend.submit(&fast), Ok(Response::Accepted));
+        // the score doesn't match the moves
+        assert_eq!(backend.submit(&submission(3, 1000, 500, Vec::new())), Ok(Response::Rejected));
+
+        let times = backend
+            .fetch("turtle")
+            .unwrap()
+            .iter()
+            .map(|entry| entry.time_millis)
+            .collect::<Vec<_>>();
+        assert_eq!(times, vec![1000, 2000]);
+
+        handle.join().unwrap();
+    }
+}
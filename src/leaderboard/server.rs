@@ -0,0 +1,218 @@
+use {
+    std::{
+        cmp::Reverse,
+        io::{
+            self,
+            BufRead,
+            BufReader,
+            Write,
+        },
+        net::{
+            TcpListener,
+            ToSocketAddrs,
+        },
+        time::Duration,
+    },
+    crate::board::{
+        Board,
+        RuleSet,
+        TileSet,
+    },
+    super::{
+        score,
+        Entry,
+        Submission,
+        LAYOUT,
+        LEADERBOARD_SIZE,
+    },
+};
+
+/// The largest request body that is read.
+static MAX_BODY_LENGTH: usize = 64 * 1024;
+
+/**
+ * A small leaderboard server to test against, which keeps the games in memory. It plays every
+ * game again from its deal to check that each pair was free and matching when it was played,
+ * and that its score matches its moves and time. It serves one request at a time.
+ */
+pub struct Server {
+    listener: TcpListener,
+    submissions: Vec<Submission>,
+}
+
+impl Server {
+    pub fn bind<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        Ok(Server {
+            listener: TcpListener::bind(address)?,
+            submissions: Vec::new(),
+        })
+    }
+
+    pub fn local_port(&self) -> io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    /// Serves requests until the process ends. A request that fails is logged and doesn't stop
+    /// the server.
+    pub fn run(&mut self) -> ! {
+        loop {
+            if let Err(err) = self.handle_next() {
+                eprintln!("leaderboard request failed: {}", err);
+            }
+        }
+    }
+
+    /// Waits for the next request and answers it.
+    pub fn handle_next(&mut self) -> io::Result<()> {
+        let (stream, _) = self.listener.accept()?;
+        stream.set_read_timeout(Some(Duration::from_secs(3)))?;
+
+        let mut reader = BufReader::new(stream);
+        let (status, body) = match read_request(&mut reader) {
+            Ok((method, target, body)) => self.respond(&method, &target, &body),
+            Err(_) => (400, String::new()),
+        };
+
+        let response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            status,
+            reason(status),
+            body.len(),
+            body,
+        );
+        reader.get_mut().write_all(response.as_bytes())
+    }
+
+    fn respond(&mut self, method: &str, target: &str, body: &str) -> (u16, String) {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        if !path.ends_with("/scores") {
+            return (404, String::new());
+        }
+
+        match method {
+            "POST" => match serde_json::from_str::<Submission>(body) {
+                Ok(ref submission) if is_valid(submission) => {
+                    self.submissions.push(submission.clone());
+                    (201, String::new())
+                }
+                _ => (422, String::new()),
+            },
+            "GET" => {
+                let layout = query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("layout="))
+                    .unwrap_or(LAYOUT);
+                let json = serde_json::to_string(&self.leaderboard(layout))
+                    .expect("leaderboard can't be written");
+                (200, json)
+            }
+            _ => (405, String::new()),
+        }
+    }
+
+    /// Returns the best games on the layout, the highest score first and the fastest first
+    /// among equal scores.
+    fn leaderboard(&self, layout: &str) -> Vec<Entry> {
+        let mut entries = self
+            .submissions
+            .iter()
+            .filter(|submission| submission.layout == layout)
+            .map(Entry::from)
+            .collect::<Vec<_>>();
+
+        entries.sort_by_key(|entry| (Reverse(entry.score), entry.time_millis));
+        entries.truncate(LEADERBOARD_SIZE);
+        entries
+    }
+}
+
+/// Plays the moves on the deal of the game and checks the score against the result.
+fn is_valid(submission: &Submission) -> bool {
+    let rules = RuleSet::from_name(&submission.rules);
+    let tile_set = TileSet::presets()
+        .into_iter()
+        .find(|tile_set| tile_set.name() == submission.tile_set);
+    let (rules, tile_set) = match (rules, tile_set) {
+        (Some(rules), Some(tile_set)) => (rules, tile_set),
+        _ => return false,
+    };
+    let mut board = match Board::with_deal(rules, tile_set, false, submission.seed) {
+        Ok(board) => board,
+        Err(_) => return false,
+    };
+    if board.play_moves(&submission.moves).is_err() {
+        return false;
+    }
+
+    let pairs = submission.moves.len();
+    let time = Duration::from_millis(submission.time_millis);
+    submission.layout == LAYOUT && submission.score == score(pairs, time, board.tiles_left() == 0)
+}
+
+/// Reads the method, target and body of a request.
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<(String, String, String)> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid request");
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut words = request_line.split_whitespace();
+    let method = words.next().ok_or_else(invalid)?.to_string();
+    let target = words.next().ok_or_else(invalid)?.to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| invalid())?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_LENGTH {
+        return Err(invalid());
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8(body).map_err(|_| invalid())?;
+    Ok((method, target, body))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Unprocessable Entity",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_valid,
+        super::submission,
+    };
+
+    #[test]
+    fn games_without_moves_score_nothing() {
+        assert!(is_valid(&submission(3, 1000, 0, Vec::new())));
+        assert!(!is_valid(&submission(3, 1000, 10, Vec::new())));
+    }
+
+    #[test]
+    fn forged_moves_are_rejected() {
+        // the tiles below the top tile are covered whatever the deal
+        assert!(!is_valid(&submission(3, 1000, 10, vec![(139, 140)])));
+        assert!(!is_valid(&submission(3, 1000, 10, vec![(0, 144)])));
+        assert!(!is_valid(&submission(3, 1000, 20, vec![(0, 0), (0, 0)])));
+    }
+}
@@ -1,11 +1,84 @@
 #![cfg_attr(all(windows, not(debug_assertions)), windows_subsystem = "windows")]
 
 mod app;
+mod assets;
 mod board;
+mod game;
+mod leaderboard;
+mod memory;
+mod race;
 mod sdl;
+mod shisen;
 mod ui;
 
+use std::{
+    env,
+    path::{
+        Path,
+        PathBuf,
+    },
+    process,
+};
+
 fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(String::as_str) == Some("--serve-leaderboard") {
+        serve_leaderboard(args.get(1));
+        return;
+    }
+    if args.first().map(String::as_str) == Some("--verify-replay") {
+        verify_replay(args.get(1));
+        return;
+    }
+
+    let (role, replay) = if args.first().map(String::as_str) == Some("--watch-replay") {
+        let path = args.get(1).unwrap_or_else(|| {
+            eprintln!("no replay file to watch");
+            process::exit(2);
+        });
+        (None, Some(PathBuf::from(path)))
+    } else {
+        let role = race::Role::from_args(args.into_iter()).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(2);
+        });
+        (role, None)
+    };
+
     let mut sdl = sdl::init();
-    app::run(&mut sdl);
+    app::run(&mut sdl, role, replay);
+}
+
+/// Runs the reference leaderboard server, on port 8787 unless another port is given.
+fn serve_leaderboard(port: Option<&String>) {
+    let port = port.map_or(Ok(8787), |port| port.parse::<u16>()).unwrap_or_else(|_| {
+        eprintln!("invalid port to serve the leaderboard on");
+        process::exit(2);
+    });
+
+    let bound = leaderboard::Server::bind(("0.0.0.0", port))
+        .and_then(|server| Ok((server.local_port()?, server)));
+    let (port, mut server) = bound.unwrap_or_else(|err| {
+        eprintln!("leaderboard server can't be started: {}", err);
+        process::exit(1);
+    });
+
+    println!("Serving the leaderboard on port {}", port);
+    server.run()
+}
+
+/// Plays a replay file again without a window, and exits with an error unless it is a win.
+fn verify_replay(path: Option<&String>) {
+    let path = path.unwrap_or_else(|| {
+        eprintln!("no replay file to verify");
+        process::exit(2);
+    });
+
+    match board::Replay::load(Path::new(path)).and_then(|replay| replay.verify()) {
+        Ok(time) => println!("The replay clears the board in {:.1}s", time.as_secs_f64()),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
 }
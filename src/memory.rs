@@ -0,0 +1,524 @@
+use {
+    std::time::{
+        Duration,
+        Instant,
+    },
+    rand::{
+        rngs::SmallRng,
+        seq::SliceRandom,
+        FromEntropy,
+        Rng,
+    },
+    sdl2::{
+        keyboard::Keycode,
+        rect::Rect,
+        render::WindowCanvas,
+    },
+    crate::{
+        board::{
+            next_tile_set,
+            TileSet,
+            TileTextures,
+            TileType,
+        },
+        game::{
+            Game,
+            GameOver,
+            Scale,
+            MIN_CANVAS,
+        },
+    },
+};
+
+static TILE_WIDTH: u32 = 36;
+static TILE_HEIGHT: u32 = 46;
+static TILE_SIDE_WIDTH: u32 = 3;
+static TILE_BOTTOM_HEIGHT: u32 = 3;
+static TILE_SPACING: u32 = 8;
+
+static MISMATCH_DURATION: Duration = Duration::from_millis(1000);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Difficulty {
+    #[default]
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn next(self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Medium,
+            Difficulty::Medium => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    /// The number of columns and rows of tiles.
+    fn grid_size(self) -> (u8, u8) {
+        match self {
+            Difficulty::Easy => (6, 4),
+            Difficulty::Medium => (8, 6),
+            Difficulty::Hard => (12, 8),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CardState {
+    FaceDown,
+    FaceUp,
+    Matched,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Card {
+    tile_type: TileType,
+    state: CardState,
+}
+
+/// Memory, also known as Concentration: the tiles are dealt face-down and two are turned over
+/// at a time to find matching pairs. With two players the turn passes on when a pair doesn't match.
+pub struct Memory {
+    cards: Vec<Card>,
+    tile_set: TileSet,
+    difficulty: Difficulty,
+    two_players: bool,
+    current_player: usize,
+    pairs_found: [u32; 2],
+    moves: u32,
+    flipped: Vec<usize>,
+    mismatch_time: Option<Instant>,
+    rng: SmallRng,
+    canvas_size: (u32, u32),
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Memory::with_rng(SmallRng::from_entropy())
+    }
+
+    fn with_rng(rng: SmallRng) -> Self {
+        let mut memory = Memory {
+            cards: Vec::new(),
+            tile_set: TileSet::default(),
+            difficulty: Difficulty::default(),
+            two_players: false,
+            current_player: 0,
+            pairs_found: [0; 2],
+            moves: 0,
+            flipped: Vec::new(),
+            mismatch_time: None,
+            rng,
+            canvas_size: MIN_CANVAS,
+        };
+        Game::reset(&mut memory);
+        memory
+    }
+
+    fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        Game::reset(self);
+    }
+
+    fn set_tile_set(&mut self, tile_set: TileSet) {
+        self.tile_set = tile_set;
+        Game::reset(self);
+    }
+
+    fn set_two_players(&mut self, two_players: bool) {
+        self.two_players = two_players;
+        Game::reset(self);
+    }
+
+    fn columns(&self) -> u8 {
+        self.difficulty.grid_size().0
+    }
+
+    fn rows(&self) -> u8 {
+        self.difficulty.grid_size().1
+    }
+
+    /// Turns a card face-up, returns the result of the game when the last pair was found.
+    fn flip(&mut self, index: usize) -> Result<(), GameOver> {
+        if self.mismatch_time.is_some() {
+            self.hide_mismatch();
+        }
+        if self.cards[index].state != CardState::FaceDown {
+            return Ok(());
+        }
+
+        self.cards[index].state = CardState::FaceUp;
+        self.flipped.push(index);
+        if self.flipped.len() < 2 {
+            return Ok(());
+        }
+
+        self.moves += 1;
+        let (card1, card2) = (self.cards[self.flipped[0]], self.cards[self.flipped[1]]);
+        if !self.tile_set.matches(card1.tile_type, card2.tile_type) {
+            self.mismatch_time = Some(Instant::now());
+            return Ok(());
+        }
+
+        for &index in &self.flipped {
+            self.cards[index].state = CardState::Matched;
+        }
+        self.flipped.clear();
+        self.pairs_found[self.current_player] += 1;
+
+        if self.cards.iter().all(|card| card.state == CardState::Matched) {
+            return Err(self.result());
+        }
+        Ok(())
+    }
+
+    /// Turns a mismatched pair face-down again and passes the turn to the other player.
+    fn hide_mismatch(&mut self) {
+        for &index in &self.flipped {
+            self.cards[index].state = CardState::FaceDown;
+        }
+        self.flipped.clear();
+        self.mismatch_time = None;
+
+        if self.two_players {
+            self.current_player = 1 - self.current_player;
+        }
+    }
+
+    fn result(&self) -> GameOver {
+        if !self.two_players {
+            return GameOver::Won;
+        }
+
+        let [pairs1, pairs2] = self.pairs_found;
+        if pairs1 > pairs2 {
+            GameOver::PlayerWon(1)
+        } else if pairs2 > pairs1 {
+            GameOver::PlayerWon(2)
+        } else {
+            GameOver::Draw
+        }
+    }
+
+    fn scale(&self) -> Scale {
+        Scale::of_canvas(self.canvas_size.0, self.canvas_size.1)
+    }
+
+    /// Returns where the top left tile is drawn, the tiles are centered on the canvas.
+    fn origin(&self) -> (i32, i32) {
+        let scale = self.scale();
+        let width = u32::from(self.columns()) * scale.size(TILE_WIDTH + TILE_SPACING);
+        let height = u32::from(self.rows()) * scale.size(TILE_HEIGHT + TILE_SPACING);
+        let (canvas_width, canvas_height) = self.canvas_size;
+        ((canvas_width as i32 - width as i32) / 2, (canvas_height as i32 - height as i32) / 2)
+    }
+
+    fn card_rect(&self, index: usize) -> Rect {
+        let scale = self.scale();
+        let (origin_x, origin_y) = self.origin();
+        let column = (index % usize::from(self.columns())) as i32;
+        let row = (index / usize::from(self.columns())) as i32;
+
+        Rect::new(
+            origin_x + column * scale.size(TILE_WIDTH + TILE_SPACING) as i32,
+            origin_y + row * scale.size(TILE_HEIGHT + TILE_SPACING) as i32,
+            scale.size(TILE_WIDTH),
+            scale.size(TILE_HEIGHT),
+        )
+    }
+
+    fn find_card_by_coord(&self, x: i32, y: i32) -> Option<usize> {
+        (0..self.cards.len()).find(|&index| self.card_rect(index).contains_point((x, y)))
+    }
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Memory::new()
+    }
+}
+
+impl Game for Memory {
+    fn title(&self) -> String {
+        let mode = format!(
+            "Memory - {} - {} tiles",
+            self.difficulty.name(),
+            self.tile_set.name(),
+        );
+
+        if self.two_players {
+            format!(
+                "{} - player {} to play - pairs {} : {}",
+                mode,
+                self.current_player + 1,
+                self.pairs_found[0],
+                self.pairs_found[1],
+            )
+        } else {
+            format!("{} - {} moves", mode, self.moves)
+        }
+    }
+
+    /**
+     * Deals random pairs from the tile set. Consecutive tiles of the tile set match, so picking
+     * pairs of them keeps every tile on the table matchable.
+     */
+    fn reset(&mut self) {
+        let num_pairs = usize::from(self.columns()) * usize::from(self.rows()) / 2;
+        let mut types = self.tile_set.types();
+
+        let mut cards = Vec::with_capacity(num_pairs * 2);
+        for _ in 0..num_pairs {
+            let random_index = self.rng.gen_range(0, types.len() / 2) * 2;
+            for tile_type in &[types.swap_remove(random_index + 1), types.swap_remove(random_index)] {
+                cards.push(Card { tile_type: *tile_type, state: CardState::FaceDown });
+            }
+        }
+        cards.shuffle(&mut self.rng);
+
+        self.cards = cards;
+        self.current_player = 0;
+        self.pairs_found = [0; 2];
+        self.moves = 0;
+        self.flipped.clear();
+        self.mismatch_time = None;
+    }
+
+    /// Turned over tiles can't be forgotten, so there is nothing to undo.
+    fn undo(&mut self) {}
+
+    /// Giving away tiles would spoil the game, so there are no hints.
+    fn hint(&mut self) {}
+
+    fn click(&mut self, x: i32, y: i32) -> Result<(), GameOver> {
+        match self.find_card_by_coord(x, y) {
+            Some(index) => self.flip(index),
+            None => Ok(()),
+        }
+    }
+
+    fn key(&mut self, keycode: Keycode) -> bool {
+        match keycode {
+            Keycode::D => self.set_difficulty(self.difficulty.next()),
+            Keycode::P => self.set_two_players(!self.two_players),
+            Keycode::T => self.set_tile_set(next_tile_set(&self.tile_set)),
+            _ => return false,
+        }
+        true
+    }
+
+    fn update(&mut self) -> Result<bool, GameOver> {
+        let expired = self
+            .mismatch_time
+            .is_some_and(|start_time| start_time.elapsed() > MISMATCH_DURATION);
+        if expired {
+            self.hide_mismatch();
+        }
+        Ok(expired)
+    }
+
+    /// The mismatched cards are shown until they turn back over.
+    fn is_animating(&self) -> bool {
+        self.mismatch_time.is_some()
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.canvas_size = (width, height);
+    }
+
+    fn render(&self, canvas: &mut WindowCanvas, textures: &TileTextures) {
+        let scale = self.scale();
+        let side_width = scale.size(TILE_SIDE_WIDTH);
+        let bottom_height = scale.size(TILE_BOTTOM_HEIGHT);
+        for (index, card) in self.cards.iter().enumerate() {
+            if card.state == CardState::Matched {
+                continue;
+            }
+
+            let face = self.card_rect(index);
+            let side = Rect::new(
+                face.x() - side_width as i32,
+                face.y(),
+                side_width,
+                face.height() + bottom_height,
+            );
+            let bottom = Rect::new(face.x(), face.bottom(), face.width(), bottom_height);
+
+            textures.side().draw(canvas, side);
+            textures.bottom().draw(canvas, bottom);
+
+            let image = if card.state == CardState::FaceUp {
+                textures.face(card.tile_type)
+            } else {
+                textures.back()
+            };
+            image.draw(canvas, face);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::time::Instant,
+        rand::{
+            rngs::SmallRng,
+            SeedableRng,
+        },
+        crate::game::{
+            Game,
+            GameOver,
+        },
+        super::{
+            CardState,
+            Difficulty,
+            Memory,
+            MISMATCH_DURATION,
+        },
+    };
+
+    fn memory(two_players: bool) -> Memory {
+        let mut memory = Memory::with_rng(SmallRng::seed_from_u64(1));
+        memory.set_two_players(two_players);
+        memory
+    }
+
+    /// Returns the indices of two face-down cards that do or don't match.
+    fn find_pair(memory: &Memory, matching: bool) -> (usize, usize) {
+        let face_down = (0..memory.cards.len())
+            .filter(|&index| memory.cards[index].state == CardState::FaceDown)
+            .collect::<Vec<_>>();
+
+        for (position, &index1) in face_down.iter().enumerate() {
+            for &index2 in &face_down[position + 1..] {
+                let type1 = memory.cards[index1].tile_type;
+                let type2 = memory.cards[index2].tile_type;
+                if memory.tile_set.matches(type1, type2) == matching {
+                    return (index1, index2);
+                }
+            }
+        }
+        panic!("no pair found");
+    }
+
+    #[test]
+    fn tiles_are_centered_on_the_canvas() {
+        let mut memory = memory(false);
+        memory.set_difficulty(Difficulty::Hard);
+        memory.resize(2780, 1872);
+
+        let (first, last) = (memory.card_rect(0), memory.card_rect(memory.cards.len() - 1));
+        assert!(first.x() > 0 && last.bottom() < 1872);
+        // the spacing after the last tile is left out of the middle
+        let spacing = memory.card_rect(1).x() - first.right();
+        assert!((first.x() + last.right() + spacing - 2780).abs() <= 1);
+        let center = last.center();
+        assert_eq!(memory.find_card_by_coord(center.x(), center.y()), Some(memory.cards.len() - 1));
+    }
+
+    #[test]
+    fn deals_matching_pairs_for_every_difficulty() {
+        let mut memory = memory(false);
+
+        for &difficulty in &[Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+            memory.set_difficulty(difficulty);
+            let (columns, rows) = difficulty.grid_size();
+            assert_eq!(memory.cards.len(), usize::from(columns) * usize::from(rows));
+
+            for card in &memory.cards {
+                let matches = memory
+                    .cards
+                    .iter()
+                    .filter(|other| memory.tile_set.matches(card.tile_type, other.tile_type))
+                    .count();
+                assert!(matches >= 2);
+            }
+        }
+    }
+
+    #[test]
+    fn counts_moves_and_removes_matches() {
+        let mut memory = memory(false);
+
+        let (match1, match2) = find_pair(&memory, true);
+        memory.flip(match1).unwrap();
+        memory.flip(match2).unwrap();
+        assert_eq!(memory.moves, 1);
+        assert_eq!(memory.cards[match1].state, CardState::Matched);
+        assert_eq!(memory.cards[match2].state, CardState::Matched);
+
+        let (miss1, miss2) = find_pair(&memory, false);
+        memory.flip(miss1).unwrap();
+        memory.flip(miss2).unwrap();
+        assert_eq!(memory.moves, 2);
+        assert_eq!(memory.cards[miss1].state, CardState::FaceUp);
+
+        // flipping another card turns the mismatched pair face-down again
+        let other = (0..memory.cards.len())
+            .find(|&index| memory.cards[index].state == CardState::FaceDown)
+            .unwrap();
+        memory.flip(other).unwrap();
+        assert_eq!(memory.cards[miss1].state, CardState::FaceDown);
+        assert_eq!(memory.cards[miss2].state, CardState::FaceDown);
+        assert_eq!(memory.cards[other].state, CardState::FaceUp);
+    }
+
+    #[test]
+    fn mismatches_turn_back_over_on_their_own() {
+        let mut memory = memory(false);
+        assert!(!memory.is_animating());
+
+        let (miss1, miss2) = find_pair(&memory, false);
+        memory.flip(miss1).unwrap();
+        memory.flip(miss2).unwrap();
+        assert!(memory.is_animating());
+        assert_eq!(memory.update(), Ok(false));
+
+        memory.mismatch_time = Some(Instant::now() - MISMATCH_DURATION * 2);
+        assert_eq!(memory.update(), Ok(true));
+        assert_eq!(memory.cards[miss1].state, CardState::FaceDown);
+        assert!(!memory.is_animating());
+    }
+
+    #[test]
+    fn turn_passes_after_mismatch_only() {
+        let mut memory = memory(true);
+
+        let (match1, match2) = find_pair(&memory, true);
+        memory.flip(match1).unwrap();
+        memory.flip(match2).unwrap();
+        assert_eq!(memory.current_player, 0);
+        assert_eq!(memory.pairs_found, [1, 0]);
+
+        let (miss1, miss2) = find_pair(&memory, false);
+        memory.flip(miss1).unwrap();
+        memory.flip(miss2).unwrap();
+        memory.hide_mismatch();
+        assert_eq!(memory.current_player, 1);
+    }
+
+    #[test]
+    fn finding_all_pairs_ends_the_game() {
+        let mut memory = memory(true);
+
+        let mut result = Ok(());
+        while result.is_ok() {
+            let (index1, index2) = find_pair(&memory, true);
+            memory.flip(index1).unwrap();
+            result = memory.flip(index2);
+        }
+
+        assert_eq!(result, Err(GameOver::PlayerWon(1)));
+        assert_eq!(memory.pairs_found, [12, 0]);
+    }
+}
@@ -0,0 +1,548 @@
+mod net;
+
+use {
+    std::time::{
+        Duration,
+        Instant,
+    },
+    sdl2::{
+        keyboard::Keycode,
+        pixels::Color,
+        rect::Rect,
+        render::WindowCanvas,
+    },
+    crate::{
+        board::{
+            Board,
+            Camera,
+            TileSize,
+            TileTextures,
+            ViewAngle,
+        },
+        game::{
+            Game,
+            GameOver,
+            Scale,
+        },
+    },
+    self::net::{
+        Connection,
+        Host,
+        Message,
+    },
+};
+
+pub static DEFAULT_PORT: u16 = 7878;
+
+/// How often the progress is sent while nothing changes, so the others see the time go on.
+static PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+static OWN_COLOR: Color = Color { r: 80, g: 140, b: 255, a: 255 };
+static OPPONENT_COLOR: Color = Color { r: 255, g: 160, b: 60, a: 255 };
+static FINISHED_COLOR: Color = Color { r: 90, g: 200, b: 90, a: 255 };
+static BAR_BACKGROUND_COLOR: Color = Color { r: 60, g: 60, b: 60, a: 255 };
+static BARS_X: i32 = 5;
+static BARS_Y: i32 = 100;
+static BAR_WIDTH: u32 = 55;
+static BAR_HEIGHT: u32 = 6;
+static BAR_SPACING: u32 = 4;
+
+/// Whether to host a race or join one, as given on the command line.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Role {
+    Host(u16),
+    Join(String),
+}
+
+impl Role {
+    /// Reads `--host [port]` or `--join <host:port>` from the command line arguments.
+    pub fn from_args<I>(mut args: I) -> Result<Option<Role>, &'static str>
+    where
+        I: Iterator<Item = String>,
+    {
+        let role = match args.next().as_deref() {
+            None => return Ok(None),
+            Some("--host") => match args.next() {
+                Some(port) => Role::Host(port.parse().map_err(|_| "invalid port to host on")?),
+                None => Role::Host(DEFAULT_PORT),
+            },
+            Some("--join") => Role::Join(args.next().ok_or("missing address to join")?),
+            Some(_) => return Err("unknown argument, use --host [port] or --join <host:port>"),
+        };
+
+        if args.next().is_some() {
+            return Err("too many arguments");
+        }
+        Ok(Some(role))
+    }
+}
+
+enum Link {
+    Host(Host),
+    Player(Connection),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Progress {
+    tiles_left: usize,
+    time: Duration,
+}
+
+/**
+ * Players on a local network race to clear the same deal, each on their own board. One player
+ * hosts and starts each race with the seed of the deal, while the tiles left and the time of
+ * every player are shown to the others as progress bars. Without a network the race is played
+ * alone against the clock.
+ */
+pub struct Race {
+    board: Board,
+    link: Option<Link>,
+    player: usize,
+    seed: Option<u64>,
+    progress: Vec<Option<Progress>>,
+    start_time: Instant,
+    finish_time: Option<Duration>,
+    last_sent: Instant,
+    status: Option<String>,
+    scale: Scale,
+}
+
+impl Race {
+    pub fn new() -> Self {
+        let mut race = Race {
+            board: Board::new(),
+            link: None,
+            player: 0,
+            seed: None,
+            progress: Vec::new(),
+            start_time: Instant::now(),
+            finish_time: None,
+            last_sent: Instant::now(),
+            status: None,
+            scale: Scale::default(),
+        };
+        race.start(rand::random(), 1);
+        race
+    }
+
+    /// Returns where the progress bar of the player is drawn, below the buttons.
+    fn bar_rect(&self, player: usize) -> Rect {
+        let y = BARS_Y + (player as u32 * (BAR_HEIGHT + BAR_SPACING)) as i32;
+        self.scale.rect(Rect::new(BARS_X, y, BAR_WIDTH, BAR_HEIGHT))
+    }
+
+    pub fn connect(&mut self, role: &Role) {
+        let link = match role {
+            Role::Host(port) => net::Host::bind(("0.0.0.0", *port)).map(Link::Host),
+            Role::Join(address) => net::join(address.as_str()).map(Link::Player),
+        };
+
+        match link {
+            Ok(link) => {
+                self.status = None;
+                if let Link::Player(_) = link {
+                    // the host sends the deal to race on
+                    self.player = 0;
+                    self.seed = None;
+                    self.progress = Vec::new();
+                }
+                self.link = Some(link);
+            }
+            Err(err) => self.status = Some(format!("network error: {}", err)),
+        }
+    }
+
+    fn start(&mut self, seed: u64, players: usize) {
+        if let Err(err) = self.board.reset_with_seed(seed) {
+            self.status = Some(err.to_string());
+            return;
+        }
+        self.seed = Some(seed);
+        self.progress = vec![None; players.max(self.player + 1)];
+        self.start_time = Instant::now();
+        self.finish_time = None;
+        self.send_progress();
+    }
+
+    fn time(&self) -> Duration {
+        self.finish_time.unwrap_or_else(|| self.start_time.elapsed())
+    }
+
+    fn send_progress(&mut self) {
+        let progress = Progress {
+            tiles_left: self.board.tiles_left(),
+            time: self.time(),
+        };
+        self.progress[self.player] = Some(progress);
+        self.last_sent = Instant::now();
+
+        let message = Message::Progress {
+            player: self.player,
+            tiles_left: progress.tiles_left,
+            millis: progress.time.as_millis() as u64,
+        };
+        let sent = match self.link.as_mut() {
+            Some(Link::Host(host)) => {
+                host.broadcast(message);
+                Ok(())
+            }
+            Some(Link::Player(connection)) => connection.send(message),
+            None => Ok(()),
+        };
+        if sent.is_err() {
+            self.lose_connection();
+        }
+    }
+
+    /// Handles the messages that arrived, and returns whether anything did.
+    fn receive(&mut self) -> bool {
+        let messages = match self.link.as_mut() {
+            Some(Link::Host(host)) => host.poll(),
+            Some(Link::Player(connection)) => match connection.receive() {
+                Ok(messages) => messages,
+                Err(_) => {
+                    self.lose_connection();
+                    return true;
+                }
+            },
+            None => return false,
+        };
+        let received = !messages.is_empty();
+
+        for message in messages {
+            match message {
+                Message::Welcome { player } => self.player = player,
+                Message::Start { seed, players } => self.start(seed, players),
+                // the progress of others is only shown, but not trusted to fit on the board
+                Message::Progress { tiles_left, .. } if tiles_left > self.board.num_tiles() => (),
+                Message::Progress { player, tiles_left, millis } => {
+                    if player >= self.progress.len() {
+                        self.progress.resize(player + 1, None);
+                    }
+                    self.progress[player] = Some(Progress {
+                        tiles_left,
+                        time: Duration::from_millis(millis),
+                    });
+                }
+            }
+        }
+        received
+    }
+
+    fn lose_connection(&mut self) {
+        self.link = None;
+        self.status = Some("lost the connection to the host".to_string());
+    }
+
+    fn is_racing(&self) -> bool {
+        self.seed.is_some()
+    }
+
+    /// Plays on the board and lets the others know when the tiles left changed.
+    fn play<F>(&mut self, play: F) -> Result<(), GameOver>
+    where
+        F: FnOnce(&mut Board) -> Result<(), GameOver>,
+    {
+        if !self.is_racing() || self.finish_time.is_some() {
+            return Ok(());
+        }
+
+        let tiles_left = self.board.tiles_left();
+        let result = play(&mut self.board);
+        if result == Err(GameOver::Won) {
+            self.finish_time = Some(self.start_time.elapsed());
+        }
+        if self.board.tiles_left() != tiles_left {
+            self.send_progress();
+        }
+        result
+    }
+
+    fn describe_link(&self) -> String {
+        match &self.link {
+            Some(Link::Host(host)) => {
+                let port = host.local_port().unwrap_or(DEFAULT_PORT);
+                format!("hosting on port {} for {} players", port, host.num_players())
+            }
+            Some(Link::Player(_)) => format!("player {}", self.player + 1),
+            None => "alone - C to host, J to join".to_string(),
+        }
+    }
+}
+
+impl Default for Race {
+    fn default() -> Self {
+        Race::new()
+    }
+}
+
+impl Game for Race {
+    fn title(&self) -> String {
+        let state = match (self.is_racing(), self.finish_time) {
+            (false, _) => "waiting for the host to start".to_string(),
+            (true, Some(time)) => format!("finished in {}s", time.as_secs()),
+            (true, None) => format!(
+                "{} tiles left - {}s",
+                self.board.tiles_left(),
+                self.time().as_secs(),
+            ),
+        };
+        let status = match &self.status {
+            Some(status) => format!(" - {}", status),
+            None => String::new(),
+        };
+
+        format!("Race - {} - {}{}", self.describe_link(), state, status)
+    }
+
+    /// The host starts a new race for everyone, a player that joined starts the same deal over
+    /// while the clock keeps running.
+    fn reset(&mut self) {
+        match &mut self.link {
+            Some(Link::Host(host)) => {
+                let seed = rand::random();
+                let players = host.num_players();
+                host.broadcast(Message::Start { seed, players });
+                self.start(seed, players);
+            }
+            Some(Link::Player(_)) => {
+                if let (Some(seed), None) = (self.seed, self.finish_time) {
+                    if self.board.reset_with_seed(seed).is_ok() {
+                        self.send_progress();
+                    }
+                }
+            }
+            None => self.start(rand::random(), 1),
+        }
+    }
+
+    fn undo(&mut self) {
+        let _ = self.play(|board| {
+            board.undo();
+            Ok(())
+        });
+    }
+
+    fn hint(&mut self) {
+        self.board.highlight_possible_matches();
+    }
+
+    fn click(&mut self, x: i32, y: i32) -> Result<(), GameOver> {
+        self.play(|board| board.try_select_tile(x, y))
+    }
+
+    fn key(&mut self, keycode: Keycode) -> bool {
+        match keycode {
+            Keycode::C => self.connect(&Role::Host(DEFAULT_PORT)),
+            Keycode::J => self.connect(&Role::Join(format!("127.0.0.1:{}", DEFAULT_PORT))),
+            _ => return false,
+        }
+        true
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.board.fit_canvas(width, height);
+        self.scale = Scale::of_canvas(width, height);
+    }
+
+    fn camera(&mut self) -> Option<&mut Camera> {
+        Some(self.board.camera_mut())
+    }
+
+    fn set_view_angle(&mut self, angle: ViewAngle) {
+        self.board.set_view_angle(angle);
+    }
+
+    fn set_tile_size(&mut self, tile_size: TileSize) {
+        self.board.set_tile_size(tile_size);
+    }
+
+    fn update(&mut self) -> Result<bool, GameOver> {
+        let changed = self.board.update();
+        let received = self.receive();
+
+        let racing = self.is_racing() && self.finish_time.is_none();
+        if racing && self.last_sent.elapsed() >= PROGRESS_INTERVAL {
+            self.send_progress();
+        }
+        Ok(changed || received)
+    }
+
+    fn is_animating(&self) -> bool {
+        self.board.is_animating()
+    }
+
+    fn summary(&self) -> Option<String> {
+        let lines = self
+            .progress
+            .iter()
+            .enumerate()
+            .filter_map(|(player, progress)| progress.map(|progress| (player, progress)))
+            .map(|(player, progress)| {
+                let name = if player == self.player {
+                    "You".to_string()
+                } else {
+                    format!("Player {}", player + 1)
+                };
+                match progress.tiles_left {
+                    0 => format!("{}: finished in {}s", name, progress.time.as_secs()),
+                    tiles_left => format!("{}: {} tiles left", name, tiles_left),
+                }
+            }).collect::<Vec<_>>();
+
+        Some(lines.join("\n"))
+    }
+
+    fn render(&self, canvas: &mut WindowCanvas, textures: &TileTextures) {
+        self.board.render(canvas, textures);
+
+        // a bar for every player, filled as far as the player cleared the board
+        let num_tiles = self.board.num_tiles();
+        for (index, progress) in self.progress.iter().enumerate() {
+            let bar = self.bar_rect(index);
+            canvas.set_draw_color(BAR_BACKGROUND_COLOR);
+            let _ = canvas.fill_rect(bar);
+
+            let progress = match progress {
+                Some(progress) => progress,
+                None => continue,
+            };
+            let color = match progress.tiles_left {
+                0 => FINISHED_COLOR,
+                _ if index == self.player => OWN_COLOR,
+                _ => OPPONENT_COLOR,
+            };
+            let cleared = (num_tiles - progress.tiles_left) as u32;
+            let width = bar.width() * cleared / num_tiles as u32;
+            if width > 0 {
+                canvas.set_draw_color(color);
+                let _ = canvas.fill_rect(Rect::new(bar.x(), bar.y(), width, bar.height()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::{
+            thread,
+            time::{
+                Duration,
+                Instant,
+            },
+        },
+        crate::game::Game,
+        super::{
+            net::{
+                self,
+                Message,
+            },
+            Link,
+            Race,
+            Role,
+        },
+    };
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    /// Updates both races until the condition holds, as messages on loopback take a moment.
+    fn update_until<F: Fn(&Race, &Race) -> bool>(host: &mut Race, player: &mut Race, done: F) {
+        let start = Instant::now();
+        while !done(host, player) {
+            assert!(start.elapsed() < Duration::from_secs(2), "races did not get there");
+            host.update().unwrap();
+            player.update().unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn progress_bars_grow_with_the_canvas() {
+        let mut race = Race::new();
+        race.resize(2780, 1872);
+
+        // the bars stay below the buttons, which end 90 high on the smallest canvas
+        let (first, second) = (race.bar_rect(0), race.bar_rect(1));
+        assert!(first.y() > 90 * 1872 / 500);
+        assert!(second.y() > first.bottom());
+        assert_eq!(first.width(), (55.0 * 1872.0 / 500.0_f64).round() as u32);
+    }
+
+    #[test]
+    fn roles_are_read_from_arguments() {
+        assert_eq!(Role::from_args(args(&[])), Ok(None));
+        assert_eq!(Role::from_args(args(&["--host"])), Ok(Some(Role::Host(7878))));
+        assert_eq!(Role::from_args(args(&["--host", "9000"])), Ok(Some(Role::Host(9000))));
+        assert_eq!(
+            Role::from_args(args(&["--join", "10.0.0.2:7878"])),
+            Ok(Some(Role::Join("10.0.0.2:7878".to_string()))),
+        );
+        assert!(Role::from_args(args(&["--join"])).is_err());
+        assert!(Role::from_args(args(&["--host", "port"])).is_err());
+        assert!(Role::from_args(args(&["--race"])).is_err());
+    }
+
+    #[test]
+    fn players_race_on_the_same_deal() {
+        let mut host = Race::new();
+        host.connect(&Role::Host(0));
+        let port = match &host.link {
+            Some(Link::Host(link)) => link.local_port().unwrap(),
+            _ => panic!("not hosting: {:?}", host.status),
+        };
+
+        let mut player = Race::new();
+        player.connect(&Role::Join(format!("127.0.0.1:{}", port)));
+        assert!(!player.is_racing());
+        update_until(&mut host, &mut player, |_, player| player.player == 1);
+
+        host.reset();
+        update_until(&mut host, &mut player, |_, player| player.is_racing());
+        assert_eq!(player.seed, host.seed);
+
+        // both boards are full, and each side sees the progress of the other
+        update_until(&mut host, &mut player, |host, player| {
+            host.progress.get(1).is_some_and(Option::is_some) && player.progress[0].is_some()
+        });
+        assert_eq!(host.progress[1].unwrap().tiles_left, 144);
+        assert_eq!(player.progress[0].unwrap().tiles_left, 144);
+    }
+
+    #[test]
+    fn forged_progress_is_dropped_and_the_player_kept() {
+        let mut host = Race::new();
+        host.connect(&Role::Host(0));
+        let port = match &host.link {
+            Some(Link::Host(link)) => link.local_port().unwrap(),
+            _ => panic!("not hosting: {:?}", host.status),
+        };
+        let num_players = |race: &Race| match &race.link {
+            Some(Link::Host(link)) => link.num_players(),
+            _ => 0,
+        };
+
+        let mut forger = net::join(("127.0.0.1", port)).unwrap();
+        let start = Instant::now();
+        while num_players(&host) < 2 {
+            assert!(start.elapsed() < Duration::from_secs(2), "player was not accepted");
+            host.update().unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        forger.send(Message::Progress { player: 1, tiles_left: usize::MAX, millis: 0 }).unwrap();
+        forger.send(Message::Progress { player: 100, tiles_left: 10, millis: 0 }).unwrap();
+        forger.send(Message::Progress { player: 1, tiles_left: 100, millis: 0 }).unwrap();
+
+        while host.progress.get(1).is_none_or(Option::is_none) {
+            assert!(start.elapsed() < Duration::from_secs(4), "progress did not arrive");
+            host.update().unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(host.progress[1].unwrap().tiles_left, 100);
+        assert_eq!(host.progress.len(), 2);
+        assert_eq!(num_players(&host), 2);
+    }
+}
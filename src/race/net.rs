@@ -0,0 +1,483 @@
+use {
+    std::{
+        io::{
+            self,
+            ErrorKind,
+            Read,
+            Write,
+        },
+        net::{
+            TcpListener,
+            TcpStream,
+            ToSocketAddrs,
+        },
+        time::Duration,
+    },
+};
+
+static CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The most players in a race, the host included.
+pub static MAX_PLAYERS: usize = 8;
+
+/// Longer than any message, so a longer line means the other side doesn't speak the protocol.
+static MAX_LINE_LENGTH: usize = 64;
+
+/**
+ * The messages of the race protocol, each sent as a line of text. A player that joins gets its
+ * player number from the host, the host starts a race by sending the seed of the deal to every
+ * player, and every player sends its progress to the host which passes it on to the others.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Message {
+    Welcome { player: usize },
+    Start { seed: u64, players: usize },
+    Progress { player: usize, tiles_left: usize, millis: u64 },
+}
+
+impl Message {
+    pub fn to_line(self) -> String {
+        match self {
+            Message::Welcome { player } => format!("WELCOME {}\n", player),
+            Message::Start { seed, players } => format!("START {} {}\n", seed, players),
+            Message::Progress { player, tiles_left, millis } => {
+                format!("PROGRESS {} {} {}\n", player, tiles_left, millis)
+            }
+        }
+    }
+
+    pub fn parse(line: &str) -> Result<Message, &'static str> {
+        let mut words = line.split_whitespace();
+        let command = words.next().ok_or("empty message")?;
+        let mut number = || -> Result<u64, &'static str> {
+            let word = words.next().ok_or("missing number in message")?;
+            word.parse().map_err(|_| "invalid number in message")
+        };
+
+        let message = match command {
+            "WELCOME" => Message::Welcome { player: player(number()?)? },
+            "START" => Message::Start { seed: number()?, players: players(number()?)? },
+            "PROGRESS" => Message::Progress {
+                player: player(number()?)?,
+                tiles_left: number()? as usize,
+                millis: number()?,
+            },
+            _ => return Err("unknown message"),
+        };
+        Ok(message)
+    }
+}
+
+fn player(number: u64) -> Result<usize, &'static str> {
+    match number {
+        player if player < MAX_PLAYERS as u64 => Ok(player as usize),
+        _ => Err("invalid player in message"),
+    }
+}
+
+fn players(number: u64) -> Result<usize, &'static str> {
+    match number {
+        players if (1..=MAX_PLAYERS as u64).contains(&players) => Ok(players as usize),
+        _ => Err("invalid number of players in message"),
+    }
+}
+
+/**
+ * A non-blocking connection that sends and receives whole messages. What the stream doesn't
+ * take right away is queued and sent on the next send or receive, so a message is never cut
+ * short.
+ */
+pub struct Connection {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    outgoing: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+
+        Ok(Connection {
+            stream,
+            buffer: Vec::new(),
+            outgoing: Vec::new(),
+        })
+    }
+
+    pub fn send(&mut self, message: Message) -> io::Result<()> {
+        self.outgoing.extend_from_slice(message.to_line().as_bytes());
+        self.flush()
+    }
+
+    /// Sends as much of the queued messages as the stream takes without waiting.
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(len) => {
+                    self.outgoing.drain(..len);
+                }
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the messages that arrived since the last call, without waiting for more. Lines
+    /// that are no valid message are dropped, while a line longer than any message fails.
+    pub fn receive(&mut self) -> io::Result<Vec<Message>> {
+        self.flush()?;
+
+        let mut messages = Vec::new();
+        let mut chunk = [0; 512];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(ErrorKind::ConnectionAborted.into()),
+                Ok(len) => {
+                    self.buffer.extend_from_slice(&chunk[..len]);
+                    self.take_messages(&mut messages)?;
+                }
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Takes the whole lines out of the buffer, so it never holds more than one line.
+    fn take_messages(&mut self, messages: &mut Vec<Message>) -> io::Result<()> {
+        let too_long = || io::Error::new(ErrorKind::InvalidData, "line too long for a message");
+
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            if end > MAX_LINE_LENGTH {
+                return Err(too_long());
+            }
+            let line = self.buffer.drain(..=end).collect::<Vec<_>>();
+            if let Ok(message) = Message::parse(&String::from_utf8_lossy(&line)) {
+                messages.push(message);
+            }
+        }
+        if self.buffer.len() > MAX_LINE_LENGTH {
+            return Err(too_long());
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Accepts players and passes the progress of each player on to the others. The host itself is
+ * player 0, and a player that joins gets the lowest number no one else has. Players only send
+ * their progress, which is passed on under the number of the connection it came in on. Players
+ * beyond `MAX_PLAYERS` are turned away.
+ */
+pub struct Host {
+    listener: TcpListener,
+    players: Vec<(usize, Connection)>,
+}
+
+impl Host {
+    pub fn bind<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Host {
+            listener,
+            players: Vec::new(),
+        })
+    }
+
+    pub fn local_port(&self) -> io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    /// The number of players including the host, which is one more than the highest player
+    /// number in use.
+    pub fn num_players(&self) -> usize {
+        self.players.iter().map(|&(player, _)| player + 1).max().unwrap_or(1)
+    }
+
+    fn free_player(&self) -> Option<usize> {
+        (1..MAX_PLAYERS).find(|&player| self.players.iter().all(|&(other, _)| other != player))
+    }
+
+    /// Accepts new players and returns the progress other players sent. Players whose connection
+    /// fails are dropped.
+    pub fn poll(&mut self) -> Vec<Message> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    let player = match self.free_player() {
+                        Some(player) => player,
+                        None => continue,
+                    };
+                    let welcomed = Connection::new(stream).and_then(|mut connection| {
+                        connection.send(Message::Welcome { player })?;
+                        Ok(connection)
+                    });
+                    if let Ok(connection) = welcomed {
+                        self.players.push((player, connection));
+                    }
+                }
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => break,
+            }
+        }
+
+        let mut received = Vec::new();
+        self.players.retain_mut(|&mut (sender, ref mut connection)| match connection.receive() {
+            Ok(messages) => {
+                received.extend(messages.into_iter().filter_map(|message| match message {
+                    Message::Progress { tiles_left, millis, .. } => {
+                        Some(Message::Progress { player: sender, tiles_left, millis })
+                    }
+                    _ => None,
+                }));
+                true
+            }
+            Err(_) => false,
+        });
+
+        for &message in &received {
+            if let Message::Progress { player, .. } = message {
+                self.send_to_all_but(player, message);
+            }
+        }
+        received
+    }
+
+    pub fn broadcast(&mut self, message: Message) {
+        self.send_to_all_but(0, message);
+    }
+
+    fn send_to_all_but(&mut self, sender: usize, message: Message) {
+        self.players.retain_mut(|(player, connection)| {
+            *player == sender || connection.send(message).is_ok()
+        });
+    }
+}
+
+/// Connects to a host.
+pub fn join<A: ToSocketAddrs>(address: A) -> io::Result<Connection> {
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "no address to join"))?;
+
+    Connection::new(TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::{
+            io::Write,
+            thread,
+            time::{
+                Duration,
+                Instant,
+            },
+        },
+        super::{
+            join,
+            Connection,
+            Host,
+            Message,
+        },
+    };
+
+    /// Polls until a message arrives, as messages on loopback still take a moment.
+    fn receive_one(connection: &mut Connection) -> Message {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(2) {
+            if let Some(&message) = connection.receive().unwrap().first() {
+                return message;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        panic!("no message arrived");
+    }
+
+    fn poll_one(host: &mut Host) -> Message {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(2) {
+            if let Some(&message) = host.poll().first() {
+                return message;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        panic!("no message arrived");
+    }
+
+    fn join_host(host: &mut Host) -> Connection {
+        let players = host.num_players();
+        let connection = join(("127.0.0.1", host.local_port().unwrap())).unwrap();
+
+        let start = Instant::now();
+        while host.num_players() == players {
+            assert!(start.elapsed() < Duration::from_secs(2), "player was not accepted");
+            host.poll();
+            thread::sleep(Duration::from_millis(5));
+        }
+        connection
+    }
+
+    #[test]
+    fn messages_survive_a_round_trip() {
+        let messages = [
+            Message::Welcome { player: 2 },
+            Message::Start { seed: u64::MAX, players: 3 },
+            Message::Progress { player: 1, tiles_left: 120, millis: 34_567 },
+        ];
+
+        for &message in &messages {
+            assert_eq!(Message::parse(&message.to_line()), Ok(message));
+        }
+        assert!(Message::parse("START 12").is_err());
+        assert!(Message::parse("HELLO").is_err());
+    }
+
+    #[test]
+    fn player_numbers_are_capped() {
+        assert!(Message::parse("WELCOME 7").is_ok());
+        assert!(Message::parse("WELCOME 8").is_err());
+        assert!(Message::parse("PROGRESS 18446744073709551615 100 0").is_err());
+        assert!(Message::parse("START 1 8").is_ok());
+        assert!(Message::parse("START 1 9").is_err());
+        assert!(Message::parse("START 1 0").is_err());
+    }
+
+    #[test]
+    fn players_are_welcomed_and_started() {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+
+        let mut player1 = join_host(&mut host);
+        let mut player2 = join_host(&mut host);
+        assert_eq!(receive_one(&mut player1), Message::Welcome { player: 1 });
+        assert_eq!(receive_one(&mut player2), Message::Welcome { player: 2 });
+
+        let start = Message::Start { seed: 42, players: host.num_players() };
+        host.broadcast(start);
+        assert_eq!(receive_one(&mut player1), start);
+        assert_eq!(receive_one(&mut player2), start);
+    }
+
+    #[test]
+    fn progress_is_passed_on_to_other_players() {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        let mut player1 = join_host(&mut host);
+        let mut player2 = join_host(&mut host);
+        receive_one(&mut player1);
+        receive_one(&mut player2);
+
+        let progress = Message::Progress { player: 1, tiles_left: 100, millis: 5000 };
+        player1.send(progress).unwrap();
+
+        assert_eq!(poll_one(&mut host), progress);
+        assert_eq!(receive_one(&mut player2), progress);
+        assert!(player1.receive().unwrap().is_empty());
+    }
+
+    #[test]
+    fn progress_is_passed_on_under_the_number_of_its_sender() {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        let mut player1 = join_host(&mut host);
+        let mut player2 = join_host(&mut host);
+        receive_one(&mut player1);
+        receive_one(&mut player2);
+
+        player1.send(Message::Progress { player: 2, tiles_left: 90, millis: 100 }).unwrap();
+        player1.send(Message::Start { seed: 1, players: 5 }).unwrap();
+
+        let progress = Message::Progress { player: 1, tiles_left: 90, millis: 100 };
+        assert_eq!(poll_one(&mut host), progress);
+        assert_eq!(receive_one(&mut player2), progress);
+        thread::sleep(Duration::from_millis(50));
+        assert!(host.poll().is_empty());
+    }
+
+    #[test]
+    fn bad_lines_are_dropped_and_the_connection_kept() {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        let mut player = join_host(&mut host);
+        receive_one(&mut player);
+
+        player.stream.write_all(b"HELLO\nPROGRESS 9 100 5\n").unwrap();
+        let progress = Message::Progress { player: 1, tiles_left: 100, millis: 5000 };
+        player.send(progress).unwrap();
+
+        assert_eq!(poll_one(&mut host), progress);
+        assert_eq!(host.num_players(), 2);
+    }
+
+    #[test]
+    fn overlong_lines_drop_the_connection() {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        let mut player = join_host(&mut host);
+        receive_one(&mut player);
+
+        player.stream.write_all(&[b'1'; 4096]).unwrap();
+
+        let start = Instant::now();
+        while !host.players.is_empty() {
+            assert!(start.elapsed() < Duration::from_secs(2), "player was not dropped");
+            host.poll();
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn players_that_leave_free_their_number() {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        let player1 = join_host(&mut host);
+        let mut player2 = join_host(&mut host);
+        receive_one(&mut player2);
+        drop(player1);
+
+        let start = Instant::now();
+        while host.players.len() == 2 {
+            assert!(start.elapsed() < Duration::from_secs(2), "player was not dropped");
+            host.poll();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(host.num_players(), 3);
+
+        let mut player3 = join(("127.0.0.1", host.local_port().unwrap())).unwrap();
+        while host.players.len() == 1 {
+            assert!(start.elapsed() < Duration::from_secs(4), "player was not accepted");
+            host.poll();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(receive_one(&mut player3), Message::Welcome { player: 1 });
+        assert_eq!(host.num_players(), 3);
+    }
+
+    #[test]
+    fn messages_wait_for_slow_players() {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        let mut player = join_host(&mut host);
+        receive_one(&mut player);
+
+        // far more than the socket buffers hold while the player doesn't read
+        let count = 100_000;
+        for millis in 0..count {
+            host.broadcast(Message::Progress { player: 0, tiles_left: 144, millis });
+        }
+        assert_eq!(host.num_players(), 2);
+
+        let mut received = 0;
+        let start = Instant::now();
+        while received < count {
+            assert!(start.elapsed() < Duration::from_secs(10), "messages were lost");
+            host.poll();
+            for message in player.receive().unwrap() {
+                let expected = Message::Progress { player: 0, tiles_left: 144, millis: received };
+                assert_eq!(message, expected);
+                received += 1;
+            }
+        }
+    }
+}
@@ -1,11 +1,15 @@
-use sdl2::{
-    EventPump,
-    image::INIT_PNG,
-    render::{
-        TextureCreator,
-        WindowCanvas,
+use {
+    sdl2::{
+        event::Event,
+        image::INIT_PNG,
+        render::{
+            TextureCreator,
+            WindowCanvas,
+        },
+        video::WindowContext,
+        EventPump,
     },
-    video::WindowContext,
+    crate::game::MIN_CANVAS,
 };
 
 pub struct SdlContext {
@@ -23,17 +27,17 @@ pub fn init() -> SdlContext {
         .window("Mahjong", 1080, 750)
         .maximized()
         .resizable()
+        .allow_highdpi()
         .build()
         .expect("error creating window");
 
-    window.set_minimum_size(730, 500).unwrap();
+    window.set_minimum_size(MIN_CANVAS.0, MIN_CANVAS.1).unwrap();
 
-    let mut canvas = window
+    let canvas = window
         .into_canvas()
         .present_vsync()
         .build()
         .expect("error creating window canvas");
-    canvas.set_logical_size(730, 500).unwrap();
 
     let texture_creator = canvas.texture_creator();
 
@@ -45,3 +49,78 @@ pub fn init() -> SdlContext {
         event_pump,
     }
 }
+
+/// Returns the size of the canvas, which is the size of the window in pixels, so high DPI
+/// displays get sharp tiles.
+pub fn canvas_size(canvas: &WindowCanvas) -> (u32, u32) {
+    canvas.window().drawable_size()
+}
+
+/// Returns the largest texture the renderer takes, where a renderer that doesn't tell has no limit.
+pub fn max_texture_size(canvas: &WindowCanvas) -> (u32, u32) {
+    let info = canvas.info();
+    let limit = |size: u32| if size == 0 { u32::MAX } else { size };
+    (limit(info.max_texture_width), limit(info.max_texture_height))
+}
+
+/// Returns how many pixels of the canvas there are to a point of the window in each direction,
+/// which mouse positions are given in.
+pub fn pointer_scale(canvas: &WindowCanvas) -> (f64, f64) {
+    let (width, height) = canvas.window().size();
+    let (canvas_width, canvas_height) = canvas_size(canvas);
+    (
+        f64::from(canvas_width) / f64::from(width.max(1)),
+        f64::from(canvas_height) / f64::from(height.max(1)),
+    )
+}
+
+/// Moves the positions of mouse events from the window to the canvas.
+pub fn to_canvas(event: Event, (scale_x, scale_y): (f64, f64)) -> Event {
+    let to_x = |x: i32| (f64::from(x) * scale_x).round() as i32;
+    let to_y = |y: i32| (f64::from(y) * scale_y).round() as i32;
+    match event {
+        Event::MouseButtonDown { timestamp, window_id, which, mouse_btn, clicks, x, y } => {
+            let (x, y) = (to_x(x), to_y(y));
+            Event::MouseButtonDown { timestamp, window_id, which, mouse_btn, clicks, x, y }
+        }
+        Event::MouseButtonUp { timestamp, window_id, which, mouse_btn, clicks, x, y } => {
+            let (x, y) = (to_x(x), to_y(y));
+            Event::MouseButtonUp { timestamp, window_id, which, mouse_btn, clicks, x, y }
+        }
+        Event::MouseMotion { timestamp, window_id, which, mousestate, x, y, xrel, yrel } => {
+            let (x, y, xrel, yrel) = (to_x(x), to_y(y), to_x(xrel), to_y(yrel));
+            Event::MouseMotion { timestamp, window_id, which, mousestate, x, y, xrel, yrel }
+        }
+        event => event,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        sdl2::{
+            event::Event,
+            mouse::MouseButton,
+        },
+        super::to_canvas,
+    };
+
+    #[test]
+    fn mouse_positions_are_moved_to_the_canvas() {
+        let click = |x, y| Event::MouseButtonUp {
+            timestamp: 0,
+            window_id: 1,
+            which: 0,
+            mouse_btn: MouseButton::Left,
+            clicks: 1,
+            x,
+            y,
+        };
+
+        // a window of 1390 by 936 points on a display with two pixels to a point
+        assert_eq!(to_canvas(click(1389, 935), (2.0, 2.0)), click(2778, 1870));
+        assert_eq!(to_canvas(click(100, 50), (1.0, 1.0)), click(100, 50));
+        let quit = Event::Quit { timestamp: 0 };
+        assert_eq!(to_canvas(quit.clone(), (2.0, 2.0)), quit);
+    }
+}
@@ -0,0 +1,355 @@
+mod deal;
+mod grid;
+
+use {
+    std::time::{
+        Duration,
+        Instant,
+    },
+    rand::{
+        rngs::SmallRng,
+        FromEntropy,
+    },
+    sdl2::{
+        keyboard::Keycode,
+        pixels::Color,
+        rect::Rect,
+        render::WindowCanvas,
+    },
+    crate::{
+        board::{
+            next_tile_set,
+            Position,
+            TileSet,
+            TileTextures,
+        },
+        game::{
+            Game,
+            GameOver,
+            Scale,
+            MIN_CANVAS,
+        },
+    },
+    self::{
+        deal::deal,
+        grid::{
+            Gravity,
+            Grid,
+            PathPoint,
+            HEIGHT,
+            WIDTH,
+        },
+    },
+};
+
+static TILE_WIDTH: u32 = 31;
+static TILE_HEIGHT: u32 = 39;
+static TILE_SIDE_WIDTH: u32 = 3;
+static TILE_BOTTOM_HEIGHT: u32 = 3;
+
+static PATH_DURATION: Duration = Duration::from_millis(400);
+
+/// Shisen-Sho: tiles lie on a flat grid and a matching pair can be removed when a path with at
+/// most two turns connects them through empty cells.
+pub struct Shisen {
+    grid: Grid,
+    tile_set: TileSet,
+    gravity: Gravity,
+    played: Vec<Grid>,
+    selected_tile: Option<Position>,
+    hints: Vec<(Position, Position)>,
+    hint_index: Option<usize>,
+    path: Option<(Vec<PathPoint>, Instant)>,
+    rng: SmallRng,
+    scale: Scale,
+    /// Where the top left cell of the grid is drawn, the grid is centered on the canvas.
+    grid_origin: (i32, i32),
+}
+
+impl Shisen {
+    pub fn new() -> Self {
+        let tile_set = TileSet::default();
+        let gravity = Gravity::default();
+        let mut rng = SmallRng::from_entropy();
+        let grid = deal(&tile_set, WIDTH, HEIGHT, gravity, &mut rng).expect("no deal found");
+
+        let mut shisen = Shisen {
+            grid,
+            tile_set,
+            gravity,
+            played: Vec::new(),
+            selected_tile: None,
+            hints: Vec::new(),
+            hint_index: None,
+            path: None,
+            rng,
+            scale: Scale::default(),
+            grid_origin: (0, 0),
+        };
+        shisen.resize(MIN_CANVAS.0, MIN_CANVAS.1);
+        shisen
+    }
+
+    /// Returns where the face of the tile in the cell is drawn, cells outside of the grid are
+    /// where paths go around it.
+    fn cell_rect(&self, x: i32, y: i32) -> Rect {
+        let (width, height) = (self.scale.size(TILE_WIDTH), self.scale.size(TILE_HEIGHT));
+        Rect::new(
+            self.grid_origin.0 + x * width as i32,
+            self.grid_origin.1 + y * height as i32,
+            width,
+            height,
+        )
+    }
+
+    fn set_gravity(&mut self, gravity: Gravity) -> Result<(), &'static str> {
+        self.grid = deal(&self.tile_set, WIDTH, HEIGHT, gravity, &mut self.rng)?;
+        self.gravity = gravity;
+        self.start();
+        Ok(())
+    }
+
+    fn set_tile_set(&mut self, tile_set: TileSet) -> Result<(), &'static str> {
+        self.grid = deal(&tile_set, WIDTH, HEIGHT, self.gravity, &mut self.rng)?;
+        self.tile_set = tile_set;
+        self.start();
+        Ok(())
+    }
+
+    /// Starts playing on the grid that was just dealt.
+    fn start(&mut self) {
+        self.played = Vec::new();
+        self.selected_tile = None;
+        self.path = None;
+        self.stop_hints();
+    }
+
+    fn stop_hints(&mut self) {
+        self.hints = Vec::new();
+        self.hint_index = None;
+    }
+
+    fn is_highlighted(&self, tile: Position) -> bool {
+        if self.selected_tile == Some(tile) {
+            return true;
+        }
+
+        self.hint_index
+            .map(|index| self.hints[index])
+            .is_some_and(|(tile1, tile2)| tile == tile1 || tile == tile2)
+    }
+
+    fn find_tile_by_coord(&self, x: i32, y: i32) -> Option<Position> {
+        let (grid_x, grid_y) = self.grid_origin;
+        if x < grid_x || y < grid_y {
+            return None;
+        }
+
+        let cell = self.cell_rect(0, 0);
+        let tile_x = (x - grid_x) / cell.width() as i32;
+        let tile_y = (y - grid_y) / cell.height() as i32;
+        if tile_x >= i32::from(self.grid.width()) || tile_y >= i32::from(self.grid.height()) {
+            return None;
+        }
+
+        let position = Position { x: tile_x as u8, y: tile_y as u8, z: 0 };
+        self.grid.get(position).map(|_| position)
+    }
+
+    fn render_path(&self, canvas: &mut WindowCanvas) {
+        if let Some((path, _)) = &self.path {
+            let points = path
+                .iter()
+                .map(|&(x, y)| self.cell_rect(x, y).center())
+                .collect::<Vec<_>>();
+
+            canvas.set_draw_color(Color::RGB(255, 127, 127));
+            let _ = canvas.draw_lines(points.as_slice());
+        }
+    }
+}
+
+impl Default for Shisen {
+    fn default() -> Self {
+        Shisen::new()
+    }
+}
+
+impl Game for Shisen {
+    fn title(&self) -> String {
+        format!(
+            "Shisen-Sho - {} - {} tiles",
+            self.gravity.name(),
+            self.tile_set.name(),
+        )
+    }
+
+    /// Starts a new game, or keeps the current one when no deal is found.
+    fn reset(&mut self) {
+        if let Ok(grid) = deal(&self.tile_set, WIDTH, HEIGHT, self.gravity, &mut self.rng) {
+            self.grid = grid;
+            self.start();
+        }
+    }
+
+    fn undo(&mut self) {
+        self.selected_tile = None;
+        self.stop_hints();
+
+        if let Some(grid) = self.played.pop() {
+            self.grid = grid;
+        }
+    }
+
+    fn hint(&mut self) {
+        self.selected_tile = None;
+
+        if self.hint_index.is_none() {
+            self.hints = self.grid.available_pairs(&self.tile_set);
+        }
+        if self.hints.is_empty() {
+            return;
+        }
+
+        self.hint_index = Some(self.hint_index.map_or(0, |index| (index + 1) % self.hints.len()));
+    }
+
+    fn click(&mut self, x: i32, y: i32) -> Result<(), GameOver> {
+        self.stop_hints();
+
+        if let Some(tile1) = self.find_tile_by_coord(x, y) {
+            match self.selected_tile {
+                Some(tile2) => {
+                    // deselect tile
+                    if tile1 == tile2 {
+                        self.selected_tile = None;
+                        return Ok(());
+                    }
+
+                    // test tile match
+                    if !self.grid.can_remove(&self.tile_set, tile1, tile2) {
+                        return Ok(());
+                    }
+
+                    // valid match
+                    let path = self.grid.find_path(tile1, tile2);
+                    self.path = path.map(|path| (path, Instant::now()));
+                    self.played.push(self.grid.clone());
+                    self.grid.remove_pair(tile1, tile2, self.gravity);
+                    self.selected_tile = None;
+
+                    if self.grid.is_empty() {
+                        return Err(GameOver::Won);
+                    }
+                    if self.grid.available_pairs(&self.tile_set).is_empty() {
+                        return Err(GameOver::Stuck);
+                    }
+                }
+                None => self.selected_tile = Some(tile1),
+            }
+        }
+        Ok(())
+    }
+
+    fn key(&mut self, keycode: Keycode) -> bool {
+        match keycode {
+            Keycode::G => {
+                let _ = self.set_gravity(self.gravity.next());
+            }
+            Keycode::T => {
+                let _ = self.set_tile_set(next_tile_set(&self.tile_set));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn update(&mut self) -> Result<bool, GameOver> {
+        let expired = self
+            .path
+            .as_ref()
+            .is_some_and(|(_, start_time)| start_time.elapsed() > PATH_DURATION);
+        if expired {
+            self.path = None;
+        }
+        Ok(expired)
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.scale = Scale::of_canvas(width, height);
+        let grid_width = self.scale.size(TILE_WIDTH) * u32::from(WIDTH);
+        let grid_height = self.scale.size(TILE_HEIGHT) * u32::from(HEIGHT);
+        self.grid_origin = (
+            (width as i32 - grid_width as i32) / 2,
+            (height as i32 - grid_height as i32) / 2,
+        );
+    }
+
+    /// The path of the pair just played is shown until it expires.
+    fn is_animating(&self) -> bool {
+        self.path.is_some()
+    }
+
+    fn render(&self, canvas: &mut WindowCanvas, textures: &TileTextures) {
+        let side_width = self.scale.size(TILE_SIDE_WIDTH);
+        let bottom_height = self.scale.size(TILE_BOTTOM_HEIGHT);
+
+        // draw from right to left and top to bottom so the side and bottom of a tile are covered
+        // by its neighbours
+        for y in 0..self.grid.height() {
+            for x in (0..self.grid.width()).rev() {
+                let position = Position { x, y, z: 0 };
+                let tile_type = match self.grid.get(position) {
+                    Some(tile_type) => tile_type,
+                    None => continue,
+                };
+
+                let face = self.cell_rect(i32::from(x), i32::from(y));
+                let side = Rect::new(
+                    face.x() - side_width as i32,
+                    face.y(),
+                    side_width,
+                    face.height() + bottom_height,
+                );
+                let bottom = Rect::new(face.x(), face.bottom(), face.width(), bottom_height);
+
+                textures.side().draw(canvas, side);
+                textures.bottom().draw(canvas, bottom);
+                textures.draw_face(canvas, tile_type, self.is_highlighted(position), face);
+            }
+        }
+
+        self.render_path(canvas);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::game::Game,
+        super::{
+            grid::{
+                HEIGHT,
+                WIDTH,
+            },
+            Position,
+            Shisen,
+        },
+    };
+
+    #[test]
+    fn the_grid_is_centered_on_the_canvas() {
+        let mut shisen = Shisen::new();
+        shisen.resize(2780, 1872);
+
+        let last_cell = (i32::from(WIDTH) - 1, i32::from(HEIGHT) - 1);
+        let (first, last) = (shisen.cell_rect(0, 0), shisen.cell_rect(last_cell.0, last_cell.1));
+        assert!((first.x() + last.right() - 2780).abs() <= 1);
+        assert!((first.y() + last.bottom() - 1872).abs() <= 1);
+
+        let last_tile = Position { x: WIDTH - 1, y: HEIGHT - 1, z: 0 };
+        let center = last.center();
+        assert_eq!(shisen.find_tile_by_coord(center.x(), center.y()), Some(last_tile));
+        assert_eq!(shisen.find_tile_by_coord(last.right() + 1, center.y()), None);
+    }
+}
@@ -0,0 +1,283 @@
+use {
+    rand::{
+        seq::SliceRandom,
+        Rng,
+    },
+    crate::board::{
+        Position,
+        TileSet,
+        TileType,
+    },
+    super::grid::{
+        Gravity,
+        Grid,
+    },
+};
+
+const RANDOM_PLACEMENT_ATTEMPTS: usize = 50;
+const MAX_DEALS: usize = 100;
+
+/// The pairs that clear a deal, in the order they are removed.
+type Solution = Vec<(Position, Position)>;
+
+/// Deals the tile set onto a grid of the given size. The deal always has a solution.
+pub fn deal<R: Rng>(
+    tile_set: &TileSet,
+    width: u8,
+    height: u8,
+    gravity: Gravity,
+    rng: &mut R,
+) -> Result<Grid, &'static str> {
+    Ok(deal_with_solution(tile_set, width, height, gravity, rng)?.0)
+}
+
+/**
+ * Without gravity the solution is found first: pairs of connected cells are cleared from a full
+ * grid until it is empty, after which each cleared pair is given a matching pair of tiles.
+ *
+ * With gravity the deal is built backwards from an empty grid: every pair is inserted into its
+ * lines so that it can be removed right away, pushing the tiles already there away from where
+ * tiles move to. Removing the pairs in reverse order lets the tiles fall back to where they were
+ * and clears the grid.
+ *
+ * Both are random and are tried again when they run into a dead end, up to a number of times.
+ */
+fn deal_with_solution<R: Rng>(
+    tile_set: &TileSet,
+    width: u8,
+    height: u8,
+    gravity: Gravity,
+    rng: &mut R,
+) -> Result<(Grid, Solution), &'static str> {
+    if tile_set.len() != usize::from(width) * usize::from(height) {
+        return Err("tile set size does not match grid size");
+    }
+
+    for _ in 0..MAX_DEALS {
+        let result = match gravity {
+            Gravity::None => try_clear(tile_set, width, height, rng),
+            _ => try_deal(tile_set, width, height, gravity, rng),
+        };
+        if let Some(result) = result {
+            return Ok(result);
+        }
+    }
+
+    Err("no deal found for the grid")
+}
+
+fn try_clear<R: Rng>(
+    tile_set: &TileSet,
+    width: u8,
+    height: u8,
+    rng: &mut R,
+) -> Option<(Grid, Solution)> {
+    let mut types = tile_set.types();
+    let mut grid = Grid::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            grid.set(Position { x, y, z: 0 }, Some(types[0]));
+        }
+    }
+
+    let mut solution = Vec::new();
+    while !grid.is_empty() {
+        let (tile1, tile2) = random_connected_pair(&grid, rng)?;
+        grid.set(tile1, None);
+        grid.set(tile2, None);
+        solution.push((tile1, tile2));
+    }
+
+    for &(tile1, tile2) in &solution {
+        let random_index = rng.gen_range(0, types.len() / 2) * 2;
+        grid.set(tile1, Some(types.swap_remove(random_index + 1)));
+        grid.set(tile2, Some(types.swap_remove(random_index)));
+    }
+
+    Some((grid, solution))
+}
+
+fn try_deal<R: Rng>(
+    tile_set: &TileSet,
+    width: u8,
+    height: u8,
+    gravity: Gravity,
+    rng: &mut R,
+) -> Option<(Grid, Solution)> {
+    let mut grid = Grid::new(width, height);
+    let mut types = tile_set.types();
+    let mut placed = Vec::new();
+
+    while !types.is_empty() {
+        let random_index = rng.gen_range(0, types.len() / 2) * 2;
+        let type1 = types.swap_remove(random_index + 1);
+        let type2 = types.swap_remove(random_index);
+
+        placed.push(insert_pair(&mut grid, type1, type2, gravity, rng)?);
+    }
+
+    placed.reverse();
+    Some((grid, placed))
+}
+
+fn random_connected_pair<R: Rng>(grid: &Grid, rng: &mut R) -> Option<(Position, Position)> {
+    let mut tiles = grid.tiles();
+    tiles.shuffle(rng);
+
+    for (index, &tile1) in tiles.iter().enumerate() {
+        for &tile2 in &tiles[index + 1..] {
+            if grid.find_path(tile1, tile2).is_some() {
+                return Some((tile1, tile2));
+            }
+        }
+    }
+
+    None
+}
+
+fn insert_pair<R: Rng>(
+    grid: &mut Grid,
+    type1: TileType,
+    type2: TileType,
+    gravity: Gravity,
+    rng: &mut R,
+) -> Option<(Position, Position)> {
+    let try_insert = |grid: &mut Grid, first: (u8, u8), second: (u8, u8)| {
+        insert(grid, gravity, first, type1)?;
+        insert(grid, gravity, second, type2)?;
+
+        let mut first = first;
+        if first.0 == second.0 && second.1 <= first.1 {
+            first.1 += 1;
+        }
+
+        let tile1 = grid.line_position(gravity, first.0, first.1);
+        let tile2 = grid.line_position(gravity, second.0, second.1);
+        grid.find_path(tile1, tile2).map(|_| (tile1, tile2))
+    };
+
+    for _ in 0..RANDOM_PLACEMENT_ATTEMPTS {
+        let first = random_insertion_point(grid, gravity, rng)?;
+        let second = random_insertion_point(grid, gravity, rng)?;
+        let mut attempt = grid.clone();
+        if let Some(pair) = try_insert(&mut attempt, first, second) {
+            *grid = attempt;
+            return Some(pair);
+        }
+    }
+
+    for first in insertion_points(grid, gravity) {
+        let mut attempt = grid.clone();
+        if insert(&mut attempt, gravity, first, type1).is_none() {
+            continue;
+        }
+        for second in insertion_points(&attempt, gravity) {
+            let mut attempt = grid.clone();
+            if let Some(pair) = try_insert(&mut attempt, first, second) {
+                *grid = attempt;
+                return Some(pair);
+            }
+        }
+    }
+
+    None
+}
+
+/// Inserts a tile into a line at the given index, moving the tiles from that index on one step
+/// further away from the start of the line.
+fn insert(grid: &mut Grid, gravity: Gravity, (line, index): (u8, u8), tile_type: TileType) -> Option<()> {
+    let mut types = grid.line_types(gravity, line);
+    if types.len() >= usize::from(grid.line_length(gravity)) || usize::from(index) > types.len() {
+        return None;
+    }
+
+    types.insert(usize::from(index), tile_type);
+    grid.set_line(gravity, line, &types);
+    Some(())
+}
+
+fn insertion_points(grid: &Grid, gravity: Gravity) -> Vec<(u8, u8)> {
+    let mut points = Vec::new();
+    for line in 0..grid.line_count(gravity) {
+        let count = grid.line_types(gravity, line).len() as u8;
+        if count < grid.line_length(gravity) {
+            points.extend((0..=count).map(|index| (line, index)));
+        }
+    }
+    points
+}
+
+fn random_insertion_point<R: Rng>(grid: &Grid, gravity: Gravity, rng: &mut R) -> Option<(u8, u8)> {
+    let points = insertion_points(grid, gravity);
+    if points.is_empty() {
+        None
+    } else {
+        Some(points[rng.gen_range(0, points.len())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        rand::{
+            rngs::SmallRng,
+            SeedableRng,
+        },
+        crate::board::TileSet,
+        super::{
+            deal_with_solution,
+            super::grid::{
+                Gravity,
+                HEIGHT,
+                WIDTH,
+            },
+        },
+    };
+
+    const SEEDS: u64 = 100;
+
+    #[test]
+    fn deals_are_solvable() {
+        let tile_set = TileSet::standard();
+
+        for &gravity in &[Gravity::None, Gravity::Down, Gravity::Left] {
+            for seed in 0..SEEDS {
+                let mut rng = SmallRng::seed_from_u64(seed);
+                let (mut grid, solution) =
+                    deal_with_solution(&tile_set, WIDTH, HEIGHT, gravity, &mut rng).unwrap();
+
+                assert_eq!(grid.tiles().len(), 144);
+
+                for &(tile1, tile2) in &solution {
+                    let context = format!("{:?} seed {}", gravity, seed);
+                    assert!(grid.can_remove(&tile_set, tile1, tile2), "{}", context);
+                    grid.remove_pair(tile1, tile2, gravity);
+                }
+
+                assert!(grid.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn deals_are_reproducible() {
+        let tile_set = TileSet::children();
+
+        let mut rng1 = SmallRng::seed_from_u64(7);
+        let mut rng2 = SmallRng::seed_from_u64(7);
+
+        assert_eq!(
+            deal_with_solution(&tile_set, WIDTH, HEIGHT, Gravity::Down, &mut rng1),
+            deal_with_solution(&tile_set, WIDTH, HEIGHT, Gravity::Down, &mut rng2),
+        );
+    }
+
+    #[test]
+    fn tile_sets_that_do_not_fill_the_grid_are_not_dealt() {
+        let tile_set = TileSet::standard();
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        let result = deal_with_solution(&tile_set, WIDTH, HEIGHT - 1, Gravity::None, &mut rng);
+        assert!(result.is_err());
+    }
+}
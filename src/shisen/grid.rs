@@ -0,0 +1,385 @@
+use crate::board::{
+    Position,
+    TileSet,
+    TileType,
+};
+
+pub const WIDTH: u8 = 18;
+pub const HEIGHT: u8 = 8;
+
+/// A corner point of a connecting path. Points can lie one cell outside of the grid.
+pub type PathPoint = (i32, i32);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Gravity {
+    #[default]
+    None,
+    /// Tiles fall down into the space left by removed tiles.
+    Down,
+    /// Tiles slide left into the space left by removed tiles.
+    Left,
+}
+
+impl Gravity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Gravity::None => "no gravity",
+            Gravity::Down => "gravity down",
+            Gravity::Left => "gravity left",
+        }
+    }
+
+    pub fn next(self) -> Gravity {
+        match self {
+            Gravity::None => Gravity::Down,
+            Gravity::Down => Gravity::Left,
+            Gravity::Left => Gravity::None,
+        }
+    }
+}
+
+/// A flat grid of tiles. Two matching tiles can be removed when a path with at most two turns
+/// connects them through empty cells, where the path may run along the outside of the grid.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    width: u8,
+    height: u8,
+    cells: Vec<Option<TileType>>,
+}
+
+impl Grid {
+    pub fn new(width: u8, height: u8) -> Self {
+        Grid {
+            width,
+            height,
+            cells: vec![None; usize::from(width) * usize::from(height)],
+        }
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    pub fn get(&self, position: Position) -> Option<TileType> {
+        self.cells[self.index(position)]
+    }
+
+    pub fn set(&mut self, position: Position, tile_type: Option<TileType>) {
+        let index = self.index(position);
+        self.cells[index] = tile_type;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().all(Option::is_none)
+    }
+
+    /// Returns the positions of all tiles on the grid.
+    pub fn tiles(&self) -> Vec<Position> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| Position { x, y, z: 0 }))
+            .filter(|&position| self.get(position).is_some())
+            .collect()
+    }
+
+    /// Returns the corner points of the shortest path with at most two turns between the two
+    /// positions, including both end points.
+    pub fn find_path(&self, from: Position, to: Position) -> Option<Vec<PathPoint>> {
+        if from == to {
+            return None;
+        }
+
+        let a = (i32::from(from.x), i32::from(from.y));
+        let b = (i32::from(to.x), i32::from(to.y));
+
+        let mut paths = Vec::new();
+
+        if self.is_line_clear(a, b) {
+            paths.push(vec![a, b]);
+        }
+
+        for &corner in &[(a.0, b.1), (b.0, a.1)] {
+            if self.is_open(corner) && self.is_line_clear(a, corner) && self.is_line_clear(corner, b) {
+                paths.push(vec![a, corner, b]);
+            }
+        }
+
+        for x in -1..=i32::from(self.width) {
+            paths.extend(self.two_turn_path(a, (x, a.1), (x, b.1), b));
+        }
+        for y in -1..=i32::from(self.height) {
+            paths.extend(self.two_turn_path(a, (a.0, y), (b.0, y), b));
+        }
+
+        paths.into_iter().min_by_key(|path| path_length(path))
+    }
+
+    /// Returns every pair of matching tiles that can currently be removed.
+    pub fn available_pairs(&self, tile_set: &TileSet) -> Vec<(Position, Position)> {
+        let tiles = self.tiles();
+        let mut pairs = Vec::new();
+
+        for (index, &tile1) in tiles.iter().enumerate() {
+            for &tile2 in &tiles[index + 1..] {
+                if self.can_remove(tile_set, tile1, tile2) {
+                    pairs.push((tile1, tile2));
+                }
+            }
+        }
+
+        pairs
+    }
+
+    pub fn can_remove(&self, tile_set: &TileSet, tile1: Position, tile2: Position) -> bool {
+        match (self.get(tile1), self.get(tile2)) {
+            (Some(type1), Some(type2)) => {
+                tile_set.matches(type1, type2) && self.find_path(tile1, tile2).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    pub fn remove_pair(&mut self, tile1: Position, tile2: Position, gravity: Gravity) {
+        self.set(tile1, None);
+        self.set(tile2, None);
+        self.apply_gravity(gravity);
+    }
+
+    pub fn apply_gravity(&mut self, gravity: Gravity) {
+        if gravity == Gravity::None {
+            return;
+        }
+
+        for line in 0..self.line_count(gravity) {
+            let types = self.line_types(gravity, line);
+            self.set_line(gravity, line, &types);
+        }
+    }
+
+    /// The number of lines tiles move along for the given gravity.
+    pub fn line_count(&self, gravity: Gravity) -> u8 {
+        match gravity {
+            Gravity::None | Gravity::Down => self.width,
+            Gravity::Left => self.height,
+        }
+    }
+
+    /// The number of cells of each line tiles move along for the given gravity.
+    pub fn line_length(&self, gravity: Gravity) -> u8 {
+        match gravity {
+            Gravity::None | Gravity::Down => self.height,
+            Gravity::Left => self.width,
+        }
+    }
+
+    /// Returns the position of a cell in a line, where index zero is the cell tiles move
+    /// towards.
+    pub fn line_position(&self, gravity: Gravity, line: u8, index: u8) -> Position {
+        match gravity {
+            Gravity::None | Gravity::Down => Position { x: line, y: self.height - 1 - index, z: 0 },
+            Gravity::Left => Position { x: index, y: line, z: 0 },
+        }
+    }
+
+    /// Returns the tiles of a line in order, starting at the cell tiles move towards.
+    pub fn line_types(&self, gravity: Gravity, line: u8) -> Vec<TileType> {
+        (0..self.line_length(gravity))
+            .filter_map(|index| self.get(self.line_position(gravity, line, index)))
+            .collect()
+    }
+
+    /// Replaces a line with the given tiles, packed towards the start of the line.
+    pub fn set_line(&mut self, gravity: Gravity, line: u8, types: &[TileType]) {
+        for index in 0..self.line_length(gravity) {
+            let position = self.line_position(gravity, line, index);
+            self.set(position, types.get(usize::from(index)).cloned());
+        }
+    }
+
+    fn two_turn_path(
+        &self,
+        a: PathPoint,
+        corner1: PathPoint,
+        corner2: PathPoint,
+        b: PathPoint,
+    ) -> Option<Vec<PathPoint>> {
+        if corner1 == a || corner2 == b {
+            return None;
+        }
+
+        let clear = self.is_open(corner1)
+            && self.is_open(corner2)
+            && self.is_line_clear(a, corner1)
+            && self.is_line_clear(corner1, corner2)
+            && self.is_line_clear(corner2, b);
+
+        if clear {
+            Some(vec![a, corner1, corner2, b])
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether all cells strictly between two points on a straight line are open.
+    fn is_line_clear(&self, from: PathPoint, to: PathPoint) -> bool {
+        if from.0 != to.0 && from.1 != to.1 {
+            return false;
+        }
+
+        let step = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+        let mut point = (from.0 + step.0, from.1 + step.1);
+
+        while point != to {
+            if !self.is_open(point) {
+                return false;
+            }
+            point = (point.0 + step.0, point.1 + step.1);
+        }
+        true
+    }
+
+    fn is_open(&self, (x, y): PathPoint) -> bool {
+        if x < 0 || y < 0 || x >= i32::from(self.width) || y >= i32::from(self.height) {
+            return true;
+        }
+        self.cells[y as usize * usize::from(self.width) + x as usize].is_none()
+    }
+
+    fn index(&self, position: Position) -> usize {
+        usize::from(position.y) * usize::from(self.width) + usize::from(position.x)
+    }
+}
+
+fn path_length(path: &[PathPoint]) -> i32 {
+    path.windows(2)
+        .map(|line| (line[0].0 - line[1].0).abs() + (line[0].1 - line[1].1).abs())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        crate::board::{
+            Position,
+            TileSet,
+            TileType::*,
+        },
+        super::{
+            Gravity,
+            Grid,
+        },
+    };
+
+    fn pos(x: u8, y: u8) -> Position {
+        Position { x, y, z: 0 }
+    }
+
+    fn fill(grid: &mut Grid) {
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                grid.set(pos(x, y), Some(BallOne));
+            }
+        }
+    }
+
+    #[test]
+    fn connects_neighbouring_tiles() {
+        let mut grid = Grid::new(4, 4);
+        fill(&mut grid);
+
+        assert_eq!(grid.find_path(pos(1, 1), pos(2, 1)), Some(vec![(1, 1), (2, 1)]));
+        assert_eq!(grid.find_path(pos(1, 1), pos(1, 2)), Some(vec![(1, 1), (1, 2)]));
+    }
+
+    #[test]
+    fn does_not_connect_enclosed_tiles() {
+        let mut grid = Grid::new(4, 4);
+        fill(&mut grid);
+
+        assert_eq!(grid.find_path(pos(1, 1), pos(2, 2)), None);
+        assert_eq!(grid.find_path(pos(1, 1), pos(1, 1)), None);
+    }
+
+    #[test]
+    fn connects_with_one_turn() {
+        let mut grid = Grid::new(3, 3);
+        fill(&mut grid);
+        grid.set(pos(1, 0), None);
+
+        assert_eq!(grid.find_path(pos(0, 0), pos(1, 1)), Some(vec![(0, 0), (1, 0), (1, 1)]));
+    }
+
+    #[test]
+    fn connects_with_two_turns_around_the_outside() {
+        let mut grid = Grid::new(4, 3);
+        fill(&mut grid);
+
+        let path = grid.find_path(pos(0, 0), pos(3, 0));
+        assert_eq!(path, Some(vec![(0, 0), (0, -1), (3, -1), (3, 0)]));
+    }
+
+    #[test]
+    fn does_not_connect_with_three_turns() {
+        let mut grid = Grid::new(5, 5);
+        fill(&mut grid);
+        // an open corridor between (0, 1) and (3, 4) that needs three turns
+        for &(x, y) in &[(1, 1), (1, 2), (2, 2), (3, 2), (3, 3)] {
+            grid.set(pos(x, y), None);
+        }
+
+        assert_eq!(grid.find_path(pos(0, 1), pos(3, 4)), None);
+
+        grid.set(pos(2, 1), None);
+        grid.set(pos(3, 1), None);
+        assert_eq!(
+            grid.find_path(pos(0, 1), pos(3, 4)),
+            Some(vec![(0, 1), (3, 1), (3, 4)]),
+        );
+    }
+
+    #[test]
+    fn finds_removable_pairs() {
+        let mut grid = Grid::new(3, 1);
+        grid.set(pos(0, 0), Some(BallOne));
+        grid.set(pos(1, 0), Some(BallTwo));
+        grid.set(pos(2, 0), Some(BallOne));
+        let tile_set = TileSet::standard();
+
+        assert_eq!(grid.available_pairs(&tile_set), vec![(pos(0, 0), pos(2, 0))]);
+        assert!(!grid.can_remove(&tile_set, pos(0, 0), pos(1, 0)));
+    }
+
+    #[test]
+    fn gravity_down_drops_tiles() {
+        let mut grid = Grid::new(2, 3);
+        grid.set(pos(0, 0), Some(BallOne));
+        grid.set(pos(0, 1), Some(BallTwo));
+        grid.set(pos(0, 2), Some(BallThree));
+        grid.set(pos(1, 2), Some(BallFour));
+
+        grid.remove_pair(pos(0, 2), pos(1, 2), Gravity::Down);
+
+        assert_eq!(grid.get(pos(0, 0)), None);
+        assert_eq!(grid.get(pos(0, 1)), Some(BallOne));
+        assert_eq!(grid.get(pos(0, 2)), Some(BallTwo));
+        assert_eq!(grid.get(pos(1, 2)), None);
+    }
+
+    #[test]
+    fn gravity_left_slides_tiles() {
+        let mut grid = Grid::new(3, 1);
+        grid.set(pos(0, 0), Some(BallOne));
+        grid.set(pos(1, 0), Some(BallTwo));
+        grid.set(pos(2, 0), Some(BallThree));
+
+        grid.set(pos(0, 0), None);
+        grid.apply_gravity(Gravity::Left);
+
+        assert_eq!(grid.get(pos(0, 0)), Some(BallTwo));
+        assert_eq!(grid.get(pos(1, 0)), Some(BallThree));
+        assert_eq!(grid.get(pos(2, 0)), None);
+    }
+}
@@ -1,12 +1,10 @@
 use {
-    std::path::Path,
     sdl2::{
         event::Event::{
             self,
             MouseButtonDown,
             MouseButtonUp,
         },
-        image::LoadTexture,
         mouse::MouseButton,
         rect::Rect,
         render::{
@@ -16,6 +14,10 @@ use {
         },
         video::WindowContext,
     },
+    crate::{
+        assets,
+        game::Scale,
+    },
 };
 
 pub struct UiContext<'tc> {
@@ -26,25 +28,30 @@ impl<'tc> UiContext<'tc> {
     pub fn new(texture_creator: &'tc TextureCreator<WindowContext>) -> Self {
         use self::Action::*;
         
-        let start_button_texture = texture_creator
-            .load_texture(Path::new("img/start.png"))
+        let start_button_texture = assets::load_texture(texture_creator, assets::START_BUTTON)
             .unwrap();
-        let undo_button_texture = texture_creator
-            .load_texture(Path::new("img/undo.png"))
+        let undo_button_texture = assets::load_texture(texture_creator, assets::UNDO_BUTTON)
             .unwrap();
-        let hint_button_texture = texture_creator
-            .load_texture(Path::new("img/hint.png"))
+        let hint_button_texture = assets::load_texture(texture_creator, assets::HINT_BUTTON)
             .unwrap();
 
-        let start_button = Button::new(10, 10, 120, 50, Start, start_button_texture);
-        let undo_button = Button::new(10, 70, 120, 50, Undo, undo_button_texture);
-        let hint_button = Button::new(10, 130, 120, 50, Hint, hint_button_texture);
+        let start_button = Button::new(5, 5, 60, 25, Start, start_button_texture);
+        let undo_button = Button::new(5, 35, 60, 25, Undo, undo_button_texture);
+        let hint_button = Button::new(5, 65, 60, 25, Hint, hint_button_texture);
 
         UiContext {
             buttons: [start_button, undo_button, hint_button],
         }
     }
 
+    /// Sizes the buttons for a canvas of the given size.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let scale = Scale::of_canvas(width, height);
+        for button in &mut self.buttons {
+            button.placement = scale.rect(button.design);
+        }
+    }
+
     pub fn handle_event(&mut self, event: &Event) -> Option<Action> {
         match *event {
             MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
@@ -79,6 +86,8 @@ pub enum Action {
 }
 
 struct Button<'tc> {
+    /// Where the button is on the smallest canvas.
+    design: Rect,
     placement: Rect,
     texture: Texture<'tc>,
     action: Action,
@@ -88,7 +97,8 @@ struct Button<'tc> {
 impl Button<'_> {
     fn new(x: i32, y: i32, width: u32, height: u32, action: Action, texture: Texture) -> Button {
         Button {
-            placement: Rect::new(x / 2, y / 2, width / 2, height / 2),
+            design: Rect::new(x, y, width, height),
+            placement: Rect::new(x, y, width, height),
             texture,
             action,
             pressed: false,